@@ -0,0 +1,108 @@
+//! Audit a deployment of the staking program.
+//!
+//! Fetches all program accounts and the reward pool balance, then checks the
+//! stake bookkeeping and the yield owed against the pool. Exits with status 2
+//! if any discrepancy is found.
+
+use std::{error::Error, process::exit, str::FromStr};
+
+use clap::{App, Arg};
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::{commitment_config::CommitmentConfig, pubkey::Pubkey};
+use staking::{
+    audit::audit,
+    client::{Addresses, ProgramState},
+};
+
+fn run(url: &str, program_id: Pubkey, time: Option<i64>) -> Result<bool, Box<dyn Error>> {
+    let rpc = RpcClient::new_with_commitment(url, CommitmentConfig::confirmed());
+    let addresses = Addresses::new(program_id);
+
+    let accounts = rpc.get_program_accounts(&program_id)?;
+    let state = ProgramState::from_accounts(
+        &program_id,
+        accounts
+            .into_iter()
+            .map(|(key, account)| (key, account.data)),
+    );
+    let pool_balance: u64 = rpc
+        .get_token_account_balance(&addresses.reward_pool())?
+        .amount
+        .parse()?;
+    let now = match time {
+        Some(time) => time,
+        None => rpc.get_block_time(rpc.get_slot()?)?,
+    };
+
+    let report = audit(&state, pool_balance, now);
+
+    println!("                Time: {}", report.now);
+    println!("           Endpoints: {}", state.endpoints.len());
+    println!("              Stakes: {}", state.stakes.len());
+    println!("       Beneficiaries: {}", state.beneficiaries.len());
+    println!("         Total Stake: {}", report.total_stake);
+    println!();
+    println!(" Reward Pool Balance: {}", report.pool_balance);
+    println!("        Total Funded: {}", report.total_funded);
+    println!("           Liability: {}", report.liability);
+    println!("  Settings Liability: {}", report.settings_liability);
+    println!(
+        "             Surplus: {}",
+        report.pool_balance as i128 - report.liability as i128
+    );
+    println!();
+
+    if report.is_consistent() {
+        println!("no discrepancies found");
+    } else {
+        println!("{} discrepancies found:", report.discrepancies.len());
+        for discrepancy in &report.discrepancies {
+            println!("  {}", discrepancy);
+        }
+    }
+
+    Ok(report.is_consistent())
+}
+
+fn main() {
+    let matches = App::new("staking-audit")
+        .about("Audit the reward pool and beneficiaries of the staking program")
+        .arg(
+            Arg::with_name("program-id")
+                .long("program-id")
+                .value_name("PUBKEY")
+                .required(true)
+                .help("The staking program"),
+        )
+        .arg(
+            Arg::with_name("url")
+                .long("url")
+                .short("u")
+                .value_name("URL")
+                .required(true)
+                .help("The RPC endpoint"),
+        )
+        .arg(
+            Arg::with_name("time")
+                .long("time")
+                .value_name("UNIX_TIMESTAMP")
+                .help("Bring the rewards up to this time [default: the latest block time]"),
+        )
+        .get_matches();
+
+    let result = Pubkey::from_str(matches.value_of("program-id").unwrap())
+        .map_err(|err| err.into())
+        .and_then(|program_id| {
+            let time = matches.value_of("time").map(i64::from_str).transpose()?;
+            run(matches.value_of("url").unwrap(), program_id, time)
+        });
+
+    match result {
+        Ok(true) => {}
+        Ok(false) => exit(2),
+        Err(err) => {
+            eprintln!("error: {}", err);
+            exit(1);
+        }
+    }
+}
@@ -0,0 +1,540 @@
+//! Command-line tool for the staking program.
+//!
+//! Every command builds a transaction signed by `--keypair`. With `--blockhash`
+//! the transaction is signed offline and printed as base64. Otherwise the
+//! blockhash is fetched from `--url` and the transaction is submitted.
+//!
+//! Accounts that the program needs but that are not given on the command line
+//! (an Endpoint's beneficiaries, the token mint) are looked up through `--url`.
+//! So is whether the Settings reference an emission schedule; without `--url`
+//! commands that update the rewards need `--emission-schedule` or
+//! `--no-emission-schedule`.
+
+use std::{error::Error, process::exit, str::FromStr};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::{
+    commitment_config::CommitmentConfig,
+    hash::Hash,
+    instruction::Instruction,
+    pubkey::Pubkey,
+    signature::{read_keypair_file, write_keypair_file, Keypair, Signer},
+    transaction::Transaction,
+};
+use staking::{
+    account::{Authority, Endpoint, Settings, Split, Versioned},
+    client::{Addresses, ProgramAccount},
+    instruction, ZERO_KEY,
+};
+
+type Result<T> = std::result::Result<T, Box<dyn Error>>;
+
+struct Context {
+    program_id: Pubkey,
+    keypair: Keypair,
+    rpc: Option<RpcClient>,
+    blockhash: Option<Hash>,
+    /// Whether to include the emission schedule, `None` to look it up
+    emission_schedule: Option<bool>,
+}
+
+impl Context {
+    fn from_matches(matches: &ArgMatches) -> Result<Context> {
+        let keypair = read_keypair_file(matches.value_of("keypair").unwrap())
+            .map_err(|err| format!("unable to read keypair: {}", err))?;
+        Ok(Context {
+            program_id: pubkey(matches, "program-id")?,
+            keypair,
+            rpc: matches
+                .value_of("url")
+                .map(|url| RpcClient::new_with_commitment(url, CommitmentConfig::confirmed())),
+            blockhash: matches
+                .value_of("blockhash")
+                .map(Hash::from_str)
+                .transpose()?,
+            emission_schedule: match (
+                matches.is_present("emission-schedule"),
+                matches.is_present("no-emission-schedule"),
+            ) {
+                (true, _) => Some(true),
+                (_, true) => Some(false),
+                _ => None,
+            },
+        })
+    }
+
+    fn payer(&self) -> Pubkey {
+        self.keypair.pubkey()
+    }
+
+    fn rpc(&self) -> Result<&RpcClient> {
+        self.rpc
+            .as_ref()
+            .ok_or_else(|| "this command needs --url to look up accounts".into())
+    }
+
+    fn account<T: Versioned>(&self, address: &Pubkey) -> Result<T> {
+        let data = self.rpc()?.get_account_data(address)?;
+        Ok(T::from_account_data(&data)?)
+    }
+
+    fn settings(&self) -> Result<Settings> {
+        self.account(&Addresses::new(self.program_id).settings())
+    }
+
+    /// Add the Emission Schedule to instructions that update the rewards, if
+    /// Settings references one. Signing offline without `--url`, the choice has
+    /// to be explicit, since a wrong guess makes the transaction fail.
+    fn update_rewards(&self, instruction: Instruction) -> Result<Instruction> {
+        let required = match (self.emission_schedule, &self.rpc) {
+            (Some(required), _) => required,
+            (None, Some(_)) => self.settings()?.emission_schedule != ZERO_KEY,
+            (None, None) => {
+                return Err(
+                    "without --url, pass --emission-schedule or --no-emission-schedule".into(),
+                )
+            }
+        };
+        Ok(match required {
+            true => instruction::with_emission_schedule(instruction),
+            false => instruction,
+        })
+    }
+
+    /// The primary and secondary beneficiary of an Endpoint, either from the
+    /// command line or from the Endpoint account
+    fn beneficiaries(&self, matches: &ArgMatches, endpoint: &Pubkey) -> Result<(Pubkey, Pubkey)> {
+        match (
+            optional_pubkey(matches, "primary")?,
+            optional_pubkey(matches, "secondary")?,
+        ) {
+            (Some(primary), Some(secondary)) => Ok((primary, secondary)),
+            _ => {
+                let endpoint: Endpoint = self.account(endpoint)?;
+                Ok((endpoint.primary, endpoint.secondary))
+            }
+        }
+    }
+
+    fn mint(&self, matches: &ArgMatches) -> Result<Pubkey> {
+        match optional_pubkey(matches, "mint")? {
+            Some(mint) => Ok(mint),
+            None => Ok(self.settings()?.token),
+        }
+    }
+
+    /// Sign the instructions and either print or submit the transaction
+    fn send(&self, instructions: &[Instruction], extra_signers: &[&Keypair]) -> Result<()> {
+        let blockhash = match self.blockhash {
+            Some(blockhash) => blockhash,
+            None => self.rpc()?.get_latest_blockhash()?,
+        };
+
+        let mut signers: Vec<&Keypair> = vec![&self.keypair];
+        signers.extend(extra_signers);
+        let tx = Transaction::new_signed_with_payer(
+            instructions,
+            Some(&self.payer()),
+            &signers,
+            blockhash,
+        );
+
+        if self.blockhash.is_some() {
+            println!("{}", STANDARD.encode(bincode::serialize(&tx)?));
+        } else {
+            let signature = self.rpc()?.send_and_confirm_transaction(&tx)?;
+            println!("Signature: {}", signature);
+        }
+        Ok(())
+    }
+}
+
+fn pubkey(matches: &ArgMatches, name: &str) -> Result<Pubkey> {
+    let value = matches
+        .value_of(name)
+        .ok_or_else(|| format!("missing argument {}", name))?;
+    Pubkey::from_str(value).map_err(|err| format!("invalid pubkey for {}: {}", name, err).into())
+}
+
+fn optional_pubkey(matches: &ArgMatches, name: &str) -> Result<Option<Pubkey>> {
+    match matches.value_of(name) {
+        Some(_) => pubkey(matches, name).map(Some),
+        None => Ok(None),
+    }
+}
+
+fn value<T: FromStr>(matches: &ArgMatches, name: &str) -> Result<T>
+where
+    T::Err: Error + 'static,
+{
+    Ok(matches.value_of(name).unwrap().parse()?)
+}
+
+/// The owner account and signer of an Endpoint. For an NFT owner the owner
+/// account is the token account holding the NFT.
+fn owner_accounts(ctx: &Context, matches: &ArgMatches) -> Result<(Pubkey, Pubkey)> {
+    let owner = optional_pubkey(matches, "owner-token-account")?.unwrap_or_else(|| ctx.payer());
+    Ok((owner, ctx.payer()))
+}
+
+fn authority(matches: &ArgMatches, name: &str, default: Pubkey) -> Result<Authority> {
+    Ok(match optional_pubkey(matches, name)? {
+        Some(address) if matches.is_present("nft") => Authority::NFT(address),
+        Some(address) => Authority::Basic(address),
+        None => Authority::Basic(default),
+    })
+}
+
+fn split(matches: &ArgMatches) -> Result<Split> {
+    match matches.value_of("split") {
+        None => Ok(Split::DEFAULT),
+        Some(value) => {
+            let parts = value
+                .split('/')
+                .map(u16::from_str)
+                .collect::<std::result::Result<Vec<_>, _>>()?;
+            match parts[..] {
+                [staker, primary, secondary] => Ok(Split {
+                    staker,
+                    primary,
+                    secondary,
+                }),
+                _ => Err("split has to be <staker>/<primary>/<secondary> in basis points".into()),
+            }
+        }
+    }
+}
+
+/// The keypair of a new Endpoint. A generated keypair is written to disk
+/// first, so the Endpoint can still be registered if sending fails.
+fn endpoint_keypair(matches: &ArgMatches) -> Result<Keypair> {
+    match matches.value_of("endpoint-keypair") {
+        Some(path) => read_keypair_file(path)
+            .map_err(|err| format!("unable to read endpoint keypair: {}", err).into()),
+        None => {
+            let keypair = Keypair::new();
+            let path = format!("endpoint-{}.json", keypair.pubkey());
+            write_keypair_file(&keypair, &path)
+                .map_err(|err| format!("unable to write endpoint keypair: {}", err))?;
+            println!("Wrote the Endpoint keypair to {}", path);
+            Ok(keypair)
+        }
+    }
+}
+
+fn run(matches: &ArgMatches) -> Result<()> {
+    let ctx = Context::from_matches(matches)?;
+    let payer = ctx.payer();
+    let program_id = ctx.program_id;
+
+    match matches.subcommand() {
+        ("initialize", Some(m)) => ctx.send(
+            &[instruction::initialize(
+                &program_id,
+                &payer,
+                &pubkey(m, "mint")?,
+                authority(m, "admin", payer)?,
+                &payer,
+                value(m, "start-time")?,
+                value(m, "unbonding-duration")?,
+            )],
+            &[],
+        ),
+        ("register-endpoint", Some(m)) => {
+            let endpoint = endpoint_keypair(m)?;
+            println!("Endpoint: {}", endpoint.pubkey());
+            ctx.send(
+                &[instruction::register_endpoint(
+                    &program_id,
+                    &payer,
+                    &endpoint.pubkey(),
+                    authority(m, "owner", payer)?,
+                    &optional_pubkey(m, "primary")?.unwrap_or(payer),
+                    &optional_pubkey(m, "secondary")?.unwrap_or(ZERO_KEY),
+                    split(m)?,
+                )],
+                &[&endpoint],
+            )
+        }
+        ("initialize-stake", Some(m)) => ctx.send(
+            &[instruction::initialize_stake(
+                &program_id,
+                &payer,
+                &payer,
+                &pubkey(m, "endpoint")?,
+                &ctx.mint(m)?,
+            )],
+            &[],
+        ),
+        (command @ "stake", Some(m)) | (command @ "unstake", Some(m)) => {
+            let endpoint = pubkey(m, "endpoint")?;
+            let (primary, secondary) = ctx.beneficiaries(m, &endpoint)?;
+            let amount: i64 = value(m, "amount")?;
+            let ix = instruction::stake(
+                &program_id,
+                &payer,
+                &payer,
+                &pubkey(m, "token-account")?,
+                &endpoint,
+                &primary,
+                &secondary,
+                if command == "unstake" {
+                    -amount
+                } else {
+                    amount
+                },
+                optional_pubkey(m, "sunset-destination")?.as_ref(),
+            );
+            ctx.send(&[ctx.update_rewards(ix)?], &[])
+        }
+        ("withdraw-unbond", Some(m)) => ctx.send(
+            &[instruction::withdraw_unbond(
+                &program_id,
+                &payer,
+                &payer,
+                &pubkey(m, "token-account")?,
+                &pubkey(m, "endpoint")?,
+            )],
+            &[],
+        ),
+        ("claim", Some(m)) => {
+            let ix = instruction::claim(&program_id, &payer, &payer, &pubkey(m, "token-account")?);
+            ctx.send(&[ctx.update_rewards(ix)?], &[])
+        }
+        ("transfer-endpoint", Some(m)) => {
+            let (owner, owner_signer) = owner_accounts(&ctx, m)?;
+            ctx.send(
+                &[instruction::transfer_endpoint(
+                    &program_id,
+                    &payer,
+                    &pubkey(m, "endpoint")?,
+                    &owner,
+                    &owner_signer,
+                    authority(m, "new-owner", payer)?,
+                )],
+                &[],
+            )
+        }
+        ("change-beneficiaries", Some(m)) => {
+            let endpoint = pubkey(m, "endpoint")?;
+            let (owner, owner_signer) = owner_accounts(&ctx, m)?;
+            let (old_primary, old_secondary) = ctx.beneficiaries(m, &endpoint)?;
+            let ix = instruction::change_beneficiaries(
+                &program_id,
+                &payer,
+                &endpoint,
+                &owner,
+                &owner_signer,
+                &old_primary,
+                &old_secondary,
+                &pubkey(m, "new-primary")?,
+                &optional_pubkey(m, "new-secondary")?.unwrap_or(ZERO_KEY),
+            );
+            ctx.send(&[ctx.update_rewards(ix)?], &[])
+        }
+        ("show", Some(m)) => {
+            let address = pubkey(m, "address")?;
+            let data = ctx.rpc()?.get_account_data(&address)?;
+            println!("{:#?}", ProgramAccount::decode(&data)?);
+            Ok(())
+        }
+        _ => unreachable!(),
+    }
+}
+
+fn main() {
+    let pubkey_arg = |name: &'static str, help: &'static str| {
+        Arg::with_name(name)
+            .long(name)
+            .value_name("PUBKEY")
+            .takes_value(true)
+            .help(help)
+    };
+    let endpoint = || pubkey_arg("endpoint", "The Endpoint account").required(true);
+    let token_account = || pubkey_arg("token-account", "Your ZEE token account").required(true);
+    let beneficiaries = || {
+        vec![
+            pubkey_arg(
+                "primary",
+                "The Endpoint's primary beneficiary [default: from --url]",
+            ),
+            pubkey_arg(
+                "secondary",
+                "The Endpoint's secondary beneficiary [default: from --url]",
+            ),
+        ]
+    };
+    let owner_token_account = || {
+        pubkey_arg(
+            "owner-token-account",
+            "The token account holding the Endpoint's NFT, if it has an NFT owner",
+        )
+    };
+    let nft = || {
+        Arg::with_name("nft")
+            .long("nft")
+            .help("The new authority is an NFT mint")
+    };
+    let amount = || {
+        Arg::with_name("amount")
+            .value_name("AMOUNT")
+            .required(true)
+            .help("The amount of ZEE in base units")
+    };
+    let default_keypair = std::env::var("HOME")
+        .map(|home| format!("{}/.config/solana/id.json", home))
+        .unwrap_or_else(|_| "id.json".to_string());
+
+    let matches = App::new("staking-cli")
+        .about("Build, sign and submit staking program transactions")
+        .setting(AppSettings::SubcommandRequiredElseHelp)
+        .arg(pubkey_arg("program-id", "The staking program").required(true))
+        .arg(
+            Arg::with_name("keypair")
+                .long("keypair")
+                .short("k")
+                .value_name("PATH")
+                .default_value(&default_keypair)
+                .help("The keypair that pays for and signs the transaction"),
+        )
+        .arg(
+            Arg::with_name("url")
+                .long("url")
+                .short("u")
+                .value_name("URL")
+                .help("The RPC endpoint used to look up accounts and submit transactions"),
+        )
+        .arg(
+            Arg::with_name("blockhash")
+                .long("blockhash")
+                .value_name("HASH")
+                .help("Sign offline with this blockhash and print the transaction instead of submitting it"),
+        )
+        .arg(
+            Arg::with_name("emission-schedule")
+                .long("emission-schedule")
+                .help("Include the emission schedule account [default: from --url]"),
+        )
+        .arg(
+            Arg::with_name("no-emission-schedule")
+                .long("no-emission-schedule")
+                .conflicts_with("emission-schedule")
+                .help("Do not include the emission schedule account [default: from --url]"),
+        )
+        .subcommand(
+            SubCommand::with_name("initialize")
+                .about("Initialize the program, the keypair has to be its upgrade authority")
+                .arg(pubkey_arg("mint", "The ZEE token mint").required(true))
+                .arg(pubkey_arg("admin", "The admin [default: keypair]"))
+                .arg(nft())
+                .arg(
+                    Arg::with_name("start-time")
+                        .long("start-time")
+                        .value_name("UNIX_TIMESTAMP")
+                        .required(true),
+                )
+                .arg(
+                    Arg::with_name("unbonding-duration")
+                        .long("unbonding-duration")
+                        .value_name("SECONDS")
+                        .required(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("register-endpoint")
+                .about("Register a new Endpoint")
+                .arg(pubkey_arg("owner", "The owner [default: keypair]"))
+                .arg(nft())
+                .arg(pubkey_arg("primary", "The primary beneficiary [default: keypair]"))
+                .arg(pubkey_arg("secondary", "The secondary beneficiary [default: none]"))
+                .arg(
+                    Arg::with_name("split")
+                        .long("split")
+                        .value_name("STAKER/PRIMARY/SECONDARY")
+                        .help("The split in basis points [default: 4750/4750/500]"),
+                )
+                .arg(
+                    Arg::with_name("endpoint-keypair")
+                        .long("endpoint-keypair")
+                        .value_name("PATH")
+                        .help(
+                            "The keypair of the new Endpoint [default: a new keypair, \
+                             written to endpoint-<ADDRESS>.json]",
+                        ),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("initialize-stake")
+                .about("Create a Stake account for the keypair")
+                .arg(endpoint())
+                .arg(pubkey_arg("mint", "The ZEE token mint [default: from --url]")),
+        )
+        .subcommand(
+            SubCommand::with_name("stake")
+                .about("Stake ZEE to an Endpoint")
+                .arg(endpoint())
+                .arg(token_account())
+                .args(&beneficiaries())
+                .arg(amount()),
+        )
+        .subcommand(
+            SubCommand::with_name("unstake")
+                .about("Unstake ZEE from an Endpoint")
+                .arg(endpoint())
+                .arg(token_account())
+                .args(&beneficiaries())
+                .arg(pubkey_arg(
+                    "sunset-destination",
+                    "The sunset destination, required once the Endpoint is sunset",
+                ))
+                .arg(amount()),
+        )
+        .subcommand(
+            SubCommand::with_name("withdraw-unbond")
+                .about("Withdraw unbonded ZEE")
+                .arg(endpoint())
+                .arg(token_account()),
+        )
+        .subcommand(
+            SubCommand::with_name("claim")
+                .about("Claim the yield of the keypair's Beneficiary")
+                .arg(token_account()),
+        )
+        .subcommand(
+            SubCommand::with_name("transfer-endpoint")
+                .about("Transfer an Endpoint to a new owner")
+                .arg(endpoint())
+                .arg(owner_token_account())
+                .arg(pubkey_arg("new-owner", "The new owner").required(true))
+                .arg(nft()),
+        )
+        .subcommand(
+            SubCommand::with_name("change-beneficiaries")
+                .about("Change the beneficiaries of an Endpoint")
+                .arg(endpoint())
+                .arg(owner_token_account())
+                .args(&beneficiaries())
+                .arg(pubkey_arg("new-primary", "The new primary beneficiary").required(true))
+                .arg(pubkey_arg(
+                    "new-secondary",
+                    "The new secondary beneficiary [default: none]",
+                )),
+        )
+        .subcommand(
+            SubCommand::with_name("show")
+                .about("Print the decoded state of a program account")
+                .arg(
+                    Arg::with_name("address")
+                        .value_name("PUBKEY")
+                        .required(true),
+                ),
+        )
+        .get_matches();
+
+    if let Err(err) = run(&matches) {
+        eprintln!("error: {}", err);
+        exit(1);
+    }
+}
@@ -1,7 +1,8 @@
 import { PublicKey } from '@solana/web3.js';
 import BN from 'bn.js';
 import * as borsh from 'borsh';
-import { PRECISION, SECONDS_PER_YEAR } from '.';
+import './extendBorsh';
+import { BASIS_POINTS, PRECISION, SECONDS_PER_YEAR } from '.';
 
 declare global {
     interface Date {
@@ -13,102 +14,418 @@ Date.prototype.getUnixTime = function (): number {
     return Math.floor(this.getTime() / 1000);
 };
 
+/** The type of data stored in an account owned by the program */
+export enum AccountType {
+    Settings,
+    Endpoint,
+    Beneficiary,
+    Stake,
+    EmissionSchedule
+}
+
+/** The first 8 bytes of `sha256("account:<type>")` */
+export const ACCOUNT_DISCRIMINATORS: Record<AccountType, Buffer> = {
+    [AccountType.Settings]: Buffer.from([
+        0xdf, 0xb3, 0xa3, 0xbe, 0xb1, 0xe0, 0x43, 0xad
+    ]),
+    [AccountType.Endpoint]: Buffer.from([
+        0xb4, 0x4c, 0x03, 0x37, 0x69, 0x87, 0x9a, 0xc6
+    ]),
+    [AccountType.Beneficiary]: Buffer.from([
+        0x2d, 0xb6, 0xe0, 0xc6, 0xc5, 0xff, 0xe9, 0x21
+    ]),
+    [AccountType.Stake]: Buffer.from([
+        0x96, 0xc5, 0xb0, 0x1d, 0x37, 0x84, 0x70, 0x95
+    ]),
+    [AccountType.EmissionSchedule]: Buffer.from([
+        0x7c, 0x31, 0xfe, 0x38, 0x23, 0x62, 0xb5, 0x58
+    ])
+};
+
+/** Identify the type of an account from the start of its data */
+export function accountType(data: Buffer): AccountType | undefined {
+    if (data.length < 8) return undefined;
+    const discriminator = data.slice(0, 8);
+    for (const [type, expected] of Object.entries(ACCOUNT_DISCRIMINATORS)) {
+        if (expected.equals(discriminator)) return Number(type);
+    }
+    return undefined;
+}
+
+/** An account class that is stored with a discriminator and version prefix */
+export interface VersionedClass<T> {
+    new (params: any): T;
+    ACCOUNT_TYPE: AccountType;
+    VERSION: number;
+}
+
+/**
+ * Decode account data that starts with a discriminator and version byte.
+ * Trailing bytes, like the unused space of a Stake account, are ignored.
+ * The address is only used for the error messages.
+ */
+export function decodeAccount<T>(
+    classType: VersionedClass<T>,
+    data: Buffer,
+    address?: PublicKey
+): T {
+    const name = `${AccountType[classType.ACCOUNT_TYPE]} account${
+        address === undefined ? '' : ` ${address.toBase58()}`
+    }`;
+
+    const found = accountType(data);
+    if (found === undefined) {
+        // accounts created before discriminators were introduced
+        throw new Error(`${name} requires migration`);
+    }
+    if (found !== classType.ACCOUNT_TYPE) {
+        throw new Error(`${name} has invalid type ${AccountType[found]}`);
+    }
+    if (data.length < 9) {
+        throw new Error(`${name} is invalid`);
+    }
+    if (data[8] !== classType.VERSION) {
+        throw new Error(`${name} requires migration`);
+    }
+
+    return borsh.deserializeUnchecked(
+        ACCOUNT_SCHEMA,
+        classType,
+        data.slice(9)
+    );
+}
+
+/** Encode account data with the discriminator and version prefix */
+export function encodeAccount<T>(
+    classType: VersionedClass<T>,
+    value: T
+): Buffer {
+    return Buffer.concat([
+        ACCOUNT_DISCRIMINATORS[classType.ACCOUNT_TYPE],
+        Buffer.from([classType.VERSION]),
+        Buffer.from(borsh.serialize(ACCOUNT_SCHEMA, value))
+    ]);
+}
+
+export enum AuthorityType {
+    Basic,
+    NFT
+}
+
+export class Authority {
+    authorityType: AuthorityType;
+    address: PublicKey;
+
+    constructor(params: { authorityType: AuthorityType; address: PublicKey }) {
+        this.authorityType = params.authorityType;
+        this.address = params.address;
+    }
+
+    static Basic(address: PublicKey): Authority {
+        return new this({ authorityType: AuthorityType.Basic, address });
+    }
+
+    static NFT(address: PublicKey): Authority {
+        return new this({ authorityType: AuthorityType.NFT, address });
+    }
+}
+
+/**
+ * The ratios in basis points at which the stake of an Endpoint is divided
+ * between the (staker, primary, secondary). The shares always add up to
+ * `BASIS_POINTS`.
+ */
+export class Split {
+    public staker: number;
+    public primary: number;
+    public secondary: number;
+
+    constructor(params: {
+        staker: number;
+        primary: number;
+        secondary: number;
+    }) {
+        this.staker = params.staker;
+        this.primary = params.primary;
+        this.secondary = params.secondary;
+    }
+
+    /** The split used before it was configurable: 47.5%, 47.5%, 5% */
+    static get DEFAULT(): Split {
+        return new Split({ staker: 4_750, primary: 4_750, secondary: 500 });
+    }
+
+    public isValid(): boolean {
+        return this.staker + this.primary + this.secondary === BASIS_POINTS;
+    }
+
+    /**
+     * Divides an amount into the (staker, primary, secondary) components.
+     * The secondary share is taken first, the primary share is taken from the
+     * rest, and remainders go to the staker.
+     */
+    public apply(amount: BN): [BN, BN, BN] {
+        const secondary = amount.muln(this.secondary).divn(BASIS_POINTS);
+        const restPoints = Math.max(BASIS_POINTS - this.secondary, 0);
+        const primary =
+            restPoints === 0
+                ? new BN(0)
+                : amount.sub(secondary).muln(this.primary).divn(restPoints);
+
+        return [amount.sub(primary).sub(secondary), primary, secondary];
+    }
+}
+
+/** The protocol-wide range of splits that Endpoints are allowed to use */
+export class SplitBounds {
+    public min: Split;
+    public max: Split;
+
+    constructor(params: { min: Split; max: Split }) {
+        this.min = params.min;
+        this.max = params.max;
+    }
+
+    /** Bounds that allow every valid split */
+    static get UNRESTRICTED(): SplitBounds {
+        return new SplitBounds({
+            min: new Split({ staker: 0, primary: 0, secondary: 0 }),
+            max: new Split({
+                staker: BASIS_POINTS,
+                primary: BASIS_POINTS,
+                secondary: BASIS_POINTS
+            })
+        });
+    }
+
+    public contains(split: Split): boolean {
+        return (
+            split.isValid() &&
+            split.staker >= this.min.staker &&
+            split.staker <= this.max.staker &&
+            split.primary >= this.min.primary &&
+            split.primary <= this.max.primary &&
+            split.secondary >= this.min.secondary &&
+            split.secondary <= this.max.secondary
+        );
+    }
+}
+
+/**
+ * A geometric decay of emissions. At the end of every period, the emission
+ * is multiplied by `numerator / denominator`, but never decays below `floor`.
+ */
+export class EmissionDecay {
+    public numerator: BN;
+    public denominator: BN;
+    public period: BN;
+    public floor: BN;
+
+    constructor(params: {
+        numerator: BN;
+        denominator: BN;
+        period: BN;
+        floor: BN;
+    }) {
+        this.numerator = params.numerator;
+        this.denominator = params.denominator;
+        this.period = params.period;
+        this.floor = params.floor;
+    }
+
+    /** 10% decay every year without a floor */
+    static get DEFAULT(): EmissionDecay {
+        return new EmissionDecay({
+            numerator: new BN(9),
+            denominator: new BN(10),
+            period: SECONDS_PER_YEAR,
+            floor: new BN(0)
+        });
+    }
+
+    /** The emission of the period following one with `emission` */
+    public apply(emission: BN): BN {
+        const decayed = emission.mul(this.numerator).div(this.denominator);
+        // an emission that was set below the floor is not raised
+        return BN.max(decayed, BN.min(this.floor, emission));
+    }
+}
+
+/** A change of emission at a specific time */
+export class Breakpoint {
+    public start: Date;
+    public yearlyEmission: BN;
+
+    constructor(params: { start: Date; yearlyEmission: BN }) {
+        this.start = params.start;
+        this.yearlyEmission = params.yearlyEmission;
+    }
+
+    /** The emission per decay period of `period` seconds */
+    public emission(period: BN): BN {
+        return this.yearlyEmission.mul(period).div(SECONDS_PER_YEAR);
+    }
+}
+
+export class EmissionSchedule {
+    static readonly ACCOUNT_TYPE = AccountType.EmissionSchedule;
+    static readonly VERSION = 1;
+
+    public breakpoints: Breakpoint[];
+
+    constructor(params: { breakpoints: Breakpoint[] }) {
+        this.breakpoints = params.breakpoints;
+    }
+}
+
 export class Settings {
+    static readonly ACCOUNT_TYPE = AccountType.Settings;
+    static readonly VERSION = 1;
+
     public token: PublicKey;
+    public admin: Authority;
     public unbondingTime: BN;
+    public splitBounds: SplitBounds;
 
     public nextEmissionChange: Date;
     public emission: BN;
+    public decay: EmissionDecay;
+    public emissionSchedule: PublicKey;
 
     public totalStake: BN;
     public rewardPerShare: BN;
     public lastReward: Date;
+    public liability: BN;
+    public totalFunded: BN;
 
     constructor(params: {
         token: PublicKey;
+        admin: Authority;
         unbondingTime: BN;
+        splitBounds: SplitBounds;
         nextEmissionChange: Date;
         emission: BN;
+        decay: EmissionDecay;
+        emissionSchedule: PublicKey;
         totalStake: BN;
         rewardPerShare: BN;
         lastReward: Date;
+        liability: BN;
+        totalFunded: BN;
     }) {
         this.token = params.token;
+        this.admin = params.admin;
         this.unbondingTime = params.unbondingTime;
+        this.splitBounds = params.splitBounds;
         this.nextEmissionChange = params.nextEmissionChange;
         this.emission = params.emission;
+        this.decay = params.decay;
+        this.emissionSchedule = params.emissionSchedule;
         this.totalStake = params.totalStake;
         this.rewardPerShare = params.rewardPerShare;
         this.lastReward = params.lastReward;
+        this.liability = params.liability;
+        this.totalFunded = params.totalFunded;
+    }
+
+    public hasEmissionSchedule(): boolean {
+        return !this.emissionSchedule.equals(PublicKey.default);
     }
 
-    public calculateRewardPerShare(now: Date): BN {
-        let reward = this.rewardPerShare;
+    private periodReward(emission: BN, seconds: number): BN {
+        if (this.totalStake.cmpn(0) <= 0) {
+            return new BN(0);
+        }
 
-        const oldSeconds = this.lastReward.getUnixTime();
+        return PRECISION.mul(emission)
+            .div(this.decay.period)
+            .div(this.totalStake)
+            .muln(seconds);
+    }
+
+    /**
+     * The reward per share at `now`, the same way the program calculates it.
+     * The schedule is required if the settings reference one. Without the
+     * balance of the reward pool, emissions are not capped by what the pool
+     * can pay out.
+     */
+    public calculateRewardPerShare(
+        now: Date,
+        schedule?: EmissionSchedule,
+        poolBalance?: BN
+    ): BN {
         const newSeconds = now.getUnixTime();
+        let lastReward = this.lastReward.getUnixTime();
 
-        if (newSeconds <= oldSeconds) {
-            return reward;
+        if (newSeconds <= lastReward) {
+            return this.rewardPerShare;
         }
 
-        if (this.totalStake.cmpn(0) > 0) {
-            const delta = new BN(0);
-            let emission = this.emission;
-            let nextEmissionChange = this.nextEmissionChange.getUnixTime();
-            let lastReward = this.lastReward.getUnixTime();
-            while (newSeconds >= nextEmissionChange) {
-                const seconds = new BN(nextEmissionChange - lastReward);
-                delta.iadd(
-                    PRECISION.mul(emission)
-                        .div(SECONDS_PER_YEAR)
-                        .div(this.totalStake)
-                        .mul(seconds)
-                );
-                lastReward = nextEmissionChange;
-                nextEmissionChange += SECONDS_PER_YEAR.toNumber();
-                emission = emission.muln(9).divn(10);
+        const breakpoints = schedule === undefined ? [] : schedule.breakpoints;
+        let emission = this.emission;
+        let nextEmissionChange = this.nextEmissionChange.getUnixTime();
+        let reward = new BN(0);
+
+        // emissions change at the end of every period and at every breakpoint
+        for (;;) {
+            const breakpoint = breakpoints.find(
+                (b) =>
+                    b.start.getUnixTime() > lastReward &&
+                    b.start.getUnixTime() <= newSeconds
+            );
+            let boundary: number;
+            if (
+                breakpoint !== undefined &&
+                breakpoint.start.getUnixTime() < nextEmissionChange
+            ) {
+                boundary = breakpoint.start.getUnixTime();
+            } else if (nextEmissionChange <= newSeconds) {
+                boundary = nextEmissionChange;
+            } else {
+                break;
             }
 
-            const seconds = new BN(newSeconds - lastReward);
-            delta.iadd(
-                PRECISION.mul(emission)
-                    .div(SECONDS_PER_YEAR)
-                    .div(this.totalStake)
-                    .mul(seconds)
+            reward = reward.add(
+                this.periodReward(emission, boundary - lastReward)
             );
+            lastReward = boundary;
 
-            reward = reward.add(delta);
+            if (boundary === nextEmissionChange) {
+                nextEmissionChange += this.decay.period.toNumber();
+                emission = this.decay.apply(emission);
+            }
+            if (
+                breakpoint !== undefined &&
+                breakpoint.start.getUnixTime() === boundary
+            ) {
+                emission = breakpoint.emission(this.decay.period);
+            }
         }
 
-        return reward;
-    }
-}
-
-export enum AuthorityType {
-    Basic,
-    NFT
-}
-
-export class Authority {
-    authorityType: AuthorityType;
-    address: PublicKey;
+        reward = reward.add(
+            this.periodReward(emission, newSeconds - lastReward)
+        );
 
-    constructor(params: { authorityType: AuthorityType; address: PublicKey }) {
-        this.authorityType = params.authorityType;
-        this.address = params.address;
-    }
+        // never emit more than the reward pool can pay out
+        if (poolBalance !== undefined && this.totalStake.cmpn(0) > 0) {
+            const available = BN.max(
+                poolBalance.sub(this.liability),
+                new BN(0)
+            );
+            const emitted = reward.mul(this.totalStake).div(PRECISION);
+            if (emitted.gt(available)) {
+                reward = available.mul(PRECISION).div(this.totalStake);
+            }
+        }
 
-    static Basic(address: PublicKey): Authority {
-        return new this({ authorityType: AuthorityType.Basic, address });
-    }
-
-    static NFT(address: PublicKey): Authority {
-        return new this({ authorityType: AuthorityType.NFT, address });
+        return this.rewardPerShare.add(reward);
     }
 }
 
 export class Beneficiary {
+    static readonly ACCOUNT_TYPE = AccountType.Beneficiary;
+    static readonly VERSION = 1;
+
     public authority: PublicKey;
     public staked: BN;
     public rewardDebt: BN;
@@ -134,17 +451,24 @@ export class Beneficiary {
     }
 
     public isEmpty(): boolean {
-        return this.authority == PublicKey.default;
+        return this.authority.equals(PublicKey.default);
     }
 }
 
 export class Endpoint {
+    static readonly ACCOUNT_TYPE = AccountType.Endpoint;
+    static readonly VERSION = 1;
+
     public creationDate: Date;
     public totalStake: BN;
 
     public owner: Authority;
     public primary: PublicKey;
     public secondary: PublicKey;
+    public split: Split;
+    public primaryStake: BN;
+    public secondaryStake: BN;
+    public sunset: PublicKey;
 
     constructor(params: {
         creationDate: Date;
@@ -152,50 +476,147 @@ export class Endpoint {
         owner: Authority;
         primary: PublicKey;
         secondary: PublicKey;
+        split: Split;
+        primaryStake: BN;
+        secondaryStake: BN;
+        sunset: PublicKey;
     }) {
         this.creationDate = params.creationDate;
         this.totalStake = params.totalStake;
         this.owner = params.owner;
         this.primary = params.primary;
         this.secondary = params.secondary;
+        this.split = params.split;
+        this.primaryStake = params.primaryStake;
+        this.secondaryStake = params.secondaryStake;
+        this.sunset = params.sunset;
+    }
+
+    /** A sunset Endpoint only allows withdrawing */
+    public isSunset(): boolean {
+        return !this.sunset.equals(PublicKey.default);
+    }
+}
+
+/** A tranche of unstaked funds that is locked until `end` */
+export class Unbonding {
+    public end: Date;
+    public amount: BN;
+
+    constructor(params: { end: Date; amount: BN }) {
+        this.end = params.end;
+        this.amount = params.amount;
     }
 }
 
 export class Stake {
+    static readonly ACCOUNT_TYPE = AccountType.Stake;
+    static readonly VERSION = 1;
+    static readonly MAX_UNBONDING = 8;
+
     public creationDate: Date;
     public totalStake: BN;
     public staker: PublicKey;
-    public unbondingEnd: Date;
+    public split: Split;
     public unbondingAmount: BN;
+    public unbonding: Unbonding[];
 
     constructor(params: {
         creationDate: Date;
         totalStake: BN;
         staker: PublicKey;
-        unbondingEnd: Date;
+        split: Split;
         unbondingAmount: BN;
+        unbonding: Unbonding[];
     }) {
         this.creationDate = params.creationDate;
         this.totalStake = params.totalStake;
         this.staker = params.staker;
-        this.unbondingEnd = params.unbondingEnd;
+        this.split = params.split;
         this.unbondingAmount = params.unbondingAmount;
+        this.unbonding = params.unbonding;
+    }
+
+    /** The unbonding funds that can be withdrawn at `now` */
+    public withdrawable(now: Date): BN {
+        return this.unbonding
+            .filter((u) => u.end.getUnixTime() <= now.getUnixTime())
+            .reduce((sum, u) => sum.add(u.amount), new BN(0));
     }
 }
 
 export const ACCOUNT_SCHEMA: borsh.Schema = new Map<any, any>([
+    [
+        Split,
+        {
+            kind: 'struct',
+            fields: [
+                ['staker', 'u16'],
+                ['primary', 'u16'],
+                ['secondary', 'u16']
+            ]
+        }
+    ],
+    [
+        SplitBounds,
+        {
+            kind: 'struct',
+            fields: [
+                ['min', Split],
+                ['max', Split]
+            ]
+        }
+    ],
+    [
+        EmissionDecay,
+        {
+            kind: 'struct',
+            fields: [
+                ['numerator', 'u64'],
+                ['denominator', 'u64'],
+                ['period', 'u64'],
+                ['floor', 'u64']
+            ]
+        }
+    ],
+    [
+        Breakpoint,
+        {
+            kind: 'struct',
+            fields: [
+                ['start', 'Date'],
+                ['yearlyEmission', 'u64']
+            ]
+        }
+    ],
+    [
+        Unbonding,
+        {
+            kind: 'struct',
+            fields: [
+                ['end', 'Date'],
+                ['amount', 'u64']
+            ]
+        }
+    ],
     [
         Settings,
         {
             kind: 'struct',
             fields: [
                 ['token', 'PublicKey'],
+                ['admin', 'Authority'],
                 ['unbondingTime', 'u64'],
+                ['splitBounds', SplitBounds],
                 ['nextEmissionChange', 'Date'],
                 ['emission', 'u64'],
+                ['decay', EmissionDecay],
+                ['emissionSchedule', 'PublicKey'],
                 ['totalStake', 'u64'],
                 ['rewardPerShare', 'u128'],
-                ['lastReward', 'Date']
+                ['lastReward', 'Date'],
+                ['liability', 'u64'],
+                ['totalFunded', 'u64']
             ]
         }
     ],
@@ -220,7 +641,11 @@ export const ACCOUNT_SCHEMA: borsh.Schema = new Map<any, any>([
                 ['totalStake', 'u64'],
                 ['owner', 'Authority'],
                 ['primary', 'PublicKey'],
-                ['secondary', 'PublicKey']
+                ['secondary', 'PublicKey'],
+                ['split', Split],
+                ['primaryStake', 'u64'],
+                ['secondaryStake', 'u64'],
+                ['sunset', 'PublicKey']
             ]
         }
     ],
@@ -232,9 +657,17 @@ export const ACCOUNT_SCHEMA: borsh.Schema = new Map<any, any>([
                 ['creationDate', 'Date'],
                 ['totalStake', 'u64'],
                 ['staker', 'PublicKey'],
-                ['unbondingEnd', 'Date'],
-                ['unbondingAmount', 'u64']
+                ['split', Split],
+                ['unbondingAmount', 'u64'],
+                ['unbonding', [Unbonding]]
             ]
         }
+    ],
+    [
+        EmissionSchedule,
+        {
+            kind: 'struct',
+            fields: [['breakpoints', [Breakpoint]]]
+        }
     ]
 ]);
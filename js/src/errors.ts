@@ -26,7 +26,32 @@ export enum StakingErrors {
     PrimaryAuthorityCannotBeEmpty,
     InvalidAuthorityType,
     AuthorityKeysDoNotMatch,
-    SecondaryAuthorityKeysDoNotMatch
+    SecondaryAuthorityKeysDoNotMatch,
+    InvalidNextEmissionChange,
+    AccountRequiresMigration,
+    NothingToMigrate,
+    InvalidUpgradeAuthority,
+    InvalidAccountType,
+    InvalidAccountOwner,
+    AccountNotWritable,
+    AccountNotSigner,
+    RebondInvalidAmount,
+    RedelegateSameEndpoint,
+    StakeNotEmpty,
+    EndpointHasStake,
+    EndpointSunset,
+    InvalidSunsetDestination,
+    BeneficiaryNotEmpty,
+    InvalidSplit,
+    SplitOutOfBounds,
+    InvalidSplitBounds,
+    InvalidEmissionDecay,
+    InvalidEmissionScheduleAccount,
+    InvalidEmissionSchedule,
+    MissingEmissionSchedule,
+    FundInvalidAmount,
+    UnbondingQueueFull,
+    ResyncOutOfBounds
 }
 
 const custom = /custom program error: 0x([0-9a-fA-F]+)/;
@@ -7,12 +7,14 @@ declare module 'borsh' {
         writePublicKey(value: PublicKey): void;
         writeDate(value: Date): void;
         writeAuthority(value: Authority): void;
+        writeBool(value: boolean): void;
     }
     interface BinaryReader {
         readBigInt(): bigint;
         readPublicKey(): PublicKey;
         readDate(): Date;
         readAuthority(): Authority;
+        readBool(): boolean;
     }
 }
 
@@ -68,3 +70,11 @@ BinaryReader.prototype.readAuthority = function () {
             throw new Error('unknown AuthorityType');
     }
 };
+
+BinaryWriter.prototype.writeBool = function (value: boolean) {
+    this.writeU8(value ? 1 : 0);
+};
+
+BinaryReader.prototype.readBool = function () {
+    return this.readU8() !== 0;
+};
@@ -7,7 +7,15 @@ import {
     SYSVAR_RENT_PUBKEY,
     TransactionInstruction
 } from '@solana/web3.js';
-import { Authority, AuthorityType, Staking } from '.';
+import { Staking } from '.';
+import {
+    ACCOUNT_SCHEMA,
+    Authority,
+    Breakpoint,
+    EmissionDecay,
+    Split,
+    SplitBounds
+} from './accounts';
 import * as borsh from 'borsh';
 import './extendBorsh';
 import BN from 'bn.js';
@@ -20,33 +28,55 @@ export enum Instructions {
     WithdrawUnbond,
     Claim,
     TransferEndpoint,
-    ChangeBeneficiaries
+    ChangeBeneficiaries,
+    UpdateSettings,
+    Migrate,
+    Rebond,
+    Redelegate,
+    CloseStake,
+    CloseEndpoint,
+    CloseBeneficiary,
+    ChangeSplit,
+    SetEmissionSchedule,
+    FundRewardPool,
+    QueryPending,
+    Resync
 }
 
 export type InstructionSchema =
     | SimpleSchema
     | AmountSchema
+    | UnsignedAmountSchema
     | InitSchema
-    | AuthoritySchema;
+    | RegisterEndpointSchema
+    | AuthoritySchema
+    | UpdateSettingsSchema
+    | CloseEndpointSchema
+    | SplitSchema
+    | EmissionScheduleSchema
+    | ResyncSchema;
+
+/** Instructions without data */
+export type SimpleInstructions =
+    | Instructions.InitializeStake
+    | Instructions.WithdrawUnbond
+    | Instructions.Claim
+    | Instructions.ChangeBeneficiaries
+    | Instructions.CloseStake
+    | Instructions.CloseBeneficiary
+    | Instructions.QueryPending;
+
+/** Instructions with a single u64 */
+export type UnsignedAmountInstructions =
+    | Instructions.Migrate
+    | Instructions.Rebond
+    | Instructions.Redelegate
+    | Instructions.FundRewardPool;
 
 export class SimpleSchema {
-    instructionId: Exclude<
-        Instructions,
-        | Instructions.Initialize
-        | Instructions.Stake
-        | Instructions.RegisterEndpoint
-        | Instructions.TransferEndpoint
-    >;
+    instructionId: SimpleInstructions;
 
-    constructor(params: {
-        instructionId: Exclude<
-            Instructions,
-            | Instructions.Initialize
-            | Instructions.Stake
-            | Instructions.RegisterEndpoint
-            | Instructions.TransferEndpoint
-        >;
-    }) {
+    constructor(params: { instructionId: SimpleInstructions }) {
         this.instructionId = params.instructionId;
     }
 }
@@ -61,34 +91,61 @@ export class AmountSchema {
     }
 }
 
+/** The amount is the liability for `Migrate` */
+export class UnsignedAmountSchema {
+    instructionId: UnsignedAmountInstructions;
+    amount: BN;
+
+    constructor(params: {
+        instructionId: UnsignedAmountInstructions;
+        amount: BN;
+    }) {
+        this.instructionId = params.instructionId;
+        this.amount = params.amount;
+    }
+}
+
 export class InitSchema {
     instructionId: Instructions.Initialize;
     startTime: Date;
     unbondingDuration: BN;
+    admin: Authority;
 
     constructor(params: {
         instructionId: Instructions.Initialize;
         startTime: Date;
         unbondingDuration: BN;
+        admin: Authority;
     }) {
         this.instructionId = params.instructionId;
         this.startTime = params.startTime;
         this.unbondingDuration = params.unbondingDuration;
+        this.admin = params.admin;
+    }
+}
+
+export class RegisterEndpointSchema {
+    instructionId: Instructions.RegisterEndpoint;
+    authority: Authority;
+    split: Split;
+
+    constructor(params: {
+        instructionId: Instructions.RegisterEndpoint;
+        authority: Authority;
+        split: Split;
+    }) {
+        this.instructionId = params.instructionId;
+        this.authority = params.authority;
+        this.split = params.split;
     }
 }
 
 export class AuthoritySchema {
-    instructionId: Extract<
-        Instructions,
-        Instructions.TransferEndpoint | Instructions.RegisterEndpoint
-    >;
+    instructionId: Instructions.TransferEndpoint;
     authority: Authority;
 
     constructor(params: {
-        instructionId: Extract<
-            Instructions,
-            Instructions.TransferEndpoint | Instructions.RegisterEndpoint
-        >;
+        instructionId: Instructions.TransferEndpoint;
         authority: Authority;
     }) {
         this.instructionId = params.instructionId;
@@ -96,17 +153,126 @@ export class AuthoritySchema {
     }
 }
 
+/** Settings that are left out are not changed */
+export interface SettingsUpdate {
+    unbondingDuration?: BN;
+    emission?: BN;
+    nextEmissionChange?: Date;
+    splitBounds?: SplitBounds;
+    decay?: EmissionDecay;
+}
+
+export class UpdateSettingsSchema {
+    instructionId: Instructions.UpdateSettings;
+    unbondingDuration?: BN;
+    emission?: BN;
+    nextEmissionChange?: Date;
+    splitBounds?: SplitBounds;
+    decay?: EmissionDecay;
+
+    constructor(
+        params: { instructionId: Instructions.UpdateSettings } & SettingsUpdate
+    ) {
+        this.instructionId = params.instructionId;
+        this.unbondingDuration = params.unbondingDuration;
+        this.emission = params.emission;
+        this.nextEmissionChange = params.nextEmissionChange;
+        this.splitBounds = params.splitBounds;
+        this.decay = params.decay;
+    }
+}
+
+export class CloseEndpointSchema {
+    instructionId: Instructions.CloseEndpoint;
+    sunset: boolean;
+
+    constructor(params: {
+        instructionId: Instructions.CloseEndpoint;
+        sunset: boolean;
+    }) {
+        this.instructionId = params.instructionId;
+        this.sunset = params.sunset;
+    }
+}
+
+export class SplitSchema {
+    instructionId: Instructions.ChangeSplit;
+    split: Split;
+
+    constructor(params: {
+        instructionId: Instructions.ChangeSplit;
+        split: Split;
+    }) {
+        this.instructionId = params.instructionId;
+        this.split = params.split;
+    }
+}
+
+export class EmissionScheduleSchema {
+    instructionId: Instructions.SetEmissionSchedule;
+    breakpoints: Breakpoint[];
+
+    constructor(params: {
+        instructionId: Instructions.SetEmissionSchedule;
+        breakpoints: Breakpoint[];
+    }) {
+        this.instructionId = params.instructionId;
+        this.breakpoints = params.breakpoints;
+    }
+}
+
+/**
+ * The corrected stake of a Beneficiary and the stake its Endpoints track for
+ * it, one value for every role the authority holds, the primary's first
+ */
+export class ResyncSchema {
+    instructionId: Instructions.Resync;
+    staked: BN;
+    tracked: BN[];
+
+    constructor(params: {
+        instructionId: Instructions.Resync;
+        staked: BN;
+        tracked: BN[];
+    }) {
+        this.instructionId = params.instructionId;
+        this.staked = params.staked;
+        this.tracked = params.tracked;
+    }
+}
+
 export class Instruction {
+    /**
+     * Append the Emission Schedule account to an instruction that updates the
+     * rewards. Required once the settings reference an emission schedule.
+     */
+    public static async WithEmissionSchedule(
+        instruction: TransactionInstruction
+    ): Promise<TransactionInstruction> {
+        const emissionScheduleId = await Staking.emissionScheduleId(
+            instruction.programId
+        );
+        instruction.keys.push(am(emissionScheduleId, false, false));
+        return instruction;
+    }
+
+    /**
+     * The upgrade authority of the program has to sign. The admin is allowed
+     * to update the settings afterwards.
+     */
     public static async Initialize(
         programId: PublicKey,
         funder: PublicKey,
         mint: PublicKey,
+        admin: Authority,
+        upgradeAuthority: PublicKey,
         startTime: Date,
         unbondingDuration: number
     ): Promise<TransactionInstruction> {
         const settingsId = await Staking.settingsId(programId);
         const poolAuthorityId = await Staking.poolAuthorityId(programId);
         const rewardPoolId = await Staking.rewardPoolId(programId);
+        const programDataId = await Staking.programDataId(programId);
 
         const keys: AccountMeta[] = [
             am(funder, true, true),
@@ -114,40 +280,42 @@ export class Instruction {
             am(poolAuthorityId, false, false),
             am(rewardPoolId, false, true),
             am(mint, false, false),
+            am(admin.address, false, false),
             am(SYSVAR_RENT_PUBKEY, false, false),
             am(TOKEN_PROGRAM_ID, false, false),
-            am(SystemProgram.programId, false, false)
+            am(SystemProgram.programId, false, false),
+            am(programDataId, false, false),
+            am(upgradeAuthority, true, false)
         ];
 
         const instruction = new InitSchema({
             instructionId: Instructions.Initialize,
             startTime: startTime,
-            unbondingDuration: new BN(unbondingDuration)
+            unbondingDuration: new BN(unbondingDuration),
+            admin
         });
-        const instructionData = borsh.serialize(
-            INSTRUCTION_SCHEMA,
-            instruction
-        );
 
-        return new TransactionInstruction({
-            keys: keys,
-            programId,
-            data: Buffer.from(instructionData)
-        });
+        return build(programId, keys, instruction);
     }
 
+    /** The split has to be within the bounds set in the settings */
     public static async RegisterEndpoint(
         programId: PublicKey,
         funder: PublicKey,
         endpoint: PublicKey,
         owner: Authority,
         primary: PublicKey,
-        secondary?: PublicKey
+        secondary?: PublicKey,
+        split?: Split
     ): Promise<TransactionInstruction> {
         if (secondary === undefined) {
             secondary = PublicKey.default;
         }
+        if (split === undefined) {
+            split = Split.DEFAULT;
+        }
 
+        const settingsId = await Staking.settingsId(programId);
         const primaryBeneficiary = await Staking.beneficiary(
             primary,
             programId
@@ -167,23 +335,17 @@ export class Instruction {
             am(secondaryBeneficiary, false, true),
             am(SYSVAR_RENT_PUBKEY, false, false),
             am(SYSVAR_CLOCK_PUBKEY, false, false),
-            am(SystemProgram.programId, false, false)
+            am(SystemProgram.programId, false, false),
+            am(settingsId, false, false)
         ];
 
-        const instruction = new AuthoritySchema({
+        const instruction = new RegisterEndpointSchema({
             instructionId: Instructions.RegisterEndpoint,
-            authority: owner
+            authority: owner,
+            split
         });
-        const instructionData = borsh.serialize(
-            INSTRUCTION_SCHEMA,
-            instruction
-        );
 
-        return new TransactionInstruction({
-            keys: keys,
-            programId,
-            data: Buffer.from(instructionData)
-        });
+        return build(programId, keys, instruction);
     }
 
     public static async InitializeStake(
@@ -209,11 +371,11 @@ export class Instruction {
             am(staker, true, false),
             am(stakerFund, false, true),
             am(stakerBeneficiary, false, true),
-            am(endpoint, false, true), // true because of pairing
+            am(endpoint, false, false),
             am(stakeId, false, true),
 
             am(mint, false, false),
-            am(settings, false, true), // true because of pairing
+            am(settings, false, false),
 
             am(SYSVAR_RENT_PUBKEY, false, false),
             am(SYSVAR_CLOCK_PUBKEY, false, false),
@@ -224,18 +386,15 @@ export class Instruction {
         const instruction = new SimpleSchema({
             instructionId: Instructions.InitializeStake
         });
-        const instructionData = borsh.serialize(
-            INSTRUCTION_SCHEMA,
-            instruction
-        );
 
-        return new TransactionInstruction({
-            keys: keys,
-            programId,
-            data: Buffer.from(instructionData)
-        });
+        return build(programId, keys, instruction);
     }
 
+    /**
+     * A negative amount unstakes. The sunset destination is required once the
+     * Endpoint is sunset. It closes the Endpoint when the last stake is
+     * withdrawn.
+     */
     public static async Stake(
         programId: PublicKey,
         funder: PublicKey,
@@ -244,7 +403,8 @@ export class Instruction {
         endpoint: PublicKey,
         primary: PublicKey,
         secondary: PublicKey,
-        amount: bigint
+        amount: bigint,
+        sunsetDestination?: PublicKey
     ): Promise<TransactionInstruction> {
         const settingsId = await Staking.settingsId(programId);
         const poolAuthorityId = await Staking.poolAuthorityId(programId);
@@ -281,23 +441,20 @@ export class Instruction {
             am(settingsId, false, true),
             am(stakeId, false, true),
             am(SYSVAR_CLOCK_PUBKEY, false, false),
-            am(TOKEN_PROGRAM_ID, false, false)
+            am(TOKEN_PROGRAM_ID, false, false),
+            // recreates closed beneficiaries
+            am(SystemProgram.programId, false, false)
         ];
+        if (sunsetDestination !== undefined) {
+            keys.push(am(sunsetDestination, false, true));
+        }
 
         const instruction = new AmountSchema({
             instructionId: Instructions.Stake,
             amount
         });
-        const instructionData = borsh.serialize(
-            INSTRUCTION_SCHEMA,
-            instruction
-        );
 
-        return new TransactionInstruction({
-            keys: keys,
-            programId,
-            data: Buffer.from(instructionData)
-        });
+        return build(programId, keys, instruction);
     }
 
     public static async WithdrawUnbond(
@@ -330,16 +487,8 @@ export class Instruction {
         const instruction = new SimpleSchema({
             instructionId: Instructions.WithdrawUnbond
         });
-        const instructionData = borsh.serialize(
-            INSTRUCTION_SCHEMA,
-            instruction
-        );
 
-        return new TransactionInstruction({
-            keys: keys,
-            programId,
-            data: Buffer.from(instructionData)
-        });
+        return build(programId, keys, instruction);
     }
 
     public static async Claim(
@@ -368,18 +517,15 @@ export class Instruction {
         const instruction = new SimpleSchema({
             instructionId: Instructions.Claim
         });
-        const instructionData = borsh.serialize(
-            INSTRUCTION_SCHEMA,
-            instruction
-        );
 
-        return new TransactionInstruction({
-            keys: keys,
-            programId,
-            data: Buffer.from(instructionData)
-        });
+        return build(programId, keys, instruction);
     }
 
+    /**
+     * For a basic owner, `owner` and `ownerSigner` are the owner's wallet. For
+     * an NFT owner, `owner` is the token account holding the NFT and
+     * `ownerSigner` is the holder.
+     */
     public static async TransferEndpoint(
         programId: PublicKey,
         funder: PublicKey,
@@ -393,7 +539,6 @@ export class Instruction {
             am(endpoint, false, true),
             am(owner, false, false),
             am(ownerSigner, true, false),
-            am(recipient.address, false, false),
             am(recipient.address, false, false)
         ];
 
@@ -401,16 +546,8 @@ export class Instruction {
             instructionId: Instructions.TransferEndpoint,
             authority: recipient
         });
-        const instructionData = borsh.serialize(
-            INSTRUCTION_SCHEMA,
-            instruction
-        );
 
-        return new TransactionInstruction({
-            keys: keys,
-            programId,
-            data: Buffer.from(instructionData)
-        });
+        return build(programId, keys, instruction);
     }
 
     public static async ChangeBeneficiaries(
@@ -425,6 +562,7 @@ export class Instruction {
         newSecondary?: PublicKey
     ): Promise<TransactionInstruction> {
         const settingsId = await Staking.settingsId(programId);
+        const rewardPoolId = await Staking.rewardPoolId(programId);
         if (newSecondary === undefined) {
             newSecondary = PublicKey.default;
         }
@@ -460,25 +598,472 @@ export class Instruction {
             am(settingsId, false, true),
             am(SYSVAR_RENT_PUBKEY, false, false),
             am(SYSVAR_CLOCK_PUBKEY, false, false),
-            am(SystemProgram.programId, false, false)
+            am(SystemProgram.programId, false, false),
+            am(rewardPoolId, false, false)
         ];
 
         const instruction = new SimpleSchema({
             instructionId: Instructions.ChangeBeneficiaries
         });
-        const instructionData = borsh.serialize(
-            INSTRUCTION_SCHEMA,
-            instruction
+
+        return build(programId, keys, instruction);
+    }
+
+    /**
+     * For a basic admin, `admin` and `adminSigner` are the admin's wallet. For
+     * an NFT admin, `admin` is the token account holding the NFT and
+     * `adminSigner` is the holder.
+     */
+    public static async UpdateSettings(
+        programId: PublicKey,
+        funder: PublicKey,
+        admin: PublicKey,
+        adminSigner: PublicKey,
+        update: SettingsUpdate
+    ): Promise<TransactionInstruction> {
+        const settingsId = await Staking.settingsId(programId);
+        const rewardPoolId = await Staking.rewardPoolId(programId);
+
+        const keys: AccountMeta[] = [
+            am(funder, true, true),
+            am(settingsId, false, true),
+            am(admin, false, false),
+            am(adminSigner, true, false),
+            am(SYSVAR_CLOCK_PUBKEY, false, false),
+            am(rewardPoolId, false, false)
+        ];
+
+        const instruction = new UpdateSettingsSchema({
+            instructionId: Instructions.UpdateSettings,
+            ...update
+        });
+
+        return build(programId, keys, instruction);
+    }
+
+    /**
+     * Migrate an Endpoint, Beneficiary or Stake account. Migrating an Endpoint
+     * needs all of its Stake accounts.
+     */
+    public static async Migrate(
+        programId: PublicKey,
+        funder: PublicKey,
+        account: PublicKey,
+        stakes: PublicKey[] = []
+    ): Promise<TransactionInstruction> {
+        const keys: AccountMeta[] = [
+            am(funder, true, true),
+            am(account, false, true),
+            am(SYSVAR_RENT_PUBKEY, false, false),
+            am(SystemProgram.programId, false, false),
+            ...stakes.map((stake) => am(stake, false, false))
+        ];
+
+        const instruction = new UnsignedAmountSchema({
+            instructionId: Instructions.Migrate,
+            amount: new BN(0)
+        });
+
+        return build(programId, keys, instruction);
+    }
+
+    /**
+     * Migrate the Settings account, signed by the program's upgrade authority.
+     * `beneficiaries` are the addresses of every Beneficiary account, the
+     * liability is the yield they are owed that has not been paid out yet.
+     */
+    public static async MigrateSettings(
+        programId: PublicKey,
+        funder: PublicKey,
+        upgradeAuthority: PublicKey,
+        beneficiaries: PublicKey[],
+        liability: BN
+    ): Promise<TransactionInstruction> {
+        const settingsId = await Staking.settingsId(programId);
+        const programDataId = await Staking.programDataId(programId);
+
+        const keys: AccountMeta[] = [
+            am(funder, true, true),
+            am(settingsId, false, true),
+            am(SYSVAR_RENT_PUBKEY, false, false),
+            am(SystemProgram.programId, false, false),
+            am(programDataId, false, false),
+            am(upgradeAuthority, true, false),
+            ...beneficiaries.map((beneficiary) => am(beneficiary, false, false))
+        ];
+
+        const instruction = new UnsignedAmountSchema({
+            instructionId: Instructions.Migrate,
+            amount: liability
+        });
+
+        return build(programId, keys, instruction);
+    }
+
+    public static async Rebond(
+        programId: PublicKey,
+        funder: PublicKey,
+        staker: PublicKey,
+        endpoint: PublicKey,
+        primary: PublicKey,
+        secondary: PublicKey,
+        amount: BN
+    ): Promise<TransactionInstruction> {
+        const settingsId = await Staking.settingsId(programId);
+        const rewardPoolId = await Staking.rewardPoolId(programId);
+        const stakeId = await Staking.stakeAddress(programId, endpoint, staker);
+
+        const keys: AccountMeta[] = [
+            am(funder, true, true),
+            am(staker, true, false),
+            am(await Staking.beneficiary(staker, programId), false, true),
+            am(endpoint, false, true),
+            am(await Staking.beneficiary(primary, programId), false, true),
+            am(await Staking.beneficiary(secondary, programId), false, true),
+            am(settingsId, false, true),
+            am(stakeId, false, true),
+            am(SYSVAR_CLOCK_PUBKEY, false, false),
+            am(rewardPoolId, false, false),
+            am(SystemProgram.programId, false, false)
+        ];
+
+        const instruction = new UnsignedAmountSchema({
+            instructionId: Instructions.Rebond,
+            amount
+        });
+
+        return build(programId, keys, instruction);
+    }
+
+    /**
+     * Move stake between two Endpoints of the same staker. The sunset
+     * destination is required once the source Endpoint is sunset. It closes
+     * the Endpoint when the last stake is moved away.
+     */
+    public static async Redelegate(
+        programId: PublicKey,
+        funder: PublicKey,
+        staker: PublicKey,
+        source: EndpointBeneficiaries,
+        destination: EndpointBeneficiaries,
+        amount: BN,
+        sunsetDestination?: PublicKey
+    ): Promise<TransactionInstruction> {
+        const settingsId = await Staking.settingsId(programId);
+        const rewardPoolId = await Staking.rewardPoolId(programId);
+
+        const keys: AccountMeta[] = [
+            am(funder, true, true),
+            am(staker, true, false),
+            am(await Staking.beneficiary(staker, programId), false, true)
+        ];
+        for (const endpoint of [source, destination]) {
+            keys.push(
+                am(endpoint.endpoint, false, true),
+                am(
+                    await Staking.beneficiary(endpoint.primary, programId),
+                    false,
+                    true
+                ),
+                am(
+                    await Staking.beneficiary(endpoint.secondary, programId),
+                    false,
+                    true
+                ),
+                am(
+                    await Staking.stakeAddress(
+                        programId,
+                        endpoint.endpoint,
+                        staker
+                    ),
+                    false,
+                    true
+                ),
+                am(
+                    await Staking.stakeFundAddress(
+                        endpoint.endpoint,
+                        staker,
+                        programId
+                    ),
+                    false,
+                    true
+                )
+            );
+        }
+        keys.push(
+            am(settingsId, false, true),
+            am(SYSVAR_CLOCK_PUBKEY, false, false),
+            am(TOKEN_PROGRAM_ID, false, false),
+            am(rewardPoolId, false, false),
+            am(SystemProgram.programId, false, false)
         );
+        if (sunsetDestination !== undefined) {
+            keys.push(am(sunsetDestination, false, true));
+        }
 
-        return new TransactionInstruction({
-            keys: keys,
-            programId,
-            data: Buffer.from(instructionData)
+        const instruction = new UnsignedAmountSchema({
+            instructionId: Instructions.Redelegate,
+            amount
         });
+
+        return build(programId, keys, instruction);
+    }
+
+    /** Everything has to be withdrawn before a stake can be closed */
+    public static async CloseStake(
+        programId: PublicKey,
+        funder: PublicKey,
+        staker: PublicKey,
+        stakerAssociated: PublicKey,
+        endpoint: PublicKey,
+        destination: PublicKey
+    ): Promise<TransactionInstruction> {
+        const stakeId = await Staking.stakeAddress(programId, endpoint, staker);
+        const stakeFund = await Staking.stakeFundAddress(
+            endpoint,
+            staker,
+            programId
+        );
+
+        const keys: AccountMeta[] = [
+            am(funder, true, true),
+            am(staker, true, false),
+            am(stakeId, false, true),
+            am(stakeFund, false, true),
+            am(endpoint, false, false),
+            am(destination, false, true),
+            am(TOKEN_PROGRAM_ID, false, false),
+            am(stakerAssociated, false, true)
+        ];
+
+        const instruction = new SimpleSchema({
+            instructionId: Instructions.CloseStake
+        });
+
+        return build(programId, keys, instruction);
+    }
+
+    /** See `TransferEndpoint` for the owner accounts */
+    public static async CloseEndpoint(
+        programId: PublicKey,
+        funder: PublicKey,
+        endpoint: PublicKey,
+        owner: PublicKey,
+        ownerSigner: PublicKey,
+        destination: PublicKey,
+        sunset: boolean
+    ): Promise<TransactionInstruction> {
+        const keys: AccountMeta[] = [
+            am(funder, true, true),
+            am(endpoint, false, true),
+            am(owner, false, false),
+            am(ownerSigner, true, false),
+            am(destination, false, true)
+        ];
+
+        const instruction = new CloseEndpointSchema({
+            instructionId: Instructions.CloseEndpoint,
+            sunset
+        });
+
+        return build(programId, keys, instruction);
+    }
+
+    public static async CloseBeneficiary(
+        programId: PublicKey,
+        funder: PublicKey,
+        authority: PublicKey
+    ): Promise<TransactionInstruction> {
+        const settingsId = await Staking.settingsId(programId);
+        const rewardPoolId = await Staking.rewardPoolId(programId);
+        const beneficiary = await Staking.beneficiary(authority, programId);
+
+        const keys: AccountMeta[] = [
+            am(funder, true, true),
+            am(authority, true, true),
+            am(beneficiary, false, true),
+            am(settingsId, false, true),
+            am(SYSVAR_CLOCK_PUBKEY, false, false),
+            am(rewardPoolId, false, false)
+        ];
+
+        const instruction = new SimpleSchema({
+            instructionId: Instructions.CloseBeneficiary
+        });
+
+        return build(programId, keys, instruction);
+    }
+
+    /**
+     * `primary` and `secondary` are the Endpoint's beneficiaries. See
+     * `TransferEndpoint` for the owner accounts.
+     */
+    public static async ChangeSplit(
+        programId: PublicKey,
+        funder: PublicKey,
+        endpoint: PublicKey,
+        owner: PublicKey,
+        ownerSigner: PublicKey,
+        primary: PublicKey,
+        secondary: PublicKey,
+        split: Split
+    ): Promise<TransactionInstruction> {
+        const settingsId = await Staking.settingsId(programId);
+        const rewardPoolId = await Staking.rewardPoolId(programId);
+
+        const keys: AccountMeta[] = [
+            am(funder, true, true),
+            am(endpoint, false, true),
+            am(owner, false, false),
+            am(ownerSigner, true, false),
+            am(settingsId, false, true),
+            am(await Staking.beneficiary(primary, programId), false, true),
+            am(await Staking.beneficiary(secondary, programId), false, true),
+            am(SYSVAR_CLOCK_PUBKEY, false, false),
+            am(rewardPoolId, false, false),
+            am(SystemProgram.programId, false, false)
+        ];
+
+        const instruction = new SplitSchema({
+            instructionId: Instructions.ChangeSplit,
+            split
+        });
+
+        return build(programId, keys, instruction);
+    }
+
+    /**
+     * Replace the breakpoints of the emission schedule. An empty list removes
+     * the schedule. See `UpdateSettings` for the admin accounts.
+     */
+    public static async SetEmissionSchedule(
+        programId: PublicKey,
+        funder: PublicKey,
+        admin: PublicKey,
+        adminSigner: PublicKey,
+        breakpoints: Breakpoint[]
+    ): Promise<TransactionInstruction> {
+        const settingsId = await Staking.settingsId(programId);
+        const emissionScheduleId = await Staking.emissionScheduleId(programId);
+        const rewardPoolId = await Staking.rewardPoolId(programId);
+
+        const keys: AccountMeta[] = [
+            am(funder, true, true),
+            am(settingsId, false, true),
+            am(admin, false, false),
+            am(adminSigner, true, false),
+            am(emissionScheduleId, false, true),
+            am(SYSVAR_RENT_PUBKEY, false, false),
+            am(SYSVAR_CLOCK_PUBKEY, false, false),
+            am(SystemProgram.programId, false, false),
+            am(rewardPoolId, false, false)
+        ];
+
+        const instruction = new EmissionScheduleSchema({
+            instructionId: Instructions.SetEmissionSchedule,
+            breakpoints
+        });
+
+        return build(programId, keys, instruction);
+    }
+
+    public static async FundRewardPool(
+        programId: PublicKey,
+        funder: PublicKey,
+        funderAuthority: PublicKey,
+        funderAssociated: PublicKey,
+        amount: BN
+    ): Promise<TransactionInstruction> {
+        const settingsId = await Staking.settingsId(programId);
+        const rewardPoolId = await Staking.rewardPoolId(programId);
+
+        const keys: AccountMeta[] = [
+            am(funder, true, true),
+            am(funderAuthority, true, false),
+            am(funderAssociated, false, true),
+            am(rewardPoolId, false, true),
+            am(settingsId, false, true),
+            am(TOKEN_PROGRAM_ID, false, false)
+        ];
+
+        const instruction = new UnsignedAmountSchema({
+            instructionId: Instructions.FundRewardPool,
+            amount
+        });
+
+        return build(programId, keys, instruction);
+    }
+
+    /**
+     * The yield the Beneficiary of `authority` could claim right now, returned
+     * as a little-endian u64 in the return data of a simulation
+     */
+    public static async QueryPending(
+        programId: PublicKey,
+        authority: PublicKey
+    ): Promise<TransactionInstruction> {
+        const keys: AccountMeta[] = [
+            am(await Staking.beneficiary(authority, programId), false, false),
+            am(await Staking.settingsId(programId), false, false),
+            am(await Staking.rewardPoolId(programId), false, false),
+            am(SYSVAR_CLOCK_PUBKEY, false, false)
+        ];
+
+        const instruction = new SimpleSchema({
+            instructionId: Instructions.QueryPending
+        });
+
+        return build(programId, keys, instruction);
+    }
+
+    /**
+     * Correct the stake of the Beneficiary of `authority`. Every Endpoint that
+     * has the authority as primary or secondary has to be passed, `tracked`
+     * holds the stake they should track for it. See `UpdateSettings` for the
+     * admin accounts.
+     */
+    public static async Resync(
+        programId: PublicKey,
+        funder: PublicKey,
+        admin: PublicKey,
+        adminSigner: PublicKey,
+        authority: PublicKey,
+        endpoints: PublicKey[],
+        staked: BN,
+        tracked: BN[]
+    ): Promise<TransactionInstruction> {
+        const settingsId = await Staking.settingsId(programId);
+        const rewardPoolId = await Staking.rewardPoolId(programId);
+        const beneficiary = await Staking.beneficiary(authority, programId);
+
+        const keys: AccountMeta[] = [
+            am(funder, true, true),
+            am(settingsId, false, true),
+            am(admin, false, false),
+            am(adminSigner, true, false),
+            am(beneficiary, false, true),
+            am(SYSVAR_CLOCK_PUBKEY, false, false),
+            am(rewardPoolId, false, false),
+            ...endpoints.map((endpoint) => am(endpoint, false, true))
+        ];
+
+        const instruction = new ResyncSchema({
+            instructionId: Instructions.Resync,
+            staked,
+            tracked
+        });
+
+        return build(programId, keys, instruction);
     }
 }
 
+/** An Endpoint with the authorities of its primary and secondary beneficiary */
+export interface EndpointBeneficiaries {
+    endpoint: PublicKey;
+    primary: PublicKey;
+    secondary: PublicKey;
+}
+
 function am(
     pubkey: PublicKey,
     isSigner: boolean,
@@ -487,21 +1072,73 @@ function am(
     return { pubkey, isSigner, isWritable };
 }
 
+function build(
+    programId: PublicKey,
+    keys: AccountMeta[],
+    instruction: InstructionSchema
+): TransactionInstruction {
+    const instructionData = borsh.serialize(INSTRUCTION_SCHEMA, instruction);
+
+    return new TransactionInstruction({
+        keys: keys,
+        programId,
+        data: Buffer.from(instructionData)
+    });
+}
+
 export function decodeInstructionData(data: Buffer): InstructionSchema {
     switch (data[0]) {
         case Instructions.Initialize:
             return borsh.deserialize(INSTRUCTION_SCHEMA, InitSchema, data);
+        case Instructions.RegisterEndpoint:
+            return borsh.deserialize(
+                INSTRUCTION_SCHEMA,
+                RegisterEndpointSchema,
+                data
+            );
         case Instructions.Stake:
             return borsh.deserialize(INSTRUCTION_SCHEMA, AmountSchema, data);
-        case Instructions.RegisterEndpoint: // fallthrough intentional
         case Instructions.TransferEndpoint:
             return borsh.deserialize(INSTRUCTION_SCHEMA, AuthoritySchema, data);
+        case Instructions.UpdateSettings:
+            return borsh.deserialize(
+                INSTRUCTION_SCHEMA,
+                UpdateSettingsSchema,
+                data
+            );
+        case Instructions.Migrate: // fallthrough intentional
+        case Instructions.Rebond:
+        case Instructions.Redelegate:
+        case Instructions.FundRewardPool:
+            return borsh.deserialize(
+                INSTRUCTION_SCHEMA,
+                UnsignedAmountSchema,
+                data
+            );
+        case Instructions.CloseEndpoint:
+            return borsh.deserialize(
+                INSTRUCTION_SCHEMA,
+                CloseEndpointSchema,
+                data
+            );
+        case Instructions.ChangeSplit:
+            return borsh.deserialize(INSTRUCTION_SCHEMA, SplitSchema, data);
+        case Instructions.SetEmissionSchedule:
+            return borsh.deserialize(
+                INSTRUCTION_SCHEMA,
+                EmissionScheduleSchema,
+                data
+            );
+        case Instructions.Resync:
+            return borsh.deserialize(INSTRUCTION_SCHEMA, ResyncSchema, data);
         default:
             return borsh.deserialize(INSTRUCTION_SCHEMA, SimpleSchema, data);
     }
 }
 
+// the account schema covers the structs nested in instruction data
 export const INSTRUCTION_SCHEMA: borsh.Schema = new Map<any, any>([
+    ...ACCOUNT_SCHEMA,
     [
         SimpleSchema,
         {
@@ -519,6 +1156,16 @@ export const INSTRUCTION_SCHEMA: borsh.Schema = new Map<any, any>([
             ]
         }
     ],
+    [
+        UnsignedAmountSchema,
+        {
+            kind: 'struct',
+            fields: [
+                ['instructionId', 'u8'],
+                ['amount', 'u64']
+            ]
+        }
+    ],
     [
         InitSchema,
         {
@@ -526,7 +1173,19 @@ export const INSTRUCTION_SCHEMA: borsh.Schema = new Map<any, any>([
             fields: [
                 ['instructionId', 'u8'],
                 ['startTime', 'Date'],
-                ['unbondingDuration', 'u64']
+                ['unbondingDuration', 'u64'],
+                ['admin', 'Authority']
+            ]
+        }
+    ],
+    [
+        RegisterEndpointSchema,
+        {
+            kind: 'struct',
+            fields: [
+                ['instructionId', 'u8'],
+                ['authority', 'Authority'],
+                ['split', Split]
             ]
         }
     ],
@@ -539,5 +1198,60 @@ export const INSTRUCTION_SCHEMA: borsh.Schema = new Map<any, any>([
                 ['authority', 'Authority']
             ]
         }
+    ],
+    [
+        UpdateSettingsSchema,
+        {
+            kind: 'struct',
+            fields: [
+                ['instructionId', 'u8'],
+                ['unbondingDuration', { kind: 'option', type: 'u64' }],
+                ['emission', { kind: 'option', type: 'u64' }],
+                ['nextEmissionChange', { kind: 'option', type: 'Date' }],
+                ['splitBounds', { kind: 'option', type: SplitBounds }],
+                ['decay', { kind: 'option', type: EmissionDecay }]
+            ]
+        }
+    ],
+    [
+        CloseEndpointSchema,
+        {
+            kind: 'struct',
+            fields: [
+                ['instructionId', 'u8'],
+                ['sunset', 'Bool']
+            ]
+        }
+    ],
+    [
+        SplitSchema,
+        {
+            kind: 'struct',
+            fields: [
+                ['instructionId', 'u8'],
+                ['split', Split]
+            ]
+        }
+    ],
+    [
+        EmissionScheduleSchema,
+        {
+            kind: 'struct',
+            fields: [
+                ['instructionId', 'u8'],
+                ['breakpoints', [Breakpoint]]
+            ]
+        }
+    ],
+    [
+        ResyncSchema,
+        {
+            kind: 'struct',
+            fields: [
+                ['instructionId', 'u8'],
+                ['staked', 'u64'],
+                ['tracked', ['u64']]
+            ]
+        }
     ]
 ]);
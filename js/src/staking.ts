@@ -1,8 +1,17 @@
 import { Connection, PublicKey } from '@solana/web3.js';
-import { ACCOUNT_SCHEMA, Beneficiary, Endpoint, Settings } from './';
-import * as borsh from 'borsh';
+import {
+    Beneficiary,
+    decodeAccount,
+    EmissionSchedule,
+    Endpoint,
+    Settings
+} from './';
 import { Stake } from './accounts';
 
+export const BPF_LOADER_UPGRADEABLE_PROGRAM_ID = new PublicKey(
+    'BPFLoaderUpgradeab1e11111111111111111111111'
+);
+
 export class Staking {
     programId: PublicKey;
     connection: Connection;
@@ -18,7 +27,26 @@ export class Staking {
         if (account === null)
             throw new Error('Unable to find settings account');
 
-        return borsh.deserialize(ACCOUNT_SCHEMA, Settings, account.data);
+        return decodeAccount(Settings, account.data, settingsId);
+    }
+
+    /** The emission schedule, if the settings reference one */
+    public async getEmissionSchedule(
+        settings: Settings
+    ): Promise<EmissionSchedule | undefined> {
+        if (!settings.hasEmissionSchedule()) return undefined;
+
+        const account = await this.connection.getAccountInfo(
+            settings.emissionSchedule
+        );
+        if (account === null)
+            throw new Error('Unable to find emission schedule account');
+
+        return decodeAccount(
+            EmissionSchedule,
+            account.data,
+            settings.emissionSchedule
+        );
     }
 
     public async getEndpoint(endpointId: PublicKey): Promise<Endpoint> {
@@ -27,7 +55,7 @@ export class Staking {
             throw new Error('Unable to find endpoint account');
         if (!account.owner.equals(this.programId))
             throw new Error('Not a recognized endpoint account');
-        return borsh.deserialize(ACCOUNT_SCHEMA, Endpoint, account.data);
+        return decodeAccount(Endpoint, account.data, endpointId);
     }
 
     public async getStakeWithoutId(
@@ -51,6 +79,25 @@ export class Staking {
         )[0];
     }
 
+    static async emissionScheduleId(programId: PublicKey): Promise<PublicKey> {
+        return (
+            await PublicKey.findProgramAddress(
+                [Buffer.from('emission schedule')],
+                programId
+            )
+        )[0];
+    }
+
+    /** The ProgramData account of the upgradeable program */
+    static async programDataId(programId: PublicKey): Promise<PublicKey> {
+        return (
+            await PublicKey.findProgramAddress(
+                [programId.toBuffer()],
+                BPF_LOADER_UPGRADEABLE_PROGRAM_ID
+            )
+        )[0];
+    }
+
     static async poolAuthorityId(programId: PublicKey): Promise<PublicKey> {
         return (
             await PublicKey.findProgramAddress(
@@ -98,7 +145,7 @@ export class Staking {
         if (account === null)
             throw new Error('Unable to find beneficiary account');
 
-        return borsh.deserialize(ACCOUNT_SCHEMA, Beneficiary, account.data);
+        return decodeAccount(Beneficiary, account.data, beneficiaryId);
     }
 
     static async stakeAddress(
@@ -118,7 +165,7 @@ export class Staking {
         const account = await this.connection.getAccountInfo(stakeId);
         if (account === null) throw new Error('Unable to find stake account');
 
-        return borsh.deserialize(ACCOUNT_SCHEMA, Stake, account.data);
+        return decodeAccount(Stake, account.data, stakeId);
     }
 
     static async stakeFundAddress(
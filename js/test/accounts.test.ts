@@ -1,6 +1,5 @@
 import { expect } from 'chai';
 import 'mocha';
-import * as borsh from 'borsh';
 import { PublicKey } from '@solana/web3.js';
 import BN from 'bn.js';
 import {
@@ -9,36 +8,77 @@ import {
     Stake,
     Settings,
     Beneficiary,
-    ACCOUNT_SCHEMA,
     Authority,
-    AuthorityType
+    Breakpoint,
+    decodeAccount,
+    EmissionDecay,
+    EmissionSchedule,
+    encodeAccount,
+    Split,
+    SplitBounds,
+    Unbonding
 } from '../src';
 
 describe('Settings', () => {
     const raw = Buffer.from([
-        0xc9, 0xf5, 0xf2, 0xcb, 0x38, 0x89, 0x94, 0x9d, 0xa6, 0x2d, 0xb6, 0xe8,
-        0xa4, 0xac, 0x33, 0x06, 0x4a, 0x5f, 0x3f, 0xe7, 0xeb, 0x3b, 0xba, 0x90,
-        0x45, 0x74, 0x2b, 0x04, 0x8c, 0xb2, 0x5d, 0xcd, 0x3c, 0x00, 0x00, 0x00,
-        0x00, 0x00, 0x00, 0x00, 0x36, 0x1e, 0x5a, 0x63, 0x00, 0x00, 0x00, 0x00,
-        0x00, 0xfc, 0x8d, 0x0e, 0x80, 0x00, 0x00, 0x00, 0xa3, 0x96, 0x13, 0x00,
-        0x00, 0x00, 0x00, 0x00, 0x7e, 0x78, 0xe0, 0xf8, 0x78, 0x07, 0x00, 0x00,
-        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x88, 0xed, 0x78, 0x61,
-        0x00, 0x00, 0x00, 0x00
+        0xdf, 0xb3, 0xa3, 0xbe, 0xb1, 0xe0, 0x43, 0xad, 0x01, 0xc9, 0xf5, 0xf2,
+        0xcb, 0x38, 0x89, 0x94, 0x9d, 0xa6, 0x2d, 0xb6, 0xe8, 0xa4, 0xac, 0x33,
+        0x06, 0x4a, 0x5f, 0x3f, 0xe7, 0xeb, 0x3b, 0xba, 0x90, 0x45, 0x74, 0x2b,
+        0x04, 0x8c, 0xb2, 0x5d, 0xcd, 0x00, 0x11, 0x9d, 0x83, 0xba, 0xb3, 0x55,
+        0x8b, 0x28, 0xee, 0xba, 0x2d, 0xe8, 0xec, 0x95, 0xad, 0x03, 0x3f, 0x38,
+        0x2a, 0x12, 0x8f, 0xe1, 0x32, 0xec, 0x76, 0xc0, 0xd4, 0x2f, 0x71, 0x58,
+        0xfc, 0x9d, 0x3c, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0xc4, 0x09, 0x00, 0x00, 0x4c, 0x1d, 0x4c, 0x1d, 0xe8, 0x03, 0x36, 0x1e,
+        0x5a, 0x63, 0x00, 0x00, 0x00, 0x00, 0x00, 0xfc, 0x8d, 0x0e, 0x80, 0x00,
+        0x00, 0x00, 0x09, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x0a, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x80, 0x33, 0xe1, 0x01, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0xe8, 0x76, 0x48, 0x17, 0x00, 0x00, 0x00, 0x5f, 0x05,
+        0xc2, 0x50, 0x29, 0x6c, 0xa8, 0xca, 0xdc, 0xb3, 0x52, 0x3c, 0xbc, 0xd0,
+        0x67, 0x99, 0xd9, 0x36, 0x6b, 0xd6, 0x7c, 0xdf, 0x7b, 0x4a, 0x0e, 0xb0,
+        0xf5, 0x00, 0x55, 0x61, 0x22, 0xf1, 0xa3, 0x96, 0x13, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x7e, 0x78, 0xe0, 0xf8, 0x78, 0x07, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x88, 0xed, 0x78, 0x61, 0x00, 0x00,
+        0x00, 0x00, 0x15, 0xcd, 0x5b, 0x07, 0x00, 0x00, 0x00, 0x00, 0x00, 0xca,
+        0x9a, 0x3b, 0x00, 0x00, 0x00, 0x00
     ]);
 
-    const settings: Settings = borsh.deserialize(ACCOUNT_SCHEMA, Settings, raw);
+    const settings = decodeAccount(Settings, raw);
 
     it('should be equal', () => {
         expect(settings.token).to.eql(
             new PublicKey('EbNTzBUBwP5vZLu71vVqLYdrkzayJ4dLCoQcs6vghZUY')
         );
+        expect(settings.admin).to.eql(
+            Authority.Basic(
+                new PublicKey('2BmEn6gnEDEPstnVW8Ek7gAmsVRhK5J9V1h6idSvcSrQ')
+            )
+        );
 
         expect(settings.unbondingTime.eqn(60)).to.be.true;
+        expect(settings.splitBounds).to.eql(
+            new SplitBounds({
+                min: new Split({ staker: 0, primary: 2_500, secondary: 0 }),
+                max: new Split({
+                    staker: 7_500,
+                    primary: 7_500,
+                    secondary: 1_000
+                })
+            })
+        );
         expect(settings.nextEmissionChange).to.eql(
             new Date('2022-10-27 05:59:18.000+00')
         );
 
         expect(settings.emission.eq(BASE_REWARD)).to.be.true;
+        expect(settings.decay.numerator.eqn(9)).to.be.true;
+        expect(settings.decay.denominator.eqn(10)).to.be.true;
+        expect(settings.decay.period.eqn(31_536_000)).to.be.true;
+        expect(settings.decay.floor.eq(new BN(100_000_000_000))).to.be.true;
+        expect(settings.emissionSchedule).to.eql(
+            new PublicKey('7PvppyrJna8fJzeNN5JUtJShsnAGT8ef7D8nwHKSMh2g')
+        );
+        expect(settings.hasEmissionSchedule()).to.be.true;
+
         expect(settings.totalStake.eqn(1283747)).to.be.true;
 
         expect(settings.rewardPerShare.eq(new BN('8216152930430', 10))).to.be
@@ -46,24 +86,98 @@ describe('Settings', () => {
         expect(settings.lastReward).to.eql(
             new Date('2021-10-27 06:11:20.000+00')
         );
+        expect(settings.liability.eqn(123_456_789)).to.be.true;
+        expect(settings.totalFunded.eqn(1_000_000_000)).to.be.true;
+    });
+
+    it('should encode', () => {
+        expect(encodeAccount(Settings, settings)).to.eql(raw);
+    });
+
+    it('should not accrue without time passing', () => {
+        expect(
+            settings.calculateRewardPerShare(settings.lastReward).eq(
+                settings.rewardPerShare
+            )
+        ).to.be.true;
+    });
+
+    it('should cap the accrual at the reward pool', () => {
+        const now = new Date('2021-10-28 06:11:20.000+00');
+        const uncapped = settings.calculateRewardPerShare(now);
+        expect(uncapped.gt(settings.rewardPerShare)).to.be.true;
+
+        // nothing left in the pool beyond the liability
+        expect(
+            settings
+                .calculateRewardPerShare(now, undefined, settings.liability)
+                .eq(settings.rewardPerShare)
+        ).to.be.true;
+    });
+});
+
+describe('EmissionDecay', () => {
+    it('should not decay below the floor', () => {
+        const decay = new EmissionDecay({
+            numerator: new BN(1),
+            denominator: new BN(2),
+            period: new BN(100),
+            floor: new BN(300)
+        });
+        expect(decay.apply(new BN(1_000)).eqn(500)).to.be.true;
+        expect(decay.apply(new BN(500)).eqn(300)).to.be.true;
+        // an emission that was set below the floor is not raised
+        expect(decay.apply(new BN(200)).eqn(200)).to.be.true;
+        expect(EmissionDecay.DEFAULT.apply(new BN(1_000)).eqn(900)).to.be.true;
+    });
+});
+
+describe('Split', () => {
+    it('should split like the program', () => {
+        const split = new Split({
+            staker: 5_000,
+            primary: 4_000,
+            secondary: 1_000
+        });
+
+        expect(
+            Split.DEFAULT.apply(new BN(1_001)).map((share) => share.toNumber())
+        ).to.eql([476, 475, 50]);
+        expect(
+            split.apply(new BN(12_345)).map((share) => share.toNumber())
+        ).to.eql([6_173, 4_938, 1_234]);
+    });
+
+    it('should check the bounds', () => {
+        expect(SplitBounds.UNRESTRICTED.contains(Split.DEFAULT)).to.be.true;
+        expect(
+            SplitBounds.UNRESTRICTED.contains(
+                new Split({ staker: 5_000, primary: 5_000, secondary: 1 })
+            )
+        ).to.be.false;
     });
 });
 
 describe('Endpoint', () => {
     const raw = Buffer.from([
-        0xf2, 0xaf, 0x72, 0x61, 0x00, 0x00, 0x00, 0x00, 0xc0, 0xd4, 0x01, 0x00,
-        0x00, 0x00, 0x00, 0x00, 0x01, 0x06, 0x16, 0x19, 0x9c, 0x9f, 0x29, 0x4a,
-        0x9e, 0x10, 0x92, 0x58, 0xc9, 0xb2, 0x5b, 0xb6, 0x7d, 0x20, 0x94, 0x4a,
-        0x31, 0x04, 0x28, 0xb9, 0x8d, 0x51, 0xf8, 0x57, 0xc6, 0xa1, 0x49, 0x91,
-        0x3c, 0x5f, 0x05, 0xc2, 0x50, 0x29, 0x6c, 0xa8, 0xca, 0xdc, 0xb3, 0x52,
-        0x3c, 0xbc, 0xd0, 0x67, 0x99, 0xd9, 0x36, 0x6b, 0xd6, 0x7c, 0xdf, 0x7b,
-        0x4a, 0x0e, 0xb0, 0xf5, 0x00, 0x55, 0x61, 0x22, 0xf1, 0x92, 0x17, 0x8a,
-        0x23, 0x7c, 0x3d, 0x90, 0x8a, 0x17, 0x36, 0x0d, 0x2e, 0xb6, 0x46, 0x6a,
-        0xcd, 0xf1, 0x45, 0x11, 0x50, 0xc4, 0x5a, 0x28, 0xa5, 0xa9, 0x22, 0x33,
-        0x65, 0xc9, 0xb1, 0x77, 0xa9
+        0xb4, 0x4c, 0x03, 0x37, 0x69, 0x87, 0x9a, 0xc6, 0x01, 0xf2, 0xaf, 0x72,
+        0x61, 0x00, 0x00, 0x00, 0x00, 0xc0, 0xd4, 0x01, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x01, 0x06, 0x16, 0x19, 0x9c, 0x9f, 0x29, 0x4a, 0x9e, 0x10, 0x92,
+        0x58, 0xc9, 0xb2, 0x5b, 0xb6, 0x7d, 0x20, 0x94, 0x4a, 0x31, 0x04, 0x28,
+        0xb9, 0x8d, 0x51, 0xf8, 0x57, 0xc6, 0xa1, 0x49, 0x91, 0x3c, 0x5f, 0x05,
+        0xc2, 0x50, 0x29, 0x6c, 0xa8, 0xca, 0xdc, 0xb3, 0x52, 0x3c, 0xbc, 0xd0,
+        0x67, 0x99, 0xd9, 0x36, 0x6b, 0xd6, 0x7c, 0xdf, 0x7b, 0x4a, 0x0e, 0xb0,
+        0xf5, 0x00, 0x55, 0x61, 0x22, 0xf1, 0x92, 0x17, 0x8a, 0x23, 0x7c, 0x3d,
+        0x90, 0x8a, 0x17, 0x36, 0x0d, 0x2e, 0xb6, 0x46, 0x6a, 0xcd, 0xf1, 0x45,
+        0x11, 0x50, 0xc4, 0x5a, 0x28, 0xa5, 0xa9, 0x22, 0x33, 0x65, 0xc9, 0xb1,
+        0x77, 0xa9, 0x8e, 0x12, 0x8e, 0x12, 0xf4, 0x01, 0xa8, 0xde, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x70, 0x17, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00
     ]);
 
-    const endpoint: Endpoint = borsh.deserialize(ACCOUNT_SCHEMA, Endpoint, raw);
+    const endpoint = decodeAccount(Endpoint, raw);
 
     it('should be equal', () => {
         expect(endpoint.creationDate).to.be.eql(
@@ -85,57 +199,76 @@ describe('Endpoint', () => {
         expect(endpoint.secondary).to.be.eql(
             new PublicKey('AqHLrtuQ31UDRDdgRmy6XtVzz7twSjB5K9LeNYa6QSiL')
         );
+
+        expect(endpoint.split).to.be.eql(Split.DEFAULT);
+        expect(endpoint.primaryStake.eqn(57_000)).to.be.true;
+        expect(endpoint.secondaryStake.eqn(6_000)).to.be.true;
+        expect(endpoint.isSunset()).to.be.false;
+    });
+
+    it('should encode', () => {
+        expect(encodeAccount(Endpoint, endpoint)).to.eql(raw);
     });
 });
 
-describe('Endpoint without secondary', () => {
+describe('Sunset Endpoint without secondary', () => {
     const raw = Buffer.from([
-        0xf2, 0xaf, 0x72, 0x61, 0x00, 0x00, 0x00, 0x00, 0xc0, 0xd4, 0x01, 0x00,
-        0x00, 0x00, 0x00, 0x00, 0x01, 0x06, 0x16, 0x19, 0x9c, 0x9f, 0x29, 0x4a,
-        0x9e, 0x10, 0x92, 0x58, 0xc9, 0xb2, 0x5b, 0xb6, 0x7d, 0x20, 0x94, 0x4a,
-        0x31, 0x04, 0x28, 0xb9, 0x8d, 0x51, 0xf8, 0x57, 0xc6, 0xa1, 0x49, 0x91,
-        0x3c, 0x5f, 0x05, 0xc2, 0x50, 0x29, 0x6c, 0xa8, 0xca, 0xdc, 0xb3, 0x52,
-        0x3c, 0xbc, 0xd0, 0x67, 0x99, 0xd9, 0x36, 0x6b, 0xd6, 0x7c, 0xdf, 0x7b,
-        0x4a, 0x0e, 0xb0, 0xf5, 0x00, 0x55, 0x61, 0x22, 0xf1, 0x00, 0x00, 0x00,
+        0xb4, 0x4c, 0x03, 0x37, 0x69, 0x87, 0x9a, 0xc6, 0x01, 0xf2, 0xaf, 0x72,
+        0x61, 0x00, 0x00, 0x00, 0x00, 0xc0, 0xd4, 0x01, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x01, 0x06, 0x16, 0x19, 0x9c, 0x9f, 0x29, 0x4a, 0x9e, 0x10, 0x92,
+        0x58, 0xc9, 0xb2, 0x5b, 0xb6, 0x7d, 0x20, 0x94, 0x4a, 0x31, 0x04, 0x28,
+        0xb9, 0x8d, 0x51, 0xf8, 0x57, 0xc6, 0xa1, 0x49, 0x91, 0x3c, 0x5f, 0x05,
+        0xc2, 0x50, 0x29, 0x6c, 0xa8, 0xca, 0xdc, 0xb3, 0x52, 0x3c, 0xbc, 0xd0,
+        0x67, 0x99, 0xd9, 0x36, 0x6b, 0xd6, 0x7c, 0xdf, 0x7b, 0x4a, 0x0e, 0xb0,
+        0xf5, 0x00, 0x55, 0x61, 0x22, 0xf1, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
         0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
         0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
-        0x00, 0x00, 0x00, 0x00, 0x00
+        0x00, 0x00, 0x88, 0x13, 0x88, 0x13, 0x00, 0x00, 0x60, 0xea, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x11, 0x9d, 0x83, 0xba, 0xb3, 0x55, 0x8b, 0x28, 0xee, 0xba, 0x2d, 0xe8,
+        0xec, 0x95, 0xad, 0x03, 0x3f, 0x38, 0x2a, 0x12, 0x8f, 0xe1, 0x32, 0xec,
+        0x76, 0xc0, 0xd4, 0x2f, 0x71, 0x58, 0xfc, 0x9d
     ]);
 
-    const endpoint: Endpoint = borsh.deserialize(ACCOUNT_SCHEMA, Endpoint, raw);
+    const endpoint = decodeAccount(Endpoint, raw);
 
     it('should be equal', () => {
-        expect(endpoint.creationDate).to.be.eql(
-            new Date('2021-10-22 12:34:58.000+00')
-        );
-
-        expect(endpoint.totalStake.eqn(120000)).to.be.true;
-
-        expect(endpoint.owner).to.be.eql(
-            Authority.NFT(
-                new PublicKey('QkzWg35HR5SsXRLHwUHnc4b1ch5YRijXp1sjxHeSCWf')
-            )
+        expect(endpoint.secondary).to.be.eql(PublicKey.default);
+        expect(endpoint.split).to.be.eql(
+            new Split({ staker: 5_000, primary: 5_000, secondary: 0 })
         );
-
-        expect(endpoint.primary).to.be.eql(
-            new PublicKey('7PvppyrJna8fJzeNN5JUtJShsnAGT8ef7D8nwHKSMh2g')
+        expect(endpoint.primaryStake.eqn(60_000)).to.be.true;
+        expect(endpoint.secondaryStake.eqn(0)).to.be.true;
+        expect(endpoint.sunset).to.be.eql(
+            new PublicKey('2BmEn6gnEDEPstnVW8Ek7gAmsVRhK5J9V1h6idSvcSrQ')
         );
-
-        expect(endpoint.secondary).to.be.eql(PublicKey.default);
+        expect(endpoint.isSunset()).to.be.true;
     });
 });
 
 describe('Stake', () => {
+    // padded with the unused space of the unbonding queue
     const raw = Buffer.from([
-        0xce, 0xd6, 0x10, 0x61, 0x00, 0x00, 0x00, 0x00, 0x4c, 0x40, 0x0f, 0x00,
-        0x00, 0x00, 0x00, 0x00, 0x11, 0x9d, 0x83, 0xba, 0xb3, 0x55, 0x8b, 0x28,
-        0xee, 0xba, 0x2d, 0xe8, 0xec, 0x95, 0xad, 0x03, 0x3f, 0x38, 0x2a, 0x12,
-        0x8f, 0xe1, 0x32, 0xec, 0x76, 0xc0, 0xd4, 0x2f, 0x71, 0x58, 0xfc, 0x9d,
-        0xe2, 0xde, 0x10, 0x61, 0x00, 0x00, 0x00, 0x00, 0xf4, 0x01, 0x00, 0x00,
-        0x00, 0x00, 0x00, 0x00
+        0x96, 0xc5, 0xb0, 0x1d, 0x37, 0x84, 0x70, 0x95, 0x01, 0xce, 0xd6, 0x10,
+        0x61, 0x00, 0x00, 0x00, 0x00, 0x4c, 0x40, 0x0f, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x11, 0x9d, 0x83, 0xba, 0xb3, 0x55, 0x8b, 0x28, 0xee, 0xba, 0x2d,
+        0xe8, 0xec, 0x95, 0xad, 0x03, 0x3f, 0x38, 0x2a, 0x12, 0x8f, 0xe1, 0x32,
+        0xec, 0x76, 0xc0, 0xd4, 0x2f, 0x71, 0x58, 0xfc, 0x9d, 0x8e, 0x12, 0x8e,
+        0x12, 0xf4, 0x01, 0xf4, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02,
+        0x00, 0x00, 0x00, 0xe2, 0xde, 0x10, 0x61, 0x00, 0x00, 0x00, 0x00, 0x2c,
+        0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xcd, 0xb0, 0x63, 0x00,
+        0x00, 0x00, 0x00, 0xc8, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00
     ]);
 
-    const stake: Stake = borsh.deserialize(ACCOUNT_SCHEMA, Stake, raw);
+    const stake = decodeAccount(Stake, raw);
 
     it('should be equal', () => {
         expect(stake.creationDate).to.be.eql(
@@ -147,28 +280,36 @@ describe('Stake', () => {
         expect(stake.staker).to.be.eql(
             new PublicKey('2BmEn6gnEDEPstnVW8Ek7gAmsVRhK5J9V1h6idSvcSrQ')
         );
+        expect(stake.split).to.be.eql(Split.DEFAULT);
 
-        expect(stake.unbondingEnd).to.be.eql(
+        expect(stake.unbondingAmount.eqn(500)).to.be.true;
+        expect(stake.unbonding).to.be.length(2);
+        expect(stake.unbonding[0]).to.be.instanceOf(Unbonding);
+        expect(stake.unbonding[0].end).to.be.eql(
             new Date('2021-08-09 07:53:06.000+00')
         );
-        expect(stake.unbondingAmount.eqn(500)).to.be.true;
+        expect(stake.unbonding[0].amount.eqn(300)).to.be.true;
+        expect(stake.unbonding[1].end).to.be.eql(
+            new Date('2023-01-01 00:00:00.000+00')
+        );
+        expect(stake.unbonding[1].amount.eqn(200)).to.be.true;
+        expect(
+            stake.withdrawable(new Date('2022-01-01 00:00:00.000+00')).eqn(300)
+        ).to.be.true;
     });
 });
 
 describe('Beneficiary', () => {
     const raw = Buffer.from([
-        0x11, 0x9d, 0x83, 0xba, 0xb3, 0x55, 0x8b, 0x28, 0xee, 0xba, 0x2d, 0xe8,
-        0xec, 0x95, 0xad, 0x03, 0x3f, 0x38, 0x2a, 0x12, 0x8f, 0xe1, 0x32, 0xec,
-        0x76, 0xc0, 0xd4, 0x2f, 0x71, 0x58, 0xfc, 0x9d, 0x5f, 0x76, 0x1b, 0x00,
-        0x00, 0x00, 0x00, 0x00, 0x91, 0xa6, 0xdc, 0x04, 0x00, 0x00, 0x00, 0x00,
-        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00
+        0x2d, 0xb6, 0xe0, 0xc6, 0xc5, 0xff, 0xe9, 0x21, 0x01, 0x11, 0x9d, 0x83,
+        0xba, 0xb3, 0x55, 0x8b, 0x28, 0xee, 0xba, 0x2d, 0xe8, 0xec, 0x95, 0xad,
+        0x03, 0x3f, 0x38, 0x2a, 0x12, 0x8f, 0xe1, 0x32, 0xec, 0x76, 0xc0, 0xd4,
+        0x2f, 0x71, 0x58, 0xfc, 0x9d, 0x5f, 0x76, 0x1b, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x91, 0xa6, 0xdc, 0x04, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00
     ]);
 
-    const beneficiary: Beneficiary = borsh.deserialize(
-        ACCOUNT_SCHEMA,
-        Beneficiary,
-        raw
-    );
+    const beneficiary = decodeAccount(Beneficiary, raw);
 
     it('should be equal', () => {
         expect(beneficiary.authority).to.be.eql(
@@ -178,5 +319,58 @@ describe('Beneficiary', () => {
         expect(beneficiary.holding.eqn(0)).to.be.true;
         expect(beneficiary.rewardDebt.eq(new BN('81569425'))).to.be.true;
         expect(beneficiary.staked.eqn(1799775)).to.be.true;
+        expect(beneficiary.isEmpty()).to.be.false;
+    });
+
+    it('should reject other account types', () => {
+        const address = new PublicKey(
+            'Svg3TsfzMNY8HbJzAYbVhGXeTJFwFfWbVirJSfyqPxd'
+        );
+        expect(() => decodeAccount(Stake, raw, address)).to.throw(
+            `Stake account ${address.toBase58()} has invalid type Beneficiary`
+        );
+    });
+
+    it('should require migrating the unversioned layout', () => {
+        expect(() => decodeAccount(Beneficiary, raw.slice(9))).to.throw(
+            'Beneficiary account requires migration'
+        );
+
+        const outdated = Buffer.from(raw);
+        outdated[8] = 0;
+        expect(() => decodeAccount(Beneficiary, outdated)).to.throw(
+            'Beneficiary account requires migration'
+        );
+    });
+});
+
+describe('EmissionSchedule', () => {
+    const raw = Buffer.from([
+        0x7c, 0x31, 0xfe, 0x38, 0x23, 0x62, 0xb5, 0x58, 0x01, 0x02, 0x00, 0x00,
+        0x00, 0x00, 0xcd, 0xb0, 0x63, 0x00, 0x00, 0x00, 0x00, 0x00, 0xa0, 0xdb,
+        0x21, 0x5d, 0x00, 0x00, 0x00, 0x80, 0x00, 0x92, 0x65, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00
+    ]);
+
+    const schedule = decodeAccount(EmissionSchedule, raw);
+
+    it('should be equal', () => {
+        expect(schedule.breakpoints).to.be.length(2);
+        expect(schedule.breakpoints[0]).to.be.instanceOf(Breakpoint);
+        expect(schedule.breakpoints[0].start).to.be.eql(
+            new Date('2023-01-01 00:00:00.000+00')
+        );
+        expect(
+            schedule.breakpoints[0].yearlyEmission.eq(new BN(400_000_000_000))
+        ).to.be.true;
+        expect(schedule.breakpoints[1].start).to.be.eql(
+            new Date('2024-01-01 00:00:00.000+00')
+        );
+        expect(schedule.breakpoints[1].yearlyEmission.eqn(0)).to.be.true;
+        expect(
+            schedule.breakpoints[0]
+                .emission(new BN(31_536_000 / 2))
+                .eq(new BN(200_000_000_000))
+        ).to.be.true;
     });
 });
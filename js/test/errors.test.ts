@@ -35,11 +35,11 @@ describe('Errors', () => {
             )
         ).to.eql(17);
 
-        for (
-            let i = 0;
-            i < StakingErrors.SecondaryAuthorityKeysDoNotMatch;
-            i++
-        ) {
+        expect(
+            parseError(new Error('custom program error: 0x34')).message
+        ).to.eql('STAKING-ERROR 0x34: ResyncOutOfBounds');
+
+        for (let i = 0; i <= StakingErrors.ResyncOutOfBounds; i++) {
             expect(
                 parseError(
                     new Error(
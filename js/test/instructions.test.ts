@@ -4,48 +4,208 @@ import * as borsh from 'borsh';
 import {
     AmountSchema,
     Authority,
-    AuthorityType,
+    Breakpoint,
     decodeInstructionData,
     AuthoritySchema,
+    EmissionDecay,
     InitSchema,
     Instruction,
     Instructions,
-    INSTRUCTION_SCHEMA
+    INSTRUCTION_SCHEMA,
+    RegisterEndpointSchema,
+    Split,
+    Staking,
+    UnsignedAmountSchema,
+    UpdateSettingsSchema
 } from '../src';
-import { PublicKey, Transaction } from '@solana/web3.js';
+import { PublicKey, SystemProgram, Transaction } from '@solana/web3.js';
 import BN from 'bn.js';
 
 const programId = new PublicKey('A7PR2hfpVDsBqd83mD6WSEr9Z9CvDNJ9FehcvvLdvuC2');
 const funder = new PublicKey('F5AeZLFDdEnAPtfxHMKLTzNYNa9kLvGPM9b8dJzWpHGZ');
 const mint = new PublicKey('Q2P36HbwEBwxTSj8QhiMscbA21vBi7edJKbsb9KjBRM');
+const admin = new PublicKey('2BmEn6gnEDEPstnVW8Ek7gAmsVRhK5J9V1h6idSvcSrQ');
 
 describe('Serialization', () => {
     it('Initialize', async () => {
+        const upgradeAuthority = new PublicKey(
+            '73aD1aXy4Z1arEYHCVxefmZHm4PgHTY7fxXTD34bSirf'
+        );
         const instruction = await Instruction.Initialize(
             programId,
             funder,
             mint,
+            Authority.Basic(admin),
+            upgradeAuthority,
             new Date('2021-07-02 08:45:51.000+00'),
             60
         );
 
         expect(instruction.programId).to.eql(programId);
-        expect(instruction.keys).to.be.length(8);
-
-        const data = Buffer.from(
-            borsh.serialize(
-                INSTRUCTION_SCHEMA,
-                new InitSchema({
-                    instructionId: Instructions.Initialize,
-                    startTime: new Date('2021-07-02 08:45:51.000+00'),
-                    unbondingDuration: new BN(60)
+        expect(instruction.keys).to.be.length(11);
+        expect(instruction.keys[5].pubkey).to.eql(admin);
+        expect(instruction.keys[9].pubkey).to.eql(
+            await Staking.programDataId(programId)
+        );
+        expect(instruction.keys[10].pubkey).to.eql(upgradeAuthority);
+        expect(instruction.keys[10].isSigner).to.be.true;
+
+        // as encoded by the program
+        const data = Buffer.from([
+        0x00, 0x3f, 0xd2, 0xde, 0x60, 0x00, 0x00, 0x00, 0x00, 0x3c, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x11, 0x9d, 0x83, 0xba, 0xb3, 0x55,
+        0x8b, 0x28, 0xee, 0xba, 0x2d, 0xe8, 0xec, 0x95, 0xad, 0x03, 0x3f, 0x38,
+        0x2a, 0x12, 0x8f, 0xe1, 0x32, 0xec, 0x76, 0xc0, 0xd4, 0x2f, 0x71, 0x58,
+        0xfc, 0x9d
+        ]);
+
+        expect(instruction.data).to.be.eql(data);
+    });
+
+    it('RegisterEndpoint', async () => {
+        const instruction = await Instruction.RegisterEndpoint(
+            programId,
+            funder,
+            new PublicKey('Svg3TsfzMNY8HbJzAYbVhGXeTJFwFfWbVirJSfyqPxd'),
+            Authority.NFT(
+                new PublicKey('2bvn5d4krBDdCXEMH9KKHPx8xGauv6wEsaPZWAyYnUJh')
+            ),
+            admin,
+            undefined,
+            new Split({ staker: 5_000, primary: 4_000, secondary: 1_000 })
+        );
+
+        expect(instruction.keys).to.be.length(11);
+        expect(instruction.keys[5].pubkey).to.eql(PublicKey.default);
+        expect(instruction.keys[10].pubkey).to.eql(
+            await Staking.settingsId(programId)
+        );
+
+        const data = Buffer.from([
+        0x01, 0x01, 0x17, 0xce, 0x3d, 0x94, 0xa6, 0x67, 0x18, 0xa6, 0x29, 0xc5,
+        0x50, 0x3e, 0x44, 0x8f, 0x79, 0x6f, 0xeb, 0xb1, 0xbd, 0x1f, 0x94, 0x8f,
+        0x1b, 0xb2, 0x36, 0x46, 0x6a, 0xe4, 0x1b, 0x3c, 0x98, 0x86, 0x88, 0x13,
+        0xa0, 0x0f, 0xe8, 0x03
+        ]);
+
+        expect(instruction.data).to.be.eql(data);
+    });
+
+    it('UpdateSettings', async () => {
+        const instruction = await Instruction.UpdateSettings(
+            programId,
+            funder,
+            admin,
+            admin,
+            {
+                emission: new BN(1_000),
+                nextEmissionChange: new Date('2023-01-01 00:00:00.000+00'),
+                decay: EmissionDecay.DEFAULT
+            }
+        );
+
+        expect(instruction.keys).to.be.length(6);
+        expect(instruction.keys[5].pubkey).to.eql(
+            await Staking.rewardPoolId(programId)
+        );
+
+        const data = Buffer.from([
+        0x08, 0x00, 0x01, 0xe8, 0x03, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01,
+        0x00, 0xcd, 0xb0, 0x63, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x09, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x0a, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x80, 0x33, 0xe1, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00
+        ]);
+
+        expect(instruction.data).to.be.eql(data);
+
+        const reverse = decodeInstructionData(data) as UpdateSettingsSchema;
+        expect(reverse.unbondingDuration).to.be.undefined;
+        expect(reverse.emission?.eqn(1_000)).to.be.true;
+        expect(reverse.splitBounds).to.be.undefined;
+        expect(reverse.decay?.numerator.eqn(9)).to.be.true;
+    });
+
+    it('CloseEndpoint', async () => {
+        const instruction = await Instruction.CloseEndpoint(
+            programId,
+            funder,
+            new PublicKey('Svg3TsfzMNY8HbJzAYbVhGXeTJFwFfWbVirJSfyqPxd'),
+            admin,
+            admin,
+            funder,
+            true
+        );
+
+        expect(instruction.data).to.be.eql(Buffer.from([0x0d, 0x01]));
+    });
+
+    it('SetEmissionSchedule', async () => {
+        const instruction = await Instruction.SetEmissionSchedule(
+            programId,
+            funder,
+            admin,
+            admin,
+            [
+                new Breakpoint({
+                    start: new Date('2023-01-01 00:00:00.000+00'),
+                    yearlyEmission: new BN(400_000_000_000)
+                }),
+                new Breakpoint({
+                    start: new Date('2024-01-01 00:00:00.000+00'),
+                    yearlyEmission: new BN(0)
                 })
-            )
+            ]
+        );
+
+        expect(instruction.keys).to.be.length(9);
+        expect(instruction.keys[4].pubkey).to.eql(
+            await Staking.emissionScheduleId(programId)
         );
 
+        const data = Buffer.from([
+        0x10, 0x02, 0x00, 0x00, 0x00, 0x00, 0xcd, 0xb0, 0x63, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0xa0, 0xdb, 0x21, 0x5d, 0x00, 0x00, 0x00, 0x80, 0x00, 0x92,
+        0x65, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00
+        ]);
+
         expect(instruction.data).to.be.eql(data);
     });
 
+    it('Resync', async () => {
+        const endpoints = [
+            new PublicKey('2VqNb6Y1CmrZefiVL2shgudkxrTF9VuqDteqwV8jJ7D5'),
+            new PublicKey('Svg3TsfzMNY8HbJzAYbVhGXeTJFwFfWbVirJSfyqPxd')
+        ];
+        const instruction = await Instruction.Resync(
+            programId,
+            funder,
+            admin,
+            admin,
+            funder,
+            endpoints,
+            new BN(1_425),
+            [new BN(100), new BN(50)]
+        );
+
+        expect(instruction.keys).to.be.length(9);
+        expect(instruction.keys[4].pubkey).to.eql(
+            await Staking.beneficiary(funder, programId)
+        );
+        expect(instruction.keys[7].pubkey).to.eql(endpoints[0]);
+        expect(instruction.keys[7].isWritable).to.be.true;
+        expect(instruction.keys[8].pubkey).to.eql(endpoints[1]);
+        expect(instruction.data).to.be.eql(
+            Buffer.from([
+            0x13, 0x91, 0x05, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x02, 0x00, 0x00, 0x00,
+            0x64, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x32, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00
+            ])
+        );
+    });
+
     it('batching initialize + stake', async () => {
         const community = new PublicKey(
             '2VqNb6Y1CmrZefiVL2shgudkxrTF9VuqDteqwV8jJ7D5'
@@ -64,6 +224,24 @@ describe('Serialization', () => {
             '73aD1aXy4Z1arEYHCVxefmZHm4PgHTY7fxXTD34bSirf'
         );
 
+        const stake = await Instruction.WithEmissionSchedule(
+            await Instruction.Stake(
+                programId,
+                funder,
+                staker,
+                assoc,
+                community,
+                primary,
+                secondary,
+                666n
+            )
+        );
+        expect(stake.keys).to.be.length(16);
+        expect(stake.keys[14].pubkey).to.eql(SystemProgram.programId);
+        expect(stake.keys[15].pubkey).to.eql(
+            await Staking.emissionScheduleId(programId)
+        );
+
         const tx = new Transaction()
             .add(
                 await Instruction.InitializeStake(
@@ -74,18 +252,7 @@ describe('Serialization', () => {
                     mint
                 )
             )
-            .add(
-                await Instruction.Stake(
-                    programId,
-                    funder,
-                    staker,
-                    assoc,
-                    community,
-                    primary,
-                    secondary,
-                    666n
-                )
-            );
+            .add(stake);
         tx.feePayer = funder;
         tx.recentBlockhash = '11111111111111111111111111111111';
 
@@ -132,7 +299,8 @@ describe('Serialization', () => {
         const init = new InitSchema({
             instructionId: Instructions.Initialize, // only this uses init schema
             startTime: new Date('2021-07-02 08:45:51.000+00'),
-            unbondingDuration: new BN(60)
+            unbondingDuration: new BN(60),
+            admin: Authority.Basic(admin)
         });
 
         const data = Buffer.from(borsh.serialize(INSTRUCTION_SCHEMA, init));
@@ -143,6 +311,7 @@ describe('Serialization', () => {
         expect(
             (reverse as InitSchema).unbondingDuration.eq(init.unbondingDuration)
         ).to.be.true;
+        expect((reverse as InitSchema).admin).to.be.eql(init.admin);
     });
 
     it('decode unknown amount instruction data', async () => {
@@ -156,12 +325,24 @@ describe('Serialization', () => {
         expect(reverse).to.be.eql(init);
     });
 
+    it('decode unknown unsigned amount instruction data', async () => {
+        const init = new UnsignedAmountSchema({
+            instructionId: Instructions.Rebond,
+            amount: new BN(1234234)
+        });
+
+        const data = Buffer.from(borsh.serialize(INSTRUCTION_SCHEMA, init));
+        const reverse = decodeInstructionData(data) as UnsignedAmountSchema;
+        expect(reverse.instructionId).to.be.eql(init.instructionId);
+        expect(reverse.amount.eq(init.amount)).to.be.true;
+    });
+
     it('decode unknown authority instruction data', async () => {
         const authority = Authority.Basic(
             new PublicKey('2bvn5d4krBDdCXEMH9KKHPx8xGauv6wEsaPZWAyYnUJh')
         );
         const init = new AuthoritySchema({
-            instructionId: Instructions.RegisterEndpoint, // uses simple schema
+            instructionId: Instructions.TransferEndpoint,
             authority
         });
 
@@ -169,4 +350,18 @@ describe('Serialization', () => {
         const reverse = decodeInstructionData(data);
         expect(reverse).to.be.eql(init);
     });
+
+    it('decode unknown register endpoint instruction data', async () => {
+        const init = new RegisterEndpointSchema({
+            instructionId: Instructions.RegisterEndpoint,
+            authority: Authority.NFT(
+                new PublicKey('2bvn5d4krBDdCXEMH9KKHPx8xGauv6wEsaPZWAyYnUJh')
+            ),
+            split: Split.DEFAULT
+        });
+
+        const data = Buffer.from(borsh.serialize(INSTRUCTION_SCHEMA, init));
+        const reverse = decodeInstructionData(data);
+        expect(reverse).to.be.eql(init);
+    });
 });
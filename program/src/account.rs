@@ -1,3 +1,5 @@
+use std::fmt;
+
 use borsh::{BorshDeserialize, BorshSerialize};
 use solana_program::account_info::AccountInfo;
 use solana_program::clock::UnixTimestamp;
@@ -8,8 +10,9 @@ use solana_program::{
 };
 use spl_token::state::{Account, Mint};
 
-use crate::error::StakingError;
-use crate::{PRECISION, SECONDS_PER_YEAR};
+use crate::error::{AccountDataError, StakingError};
+use crate::event::StakingEvent;
+use crate::{PRECISION, SECONDS_PER_YEAR, ZERO_KEY};
 
 /// Verifies that an account is a valid mint for an NFT
 #[macro_export]
@@ -38,29 +41,249 @@ macro_rules! is_nft_mint {
     };
 }
 
+/// The type of data stored in an account owned by the program
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum AccountType {
+    Settings,
+    Endpoint,
+    Beneficiary,
+    Stake,
+    EmissionSchedule,
+}
+
+impl AccountType {
+    pub const ALL: [AccountType; 5] = [
+        AccountType::Settings,
+        AccountType::Endpoint,
+        AccountType::Beneficiary,
+        AccountType::Stake,
+        AccountType::EmissionSchedule,
+    ];
+
+    /// The first 8 bytes of `sha256("account:<type>")`
+    pub const fn discriminator(&self) -> [u8; 8] {
+        match self {
+            AccountType::Settings => [0xdf, 0xb3, 0xa3, 0xbe, 0xb1, 0xe0, 0x43, 0xad],
+            AccountType::Endpoint => [0xb4, 0x4c, 0x03, 0x37, 0x69, 0x87, 0x9a, 0xc6],
+            AccountType::Beneficiary => [0x2d, 0xb6, 0xe0, 0xc6, 0xc5, 0xff, 0xe9, 0x21],
+            AccountType::Stake => [0x96, 0xc5, 0xb0, 0x1d, 0x37, 0x84, 0x70, 0x95],
+            AccountType::EmissionSchedule => [0x7c, 0x31, 0xfe, 0x38, 0x23, 0x62, 0xb5, 0x58],
+        }
+    }
+
+    /// Identify the type of an account from the start of its data
+    pub fn from_discriminator(data: &[u8]) -> Option<AccountType> {
+        Self::ALL
+            .iter()
+            .find(|t| data.len() >= 8 && data[..8] == t.discriminator())
+            .copied()
+    }
+}
+
+impl fmt::Display for AccountType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+/// The flags an account is required to be passed with to an instruction
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Access {
+    pub writable: bool,
+    pub signer: bool,
+}
+
+impl Access {
+    pub const READ: Access = Access {
+        writable: false,
+        signer: false,
+    };
+    pub const WRITE: Access = Access {
+        writable: true,
+        signer: false,
+    };
+    pub const WRITE_SIGNER: Access = Access {
+        writable: true,
+        signer: true,
+    };
+
+    /// Verifies that the account has the required flags
+    pub fn verify(&self, info: &AccountInfo) -> Result<(), ProgramError> {
+        if self.writable && !info.is_writable {
+            msg!("account {} is not writable", info.key);
+            return Err(StakingError::AccountNotWritable.into());
+        }
+        if self.signer && !info.is_signer {
+            msg!("account {} is not a signer", info.key);
+            return Err(StakingError::AccountNotSigner.into());
+        }
+        Ok(())
+    }
+}
+
+/// Account data starts with an 8 byte discriminator identifying the type of
+/// account, followed by a single byte that identifies the layout of the Borsh
+/// encoded struct after it. Accounts with an outdated layout have to be
+/// upgraded via the `Migrate` instruction before use.
+pub trait Versioned: BorshSerialize + BorshDeserialize {
+    /// The type of account
+    const ACCOUNT_TYPE: AccountType;
+    /// The current layout version
+    const VERSION: u8;
+    /// Error returned when the account data cannot be decoded
+    const INVALID_ACCOUNT: StakingError;
+
+    /// Encode the account data with the discriminator and version prefix
+    fn to_account_data(&self) -> Result<Vec<u8>, ProgramError> {
+        let mut data = Self::ACCOUNT_TYPE.discriminator().to_vec();
+        data.push(Self::VERSION);
+        self.serialize(&mut data)?;
+        Ok(data)
+    }
+
+    /// Write the account data into an account, zero-filling any space left over
+    fn save(&self, info: &AccountInfo) -> Result<(), ProgramError> {
+        let data = self.to_account_data()?;
+        let mut dst = info.data.borrow_mut();
+        if data.len() > dst.len() {
+            return Err(ProgramError::AccountDataTooSmall);
+        }
+        dst[..data.len()].copy_from_slice(&data);
+        dst[data.len()..].iter_mut().for_each(|b| *b = 0);
+        Ok(())
+    }
+
+    /// Decode account data that has a discriminator and version prefix
+    fn from_account_data(data: &[u8]) -> Result<Self, AccountDataError> {
+        if data.is_empty() {
+            return Err(Self::INVALID_ACCOUNT.into());
+        }
+
+        match AccountType::from_discriminator(data) {
+            Some(t) if t == Self::ACCOUNT_TYPE => {}
+            Some(found) => {
+                return Err(AccountDataError::InvalidType {
+                    expected: Self::ACCOUNT_TYPE,
+                    found,
+                })
+            }
+            // accounts created before discriminators were introduced
+            None => return Err(StakingError::AccountRequiresMigration.into()),
+        }
+
+        match data[8..].split_first() {
+            Some((&version, mut body)) if version == Self::VERSION => {
+                Self::deserialize(&mut body).map_err(|_| Self::INVALID_ACCOUNT.into())
+            }
+            Some(_) => Err(StakingError::AccountRequiresMigration.into()),
+            None => Err(Self::INVALID_ACCOUNT.into()),
+        }
+    }
+
+    /// Validate and decode a program account from `AccountInfo`.
+    ///
+    /// Every account loader goes through this function. Checks the access flags,
+    /// the owner and the discriminator. Program derived addresses are verified
+    /// by the caller beforehand.
+    fn load(info: &AccountInfo, program_id: &Pubkey, access: Access) -> Result<Self, ProgramError> {
+        access.verify(info)?;
+
+        if info.data_is_empty() {
+            return Err(Self::INVALID_ACCOUNT.into());
+        }
+        if info.owner != program_id {
+            msg!(
+                "{} account {} has invalid owner {}",
+                Self::ACCOUNT_TYPE,
+                info.key,
+                info.owner
+            );
+            return Err(StakingError::InvalidAccountOwner.into());
+        }
+
+        Self::from_account_data(&info.data.borrow()).map_err(|e| {
+            if let AccountDataError::InvalidType { .. } = e {
+                msg!("account {}: {}", info.key, e);
+            }
+            e.into()
+        })
+    }
+}
+
+/// A geometric decay of emissions. At the end of every period, the emission
+/// is multiplied by `numerator / denominator`, but never decays below `floor`.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, BorshDeserialize, BorshSerialize)]
+pub struct EmissionDecay {
+    pub numerator: u64,
+    pub denominator: u64,
+    /// Length of a period in seconds
+    pub period: u64,
+    /// The lowest emission that decay reduces to, 0 for no floor
+    pub floor: u64,
+}
+
+impl EmissionDecay {
+    /// 10% decay every year without a floor
+    pub const DEFAULT: EmissionDecay = EmissionDecay {
+        numerator: 9,
+        denominator: 10,
+        period: SECONDS_PER_YEAR as u64,
+        floor: 0,
+    };
+
+    /// True if the schedule decays (or stays flat) with a non-empty period
+    pub fn is_valid(&self) -> bool {
+        self.denominator > 0 && self.numerator <= self.denominator && self.period > 0
+    }
+
+    /// The emission of the period following one with `emission`
+    pub fn apply(&self, emission: u64) -> u64 {
+        let decayed = (emission as u128 * self.numerator as u128 / self.denominator as u128) as u64;
+        // an emission that was set below the floor is not raised
+        decayed.max(self.floor.min(emission))
+    }
+}
+
 /// Account to hold global variables commonly used by instructions
 #[repr(C)]
 #[derive(Debug, PartialEq, BorshDeserialize, BorshSerialize, Clone, Copy, Eq)]
 pub struct Settings {
     /// SPL Token Mint accepted by instructions ("ZEE")
     pub token: Pubkey,
+    /// The authority allowed to change the settings
+    pub admin: Authority,
     /// Time (in seconds) that funds are locked after unstaking
     pub unbonding_duration: u64,
+    /// The range of splits that Endpoints are allowed to use
+    pub split_bounds: SplitBounds,
 
-    /// The time at which emissions is reduced by 25%
+    /// The time at which emissions are reduced next according to `decay`
     pub next_emission_change: UnixTimestamp,
     /// Amount of ZEE paid out during the current period
     pub emission: u64,
+    /// How emissions are reduced at the end of every period
+    pub decay: EmissionDecay,
+    /// The EmissionSchedule account with explicit emission changes.
+    /// `ZERO_KEY` if there is none.
+    pub emission_schedule: Pubkey,
 
     // tokenomics variables
     // for a more detailed explanation of the algorithm and variables
     // see https://www.mathcha.io/editor/j4V1YiODsYQu8dee0NiO39Z05cePQvk0f9qPex6
-    /// Total amount of ZEE staked
+    /// Total amount of ZEE the beneficiaries that earn rewards hold a share of.
+    /// Equals the amount delegated to the Endpoints, apart from the share of
+    /// Endpoints without a secondary and stakes that are still accounted under
+    /// an older split of their Endpoint.
     pub total_stake: u64,
     /// The yield for every 1 ZEE staked, multiplied by PRECISION
     pub reward_per_share: u128,
     /// Last time the pool reward was updated
     pub last_reward: UnixTimestamp,
+    /// Amount of ZEE emitted as yield that has not been paid out of the
+    /// reward pool yet
+    pub liability: u64,
+    /// Total amount of ZEE deposited with `FundRewardPool`
+    pub total_funded: u64,
 }
 
 impl Settings {
@@ -85,10 +308,27 @@ impl Settings {
     pub fn from_account_info(
         info: &AccountInfo,
         program_id: &Pubkey,
+        access: Access,
     ) -> Result<Settings, ProgramError> {
         Self::verify_program_address(info.key, program_id)?;
-        Self::try_from_slice(&info.data.borrow())
-            .map_err(|_| StakingError::ProgramNotInitialized.into())
+        Self::load(info, program_id, access)
+    }
+
+    /// The reward per share for `seconds` at the current emission
+    fn period_reward(&self, seconds: u128) -> u128 {
+        if self.total_stake == 0 {
+            return 0;
+        }
+
+        PRECISION
+            .checked_mul(self.emission as u128)
+            .unwrap()
+            .checked_div(self.decay.period as u128)
+            .unwrap()
+            .checked_div(self.total_stake as u128)
+            .unwrap()
+            .checked_mul(seconds)
+            .unwrap()
     }
 
     /// Update the Reward per Share variable
@@ -96,8 +336,18 @@ impl Settings {
     /// The basic formula is:
     ///   `reward per share += <time elapsed> * <emissions during that period> / <total amount staked>`
     ///
-    /// Emissions are automatically reduced by 10% every year
-    pub fn update_rewards(&mut self, now: UnixTimestamp) {
+    /// Emissions are automatically reduced at the end of every period according to
+    /// the `decay` schedule. If Settings references an EmissionSchedule, it has to be
+    /// passed in and the emission is set to each breakpoint's value as it is reached.
+    ///
+    /// Emissions stop once the liability would exceed `pool_balance`, the amount of
+    /// ZEE in the reward pool.
+    pub fn update_rewards(
+        &mut self,
+        now: UnixTimestamp,
+        schedule: Option<&EmissionSchedule>,
+        pool_balance: u64,
+    ) {
         if now <= self.last_reward {
             return;
         }
@@ -105,56 +355,14 @@ impl Settings {
         let old_last_reward = self.last_reward;
         let old_rps = self.reward_per_share;
 
-        if self.total_stake > 0 {
-            let mut reward: u128 = 0;
-
-            // emissions reduce by 10% every year, so if the period between `last_reward`
-            // and `now` crosses the year threshold, we calculate the period in each year
-            // separately
-            // the math works across multiple year gaps though in production this would
-            // never occur
-            while now >= self.next_emission_change {
-                let seconds = (self
-                    .next_emission_change
-                    .checked_sub(self.last_reward)
-                    .unwrap()) as u128;
-                reward = reward
-                    .checked_add(
-                        PRECISION
-                            .checked_mul(self.emission as u128)
-                            .unwrap()
-                            .checked_div(SECONDS_PER_YEAR)
-                            .unwrap()
-                            .checked_div(self.total_stake as u128)
-                            .unwrap()
-                            .checked_mul(seconds)
-                            .unwrap(),
-                    )
-                    .unwrap();
-
-                self.last_reward = self.next_emission_change;
-                self.next_emission_change += SECONDS_PER_YEAR as i64;
-                self.emission = (self.emission as u128 * 9 / 10) as u64; // 90%
-            }
-
-            let seconds = (now - self.last_reward) as u128;
-            reward = reward
-                .checked_add(
-                    PRECISION
-                        .checked_mul(self.emission as u128)
-                        .unwrap()
-                        .checked_div(SECONDS_PER_YEAR)
-                        .unwrap()
-                        .checked_div(self.total_stake as u128)
-                        .unwrap()
-                        .checked_mul(seconds)
-                        .unwrap(),
-                )
-                .unwrap();
-
-            self.reward_per_share = self.reward_per_share.checked_add(reward).unwrap();
+        let (emitted, shortfall) = self.accrue(now, schedule, pool_balance);
+        if shortfall > 0 {
+            msg!(
+                "reward pool can only cover {} of {} emitted zee",
+                emitted,
+                emitted + shortfall
+            );
         }
-        self.last_reward = now;
 
         msg!(
             "updated pool rewards. last_reward: {} -> {} ({} seconds), reward_per_share: {} -> {}, stake = {}",
@@ -165,9 +373,86 @@ impl Settings {
             self.reward_per_share,
             self.total_stake
         );
+        StakingEvent::RewardsUpdated {
+            last_reward: self.last_reward,
+            reward_per_share: self.reward_per_share,
+            emission: self.emission,
+            total_stake: self.total_stake,
+            liability: self.liability,
+        }
+        .emit();
+    }
+
+    /// The math behind `update_rewards` without any logging. Returns the amount of
+    /// ZEE emitted and the amount the reward pool could not cover.
+    pub fn accrue(
+        &mut self,
+        now: UnixTimestamp,
+        schedule: Option<&EmissionSchedule>,
+        pool_balance: u64,
+    ) -> (u64, u64) {
+        if now <= self.last_reward {
+            return (0, 0);
+        }
+
+        let breakpoints = schedule.map_or(&[][..], |s| &s.breakpoints[..]);
+        let mut reward: u128 = 0;
+
+        // emissions change at the end of every period and at every breakpoint, so if
+        // the time between `last_reward` and `now` crosses one of those, we calculate
+        // the time in between each of them separately
+        // the math works across multiple period gaps though in production this would
+        // rarely occur
+        loop {
+            let breakpoint = breakpoints
+                .iter()
+                .find(|b| b.start > self.last_reward && b.start <= now);
+            let boundary = match breakpoint {
+                Some(b) if b.start < self.next_emission_change => b.start,
+                _ if self.next_emission_change <= now => self.next_emission_change,
+                _ => break,
+            };
+
+            let seconds = (boundary - self.last_reward) as u128;
+            reward = reward.checked_add(self.period_reward(seconds)).unwrap();
+            self.last_reward = boundary;
+
+            if boundary == self.next_emission_change {
+                self.next_emission_change += self.decay.period as i64;
+                self.emission = self.decay.apply(self.emission);
+            }
+            if let Some(b) = breakpoint.filter(|b| b.start == boundary) {
+                self.emission = b.emission(self.decay.period);
+            }
+        }
+
+        let seconds = (now - self.last_reward) as u128;
+        reward = reward.checked_add(self.period_reward(seconds)).unwrap();
+
+        // never emit more than the reward pool can pay out
+        let available = pool_balance.saturating_sub(self.liability) as u128;
+        let mut emitted = reward * self.total_stake as u128 / PRECISION;
+        let mut shortfall = 0;
+        if emitted > available {
+            shortfall = (emitted - available) as u64;
+            reward = available * PRECISION / self.total_stake as u128;
+            emitted = reward * self.total_stake as u128 / PRECISION;
+        }
+        self.liability += emitted as u64;
+
+        self.reward_per_share = self.reward_per_share.checked_add(reward).unwrap();
+        self.last_reward = now;
+
+        (emitted as u64, shortfall)
     }
 }
 
+impl Versioned for Settings {
+    const ACCOUNT_TYPE: AccountType = AccountType::Settings;
+    const VERSION: u8 = 1;
+    const INVALID_ACCOUNT: StakingError = StakingError::ProgramNotInitialized;
+}
+
 /// The PDA that owns the reward pool associated account
 #[derive(Debug, PartialEq, Clone, Copy, Eq)]
 pub struct PoolAuthority {}
@@ -217,6 +502,13 @@ pub enum Authority {
 }
 
 impl Authority {
+    /// The wallet address or the NFT mint
+    pub fn address(&self) -> Pubkey {
+        match self {
+            Authority::Basic(pubkey) | Authority::NFT(pubkey) => *pubkey,
+        }
+    }
+
     /// Verifies that an account matches the authority's type
     pub fn verify(&self, account: &AccountInfo) -> Result<(), ProgramError> {
         match self {
@@ -265,14 +557,113 @@ impl Authority {
     }
 }
 
+/// Basis points that make up the whole of a stake
+pub const BASIS_POINTS: u16 = 10_000;
+
+/// The ratios in basis points at which the stake (and with it the yield) of
+/// an Endpoint is divided between the (staker, primary, secondary).
+/// The three shares always add up to `BASIS_POINTS`.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, BorshDeserialize, BorshSerialize)]
+pub struct Split {
+    pub staker: u16,
+    pub primary: u16,
+    pub secondary: u16,
+}
+
+impl Split {
+    /// The split used before it was configurable: 47.5%, 47.5%, 5%
+    pub const DEFAULT: Split = Split {
+        staker: 4_750,
+        primary: 4_750,
+        secondary: 500,
+    };
+
+    /// True if the shares add up to 100%
+    pub fn is_valid(&self) -> bool {
+        self.staker as u32 + self.primary as u32 + self.secondary as u32 == BASIS_POINTS as u32
+    }
+
+    /// Divides an amount into the (staker, primary, secondary) components.
+    ///
+    /// The secondary share is taken first, the primary share is taken from the
+    /// rest, and remainders go to the staker.
+    pub fn apply(&self, amount: u64) -> (u64, u64, u64) {
+        let secondary = (amount as u128 * self.secondary as u128 / BASIS_POINTS as u128) as u64;
+        let rest_points = BASIS_POINTS.saturating_sub(self.secondary) as u128;
+        let primary = match rest_points {
+            0 => 0,
+            _ => ((amount - secondary) as u128 * self.primary as u128 / rest_points) as u64,
+        };
+
+        (amount - primary - secondary, primary, secondary)
+    }
+}
+
+/// The protocol-wide range of splits that Endpoints are allowed to use.
+/// Every share of an Endpoint's split must be within `min` and `max`.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, BorshDeserialize, BorshSerialize)]
+pub struct SplitBounds {
+    pub min: Split,
+    pub max: Split,
+}
+
+impl SplitBounds {
+    /// Bounds that allow every valid split
+    pub const UNRESTRICTED: SplitBounds = SplitBounds {
+        min: Split {
+            staker: 0,
+            primary: 0,
+            secondary: 0,
+        },
+        max: Split {
+            staker: BASIS_POINTS,
+            primary: BASIS_POINTS,
+            secondary: BASIS_POINTS,
+        },
+    };
+
+    /// True if the bounds are ordered and leave room for at least one valid split
+    pub fn is_valid(&self) -> bool {
+        let sum =
+            |split: &Split| split.staker as u32 + split.primary as u32 + split.secondary as u32;
+
+        self.min.staker <= self.max.staker
+            && self.min.primary <= self.max.primary
+            && self.min.secondary <= self.max.secondary
+            && sum(&self.min) <= BASIS_POINTS as u32
+            && sum(&self.max) >= BASIS_POINTS as u32
+    }
+
+    /// Verify that a split is valid and within the bounds
+    pub fn verify(&self, split: &Split) -> Result<(), ProgramError> {
+        if !split.is_valid() {
+            msg!("split {:?} does not add up to {}", split, BASIS_POINTS);
+            return Err(StakingError::InvalidSplit.into());
+        }
+
+        if split.staker < self.min.staker
+            || split.staker > self.max.staker
+            || split.primary < self.min.primary
+            || split.primary > self.max.primary
+            || split.secondary < self.min.secondary
+            || split.secondary > self.max.secondary
+        {
+            msg!("split {:?} is outside of bounds {:?}", split, self);
+            return Err(StakingError::SplitOutOfBounds.into());
+        }
+
+        Ok(())
+    }
+}
+
 /// An Endpoint is a the entity that someone can stake against to share yield.
 /// Each endpoint has an owner, which is the entity in charge of making
 /// decisions about the Endpoint itself, once that functionality is implemented.
-/// The Primary beneficiary receives 45% of the staker's yield, the secondary beneficiary
-/// receives 5% of the staker's yield.
+/// The stake is divided between the staker, the primary and the secondary beneficiary
+/// according to the Endpoint's `split`, by default 47.5%, 47.5% and 5%.
 ///
 /// It is possible for an Endpoint to have no secondary Beneficiary, in which case the
-/// the 5% stay in the reward pool.
+/// the secondary share stays in the reward pool.
 #[derive(Debug, PartialEq, BorshDeserialize, BorshSerialize, Clone, Copy, Eq)]
 pub struct Endpoint {
     /// The time the endpoint was initialized
@@ -281,26 +672,43 @@ pub struct Endpoint {
     pub total_stake: u64,
     /// The owner of the endpoint
     pub owner: Authority,
-    /// The primary beneficiary receiving 45% of yield
+    /// The primary beneficiary receiving the primary share of yield
     pub primary: Pubkey,
-    /// The secondary beneficiary receiving 5% of yield
+    /// The secondary beneficiary receiving the secondary share of yield
     pub secondary: Pubkey,
+    /// How stake is divided between the staker, primary and secondary
+    pub split: Split,
+    /// The stake accounted to the primary, its share of every stake under the
+    /// Endpoint's split. `ChangeSplit` moves it to a new split right away.
+    pub primary_stake: u64,
+    /// The stake accounted to the secondary, see `primary_stake`
+    pub secondary_stake: u64,
+    /// If the endpoint is being sunset, the address that receives the rent
+    /// once all stake has been withdrawn. `ZERO_KEY` otherwise.
+    pub sunset: Pubkey,
 }
 
 impl Endpoint {
+    /// True if the endpoint no longer accepts new stake
+    pub fn is_sunset(&self) -> bool {
+        self.sunset != ZERO_KEY
+    }
+
     pub fn from_account_info(
         info: &AccountInfo,
         program_id: &Pubkey,
+        access: Access,
     ) -> Result<Endpoint, ProgramError> {
-        if info.owner != program_id {
-            return Err(StakingError::InvalidEndpointAccount.into());
-        }
-
-        Self::try_from_slice(&info.data.borrow())
-            .map_err(|_| StakingError::InvalidEndpointAccount.into())
+        Self::load(info, program_id, access)
     }
 }
 
+impl Versioned for Endpoint {
+    const ACCOUNT_TYPE: AccountType = AccountType::Endpoint;
+    const VERSION: u8 = 1;
+    const INVALID_ACCOUNT: StakingError = StakingError::InvalidEndpointAccount;
+}
+
 /// A Beneficiary receives yield based on the amount of ZEE staked.
 #[derive(Debug, PartialEq, BorshDeserialize, BorshSerialize, Clone, Copy, Eq)]
 pub struct Beneficiary {
@@ -334,10 +742,10 @@ impl Beneficiary {
         info: &AccountInfo,
         authority: &Pubkey,
         program_id: &Pubkey,
+        access: Access,
     ) -> Result<Beneficiary, ProgramError> {
         Self::verify_program_address(info.key, authority, program_id)?;
-        Self::try_from_slice(&info.data.borrow())
-            .map_err(|_| StakingError::InvalidBeneficiaryAccount.into())
+        Self::load(info, program_id, access)
     }
 
     /// True if there is no authority
@@ -345,6 +753,13 @@ impl Beneficiary {
         self.authority == Pubkey::default()
     }
 
+    /// The Beneficiary of `ZERO_KEY` holds the secondary's share of Endpoints
+    /// without a secondary. Nobody can claim its yield, so its stake is left
+    /// out of the total stake the rewards are emitted for.
+    pub fn earns_rewards(&self) -> bool {
+        self.authority != ZERO_KEY
+    }
+
     /// The total amount of theoretical ZEE owed if the amount staked had been staked
     /// since the beginning of time.
     pub fn calculate_holding(&self, reward_per_share: u128) -> u64 {
@@ -365,8 +780,23 @@ impl Beneficiary {
     }
 }
 
-/// The account that holds the data for a staker staking with a specific Endpoint.
+impl Versioned for Beneficiary {
+    const ACCOUNT_TYPE: AccountType = AccountType::Beneficiary;
+    const VERSION: u8 = 1;
+    const INVALID_ACCOUNT: StakingError = StakingError::InvalidBeneficiaryAccount;
+}
+
+/// A tranche of unstaked funds that is locked until its unbonding time has passed
 #[derive(Debug, PartialEq, BorshDeserialize, BorshSerialize, Clone, Copy, Eq)]
+pub struct Unbonding {
+    /// The time after which the funds can be withdrawn
+    pub end: UnixTimestamp,
+    /// The amount of funds
+    pub amount: u64,
+}
+
+/// The account that holds the data for a staker staking with a specific Endpoint.
+#[derive(Debug, PartialEq, BorshDeserialize, BorshSerialize, Clone, Eq)]
 pub struct Stake {
     /// Time the account was initiated
     pub creation_date: UnixTimestamp,
@@ -377,13 +807,24 @@ pub struct Stake {
     /// The staker's address
     pub staker: Pubkey,
 
-    /// The most recent time an amount was unstaked
-    pub unbonding_end: UnixTimestamp,
+    /// The Endpoint's split the shares of the stake were last accounted under.
+    /// Lags behind the Endpoint's split after an owner changes it, until the
+    /// next time the stake is touched.
+    pub split: Split,
+
     /// The total amount of pending funds
     pub unbonding_amount: u64,
+    /// Every unstake adds a tranche, in the order they were unstaked.
+    /// Holds at most `MAX_UNBONDING` tranches.
+    pub unbonding: Vec<Unbonding>,
 }
 
 impl Stake {
+    /// The maximum number of unbonding tranches
+    pub const MAX_UNBONDING: usize = 8;
+    /// The size of the account with a full unbonding queue
+    pub const LEN: usize = 8 + 1 + 8 + 8 + 32 + 6 + 8 + 4 + Self::MAX_UNBONDING * 16;
+
     pub fn fund_address(endpoint: &Pubkey, staker: &Pubkey, program_id: &Pubkey) -> (Pubkey, u8) {
         Pubkey::find_program_address(
             &[b"stake fund", &endpoint.to_bytes(), &staker.to_bytes()],
@@ -430,29 +871,167 @@ impl Stake {
         endpoint: &Pubkey,
         staker: &Pubkey,
         program_id: &Pubkey,
+        access: Access,
     ) -> Result<Stake, ProgramError> {
         Self::verify_program_address(info.key, endpoint, staker, program_id)?;
-        Self::try_from_slice(&info.data.borrow())
-            .map_err(|_| StakingError::StakerInvalidStakeAccount.into())
+        Self::load(info, program_id, access)
+    }
+
+    /// True if another tranche can be added to the unbonding queue
+    pub fn can_unbond(&self) -> bool {
+        self.unbonding.len() < Self::MAX_UNBONDING
+    }
+
+    /// Add unstaked funds to the unbonding queue. Fails if the queue is full,
+    /// tranches that have ended have to be released first.
+    pub fn unbond(&mut self, amount: u64, end: UnixTimestamp) -> Result<(), ProgramError> {
+        if !self.can_unbond() {
+            return Err(StakingError::UnbondingQueueFull.into());
+        }
+        self.unbonding_amount += amount;
+        self.unbonding.push(Unbonding { end, amount });
+        Ok(())
+    }
+
+    /// Remove every tranche whose unbonding time has passed.
+    /// Returns the amount of funds released.
+    pub fn release_unbonded(&mut self, now: UnixTimestamp) -> u64 {
+        let released = self
+            .unbonding
+            .iter()
+            .filter(|tranche| tranche.end <= now)
+            .map(|tranche| tranche.amount)
+            .sum();
+        self.unbonding.retain(|tranche| tranche.end > now);
+        self.unbonding_amount -= released;
+        released
+    }
+
+    /// Take funds out of the unbonding queue, starting with the most recent tranche.
+    /// The amount must not exceed `unbonding_amount`.
+    pub fn rebond(&mut self, mut amount: u64) {
+        self.unbonding_amount -= amount;
+        while amount > 0 {
+            let last = self.unbonding.last_mut().unwrap();
+            if last.amount > amount {
+                last.amount -= amount;
+                amount = 0;
+            } else {
+                amount -= last.amount;
+                self.unbonding.pop();
+            }
+        }
+    }
+}
+
+impl Versioned for Stake {
+    const ACCOUNT_TYPE: AccountType = AccountType::Stake;
+    const VERSION: u8 = 1;
+    const INVALID_ACCOUNT: StakingError = StakingError::StakerInvalidStakeAccount;
+}
+
+/// A change of emission at a specific time
+#[derive(Debug, PartialEq, BorshDeserialize, BorshSerialize, Clone, Copy, Eq)]
+pub struct Breakpoint {
+    /// The time the emission changes
+    pub start: UnixTimestamp,
+    /// The new emission in ZEE per year, regardless of the decay period
+    pub yearly_emission: u64,
+}
+
+impl Breakpoint {
+    /// The emission per decay period of `period` seconds, the unit of
+    /// `Settings::emission`
+    pub fn emission(&self, period: u64) -> u64 {
+        (self.yearly_emission as u128 * period as u128 / SECONDS_PER_YEAR) as u64
     }
 }
 
+/// An explicit list of emission changes set by the admin, used to model
+/// events like token unlocks or promotional boosts.
+/// Geometric decay still applies between breakpoints, unless the decay is set
+/// to be flat.
+#[derive(Debug, PartialEq, BorshDeserialize, BorshSerialize, Clone, Eq)]
+pub struct EmissionSchedule {
+    /// Breakpoints in ascending order of their start
+    pub breakpoints: Vec<Breakpoint>,
+}
+
+impl EmissionSchedule {
+    /// The maximum number of breakpoints
+    pub const MAX_BREAKPOINTS: usize = 32;
+    /// The size of the account with all breakpoints used
+    pub const LEN: usize = 8 + 1 + 4 + Self::MAX_BREAKPOINTS * 16;
+
+    /// PDA of the emission schedule account
+    pub fn program_address(program_id: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[b"emission schedule"], program_id)
+    }
+
+    /// Verify if an address matches the emission schedule PDA
+    pub fn verify_program_address(
+        address: &Pubkey,
+        program_id: &Pubkey,
+    ) -> Result<u8, ProgramError> {
+        match Self::program_address(program_id) {
+            (real, seed) if real == *address => Ok(seed),
+            _ => Err(StakingError::InvalidEmissionScheduleAccount.into()),
+        }
+    }
+
+    pub fn from_account_info(
+        info: &AccountInfo,
+        program_id: &Pubkey,
+        access: Access,
+    ) -> Result<EmissionSchedule, ProgramError> {
+        Self::verify_program_address(info.key, program_id)?;
+        Self::load(info, program_id, access)
+    }
+
+    /// True if the breakpoints fit in the account, are strictly ascending, and
+    /// all start after `after`
+    pub fn is_valid(&self, after: UnixTimestamp) -> bool {
+        self.breakpoints.len() <= Self::MAX_BREAKPOINTS
+            && self
+                .breakpoints
+                .iter()
+                .try_fold(
+                    after,
+                    |prev, b| if b.start > prev { Some(b.start) } else { None },
+                )
+                .is_some()
+    }
+}
+
+impl Versioned for EmissionSchedule {
+    const ACCOUNT_TYPE: AccountType = AccountType::EmissionSchedule;
+    const VERSION: u8 = 1;
+    const INVALID_ACCOUNT: StakingError = StakingError::InvalidEmissionScheduleAccount;
+}
+
 #[cfg(test)]
 mod tests {
 
     use super::*;
     use crate::BASE_REWARD;
+    use solana_program::hash::hashv;
 
     #[test]
     pub fn test_settings_serialization() {
         let v = Settings {
             token: Pubkey::new_unique(),
+            admin: Authority::Basic(Pubkey::new_unique()),
             unbonding_duration: 10 * 3600 * 24,
+            split_bounds: SplitBounds::UNRESTRICTED,
             next_emission_change: 98123798352345,
             emission: 23458972935823,
+            decay: EmissionDecay::DEFAULT,
+            emission_schedule: ZERO_KEY,
 
             reward_per_share: 348923452348342394u128,
             last_reward: 293458234234,
+            liability: 0,
+            total_funded: 0,
             total_stake: 9821429382935u64,
         };
 
@@ -462,17 +1041,228 @@ mod tests {
         assert_eq!(v, ret);
     }
 
+    #[test]
+    pub fn test_versioned_account_data() {
+        let beneficiary = Beneficiary {
+            authority: Pubkey::new_unique(),
+            staked: 1234,
+            reward_debt: 5678,
+            holding: 9012,
+        };
+
+        let mut data = beneficiary.to_account_data().unwrap();
+        assert_eq!(data[..8], AccountType::Beneficiary.discriminator());
+        assert_eq!(data[8], Beneficiary::VERSION);
+        assert_eq!(Ok(beneficiary), Beneficiary::from_account_data(&data));
+        let mismatch = Stake::from_account_data(&data).unwrap_err();
+        assert_eq!(
+            AccountDataError::InvalidType {
+                expected: AccountType::Stake,
+                found: AccountType::Beneficiary,
+            },
+            mismatch
+        );
+        assert_eq!(
+            "expected Stake account, found Beneficiary account",
+            mismatch.to_string()
+        );
+
+        // loading checks the owner before the type
+        let program_id = Pubkey::new_unique();
+        let key = Pubkey::new_unique();
+        let mut lamports = 0;
+        let mut account_data = data.clone();
+        let mut info = AccountInfo::new(
+            &key,
+            false,
+            false,
+            &mut lamports,
+            &mut account_data,
+            &program_id,
+            false,
+            0,
+        );
+        assert_eq!(
+            Err(StakingError::InvalidAccountType.into()),
+            Stake::load(&info, &program_id, Access::READ)
+        );
+        assert_eq!(
+            Ok(beneficiary),
+            Beneficiary::load(&info, &program_id, Access::READ)
+        );
+        let other = Pubkey::new_unique();
+        info.owner = &other;
+        assert_eq!(
+            Err(StakingError::InvalidAccountOwner.into()),
+            Stake::load(&info, &program_id, Access::READ)
+        );
+
+        data[8] = Beneficiary::VERSION + 1;
+        assert_eq!(
+            Err(StakingError::AccountRequiresMigration.into()),
+            Beneficiary::from_account_data(&data)
+        );
+        assert_eq!(
+            Err(StakingError::InvalidBeneficiaryAccount.into()),
+            Beneficiary::from_account_data(&[])
+        );
+        assert_eq!(
+            Err(StakingError::AccountRequiresMigration.into()),
+            Beneficiary::from_account_data(&data[8..])
+        );
+    }
+
+    #[test]
+    pub fn test_stake_unbonding_queue() {
+        let mut stake = Stake {
+            creation_date: 0,
+            total_stake: 0,
+            staker: Pubkey::new_unique(),
+            split: Split::DEFAULT,
+            unbonding_amount: 0,
+            unbonding: vec![],
+        };
+
+        for i in 1..=Stake::MAX_UNBONDING as i64 {
+            stake.unbond(100, i * 10).unwrap();
+        }
+        assert_eq!(stake.to_account_data().unwrap().len(), Stake::LEN);
+
+        // a full queue does not accept more tranches until some are released
+        assert!(!stake.can_unbond());
+        assert_eq!(
+            stake.unbond(50, 1000),
+            Err(StakingError::UnbondingQueueFull.into())
+        );
+        assert_eq!(stake.unbonding_amount, 800);
+
+        assert_eq!(stake.release_unbonded(9), 0);
+        assert_eq!(stake.release_unbonded(25), 200);
+        assert_eq!(stake.unbonding.len(), Stake::MAX_UNBONDING - 2);
+        assert_eq!(stake.unbonding_amount, 600);
+
+        stake.unbond(50, 1000).unwrap();
+        assert_eq!(
+            stake.unbonding.last(),
+            Some(&Unbonding {
+                end: 1000,
+                amount: 50
+            })
+        );
+        assert_eq!(stake.unbonding_amount, 650);
+
+        stake.rebond(200);
+        assert_eq!(stake.unbonding_amount, 450);
+        assert_eq!(
+            stake.unbonding.last(),
+            Some(&Unbonding {
+                end: 70,
+                amount: 50
+            })
+        );
+
+        assert_eq!(stake.release_unbonded(1000), 450);
+        assert!(stake.unbonding.is_empty());
+        assert_eq!(stake.unbonding_amount, 0);
+    }
+
+    #[test]
+    pub fn test_account_type_discriminator() {
+        for account_type in AccountType::ALL.iter() {
+            let hash = hashv(&[format!("account:{}", account_type).as_bytes()]);
+            assert_eq!(account_type.discriminator(), hash.to_bytes()[..8]);
+            assert_eq!(
+                Some(*account_type),
+                AccountType::from_discriminator(&account_type.discriminator())
+            );
+        }
+        assert_eq!(None, AccountType::from_discriminator(&[0; 8]));
+        assert_eq!(None, AccountType::from_discriminator(&[]));
+    }
+
+    #[test]
+    pub fn test_split() {
+        // the default split matches the original hardcoded math
+        for amount in 0..=10_000u64 {
+            let secondary = amount / 20;
+            let primary = (amount - secondary) / 2;
+            assert_eq!(
+                Split::DEFAULT.apply(amount),
+                (amount - primary - secondary, primary, secondary)
+            );
+        }
+
+        let creator = Split {
+            staker: 8_000,
+            primary: 1_500,
+            secondary: 500,
+        };
+        assert!(creator.is_valid());
+        assert_eq!(creator.apply(1_000), (800, 150, 50));
+        let (staker, primary, secondary) = creator.apply(u64::MAX);
+        assert_eq!(
+            staker as u128 + primary as u128 + secondary as u128,
+            u64::MAX as u128
+        );
+
+        let all_secondary = Split {
+            staker: 0,
+            primary: 0,
+            secondary: BASIS_POINTS,
+        };
+        assert_eq!(all_secondary.apply(1_000), (0, 0, 1_000));
+
+        assert!(!Split {
+            staker: 5_000,
+            primary: 5_000,
+            secondary: 1,
+        }
+        .is_valid());
+
+        let bounds = SplitBounds {
+            min: Split {
+                staker: 4_000,
+                primary: 0,
+                secondary: 500,
+            },
+            max: Split {
+                staker: 9_000,
+                primary: 5_000,
+                secondary: 500,
+            },
+        };
+        assert!(bounds.is_valid());
+        assert!(bounds.verify(&Split::DEFAULT).is_ok());
+        assert!(bounds.verify(&creator).is_ok());
+        assert_eq!(
+            bounds.verify(&all_secondary),
+            Err(StakingError::SplitOutOfBounds.into())
+        );
+        assert!(SplitBounds::UNRESTRICTED.is_valid());
+        assert!(!SplitBounds {
+            min: creator,
+            max: Split::DEFAULT,
+        }
+        .is_valid());
+    }
+
     #[test]
     pub fn test_settings_update_rewards() {
         let base = Settings {
             token: Pubkey::new_unique(),
+            admin: Authority::Basic(Pubkey::new_unique()),
             unbonding_duration: 0,
+            split_bounds: SplitBounds::UNRESTRICTED,
 
             next_emission_change: SECONDS_PER_YEAR as i64,
             emission: BASE_REWARD as u64,
+            decay: EmissionDecay::DEFAULT,
+            emission_schedule: ZERO_KEY,
 
             reward_per_share: 0,
             last_reward: 0,
+            liability: 0,
+            total_funded: 0,
             total_stake: 1, // makes math easier,
         };
 
@@ -528,13 +1318,13 @@ mod tests {
         ];
 
         for (secs, rps) in breakpoints {
-            let mut settings = base.clone();
+            let mut settings = base;
 
-            settings.update_rewards(secs as i64);
+            settings.update_rewards(secs as i64, None, u64::MAX);
             assert_eq!(rps, settings.reward_per_share);
 
             previous.iter_mut().all(|prev| {
-                prev.update_rewards(secs as i64);
+                prev.update_rewards(secs as i64, None, u64::MAX);
                 prev.reward_per_share == rps
             });
 
@@ -542,6 +1332,184 @@ mod tests {
         }
     }
 
+    #[test]
+    pub fn test_emission_decay() {
+        let decay = EmissionDecay {
+            numerator: 1,
+            denominator: 2,
+            period: 100,
+            floor: 300,
+        };
+        assert!(decay.is_valid());
+        assert_eq!(decay.apply(1_000), 500);
+        assert_eq!(decay.apply(500), 300);
+        assert_eq!(decay.apply(300), 300);
+        assert_eq!(decay.apply(200), 200);
+
+        assert!(!EmissionDecay { period: 0, ..decay }.is_valid());
+        assert!(!EmissionDecay {
+            denominator: 0,
+            ..decay
+        }
+        .is_valid());
+        assert!(!EmissionDecay {
+            numerator: 3,
+            ..decay
+        }
+        .is_valid());
+
+        let mut settings = Settings {
+            token: Pubkey::new_unique(),
+            admin: Authority::Basic(Pubkey::new_unique()),
+            unbonding_duration: 0,
+            split_bounds: SplitBounds::UNRESTRICTED,
+            next_emission_change: 100,
+            emission: 1_000,
+            decay,
+            emission_schedule: ZERO_KEY,
+            reward_per_share: 0,
+            last_reward: 0,
+            liability: 0,
+            total_funded: 0,
+            total_stake: 1,
+        };
+
+        // 1000 + 500 + 300 + 300 emitted over four periods
+        settings.update_rewards(400, None, u64::MAX);
+        assert_eq!(settings.reward_per_share, PRECISION * 2_100);
+        assert_eq!(settings.emission, 300);
+        assert_eq!(settings.next_emission_change, 500);
+    }
+
+    #[test]
+    pub fn test_emission_schedule() {
+        // the emission per year of an emission per period of 100 seconds
+        let yearly = |emission: u64| emission * SECONDS_PER_YEAR as u64 / 100;
+        assert_eq!(
+            Breakpoint {
+                start: 0,
+                yearly_emission: yearly(2_000),
+            }
+            .emission(100),
+            2_000
+        );
+
+        let schedule = EmissionSchedule {
+            breakpoints: vec![
+                Breakpoint {
+                    start: 50,
+                    yearly_emission: yearly(2_000),
+                },
+                Breakpoint {
+                    start: 100,
+                    yearly_emission: yearly(4_000),
+                },
+                Breakpoint {
+                    start: 250,
+                    yearly_emission: yearly(1_000),
+                },
+            ],
+        };
+        assert!(schedule.is_valid(0));
+        assert!(!schedule.is_valid(50));
+        assert!(!EmissionSchedule {
+            breakpoints: vec![schedule.breakpoints[1], schedule.breakpoints[0]],
+        }
+        .is_valid(0));
+
+        let base = Settings {
+            token: Pubkey::new_unique(),
+            admin: Authority::Basic(Pubkey::new_unique()),
+            unbonding_duration: 0,
+            split_bounds: SplitBounds::UNRESTRICTED,
+            next_emission_change: 100,
+            emission: 1_000,
+            decay: EmissionDecay {
+                numerator: 1,
+                denominator: 2,
+                period: 100,
+                floor: 0,
+            },
+            emission_schedule: Pubkey::new_unique(),
+            reward_per_share: 0,
+            last_reward: 0,
+            liability: 0,
+            total_funded: 0,
+            total_stake: 1,
+        };
+
+        // 0-50: 1000, 50-100: 2000, 100-200: 4000 (breakpoint overrides the decay),
+        // 200-250: 2000, 250-300: 1000, decaying to 500 at 300
+        let mut settings = base;
+        settings.update_rewards(300, Some(&schedule), u64::MAX);
+        let per_second = |emission: u128| PRECISION * emission / 100;
+        assert_eq!(
+            settings.reward_per_share,
+            per_second(1_000) * 50
+                + per_second(2_000) * 50
+                + per_second(4_000) * 100
+                + per_second(2_000) * 50
+                + per_second(1_000) * 50
+        );
+        assert_eq!(settings.emission, 500);
+        assert_eq!(settings.next_emission_change, 400);
+
+        // updating in smaller steps arrives at the same result
+        let mut stepped = base;
+        for now in (0..=300).step_by(30) {
+            stepped.update_rewards(now, Some(&schedule), u64::MAX);
+        }
+        assert_eq!(stepped, settings);
+
+        // without stake, emissions still follow the schedule
+        let mut empty = base;
+        empty.total_stake = 0;
+        empty.update_rewards(300, Some(&schedule), u64::MAX);
+        assert_eq!(empty.reward_per_share, 0);
+        assert_eq!(empty.emission, 500);
+        assert_eq!(empty.next_emission_change, 400);
+    }
+
+    #[test]
+    pub fn test_settings_emission_cap() {
+        let mut settings = Settings {
+            token: Pubkey::new_unique(),
+            admin: Authority::Basic(Pubkey::new_unique()),
+            unbonding_duration: 0,
+            split_bounds: SplitBounds::UNRESTRICTED,
+            next_emission_change: 1_000,
+            emission: 1_000,
+            decay: EmissionDecay {
+                numerator: 1,
+                denominator: 1,
+                period: 1_000,
+                floor: 0,
+            },
+            emission_schedule: ZERO_KEY,
+            reward_per_share: 0,
+            last_reward: 0,
+            liability: 0,
+            total_funded: 0,
+            total_stake: 10,
+        };
+
+        // 1 zee per second with plenty in the pool
+        settings.update_rewards(100, None, 1_000);
+        assert_eq!(settings.liability, 100);
+        assert_eq!(settings.reward_per_share, PRECISION * 10);
+
+        // only 50 of the next 100 are covered
+        settings.update_rewards(200, None, 150);
+        assert_eq!(settings.liability, 150);
+        assert_eq!(settings.reward_per_share, PRECISION * 15);
+
+        // nothing left to emit
+        settings.update_rewards(300, None, 150);
+        assert_eq!(settings.liability, 150);
+        assert_eq!(settings.reward_per_share, PRECISION * 15);
+        assert_eq!(settings.last_reward, 300);
+    }
+
     #[test]
     pub fn test_deserialize_empty() {
         let data = [0; 56];
@@ -0,0 +1,374 @@
+//! Consistency checks on a snapshot of the program's accounts.
+//!
+//! Recomputes the yield owed to every Beneficiary and compares it against the
+//! liability in Settings. Compares the stake recorded in Settings, Endpoints
+//! and Beneficiaries against the Stake accounts.
+//! Only available with the `client` feature.
+
+use std::fmt;
+
+use solana_program::{clock::UnixTimestamp, pubkey::Pubkey};
+
+use crate::account::Settings;
+use crate::client::ProgramState;
+use crate::error::AccountDataError;
+use crate::invariant::expected_staked;
+use crate::ZERO_KEY;
+
+/// An inconsistency found in the snapshot
+#[derive(Debug, PartialEq, Clone)]
+pub enum Discrepancy {
+    /// The Settings account is not part of the snapshot
+    MissingSettings,
+    /// Settings references an EmissionSchedule that is not part of the snapshot
+    MissingEmissionSchedule,
+    /// An account could not be decoded
+    InvalidAccount {
+        address: Pubkey,
+        error: AccountDataError,
+    },
+    /// A Stake account whose Endpoint is not part of the snapshot
+    OrphanedStake { address: Pubkey },
+    /// The sum of the stake of the Beneficiaries that earn rewards does not match
+    /// the Settings
+    TotalStake { settings: u64, beneficiaries: u64 },
+    /// The sum of the Stakes delegated to an Endpoint does not match the Endpoint
+    EndpointStake {
+        endpoint: Pubkey,
+        recorded: u64,
+        stakes: u64,
+    },
+    /// A Beneficiary's stake does not match the expected value, see `invariant`
+    BeneficiaryStake {
+        authority: Pubkey,
+        staked: u64,
+        expected: u64,
+    },
+    /// A Beneficiary that should receive a share of stake does not exist
+    MissingBeneficiary { authority: Pubkey, expected: u64 },
+    /// The yield owed to beneficiaries does not match the liability in Settings
+    Liability { settings: u64, beneficiaries: u64 },
+    /// The yield owed to beneficiaries exceeds the reward pool
+    Insolvent { liability: u64, pool_balance: u64 },
+}
+
+impl fmt::Display for Discrepancy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Discrepancy::MissingSettings => write!(f, "settings account missing"),
+            Discrepancy::MissingEmissionSchedule => write!(f, "emission schedule missing"),
+            Discrepancy::InvalidAccount { address, error } => {
+                write!(f, "account {} is invalid: {}", address, error)
+            }
+            Discrepancy::OrphanedStake { address } => {
+                write!(f, "stake {} has no known endpoint", address)
+            }
+            Discrepancy::TotalStake {
+                settings,
+                beneficiaries,
+            } => write!(
+                f,
+                "settings total stake {} != beneficiary total stake {}",
+                settings, beneficiaries
+            ),
+            Discrepancy::EndpointStake {
+                endpoint,
+                recorded,
+                stakes,
+            } => write!(
+                f,
+                "endpoint {} total stake {} != sum of stakes {}",
+                endpoint, recorded, stakes
+            ),
+            Discrepancy::BeneficiaryStake {
+                authority,
+                staked,
+                expected,
+            } => write!(
+                f,
+                "beneficiary {} staked {} != expected {}",
+                authority, staked, expected
+            ),
+            Discrepancy::MissingBeneficiary {
+                authority,
+                expected,
+            } => write!(
+                f,
+                "beneficiary {} missing, expected {} staked",
+                authority, expected
+            ),
+            Discrepancy::Liability {
+                settings,
+                beneficiaries,
+            } => write!(
+                f,
+                "settings liability {} != yield owed to beneficiaries {}",
+                settings, beneficiaries
+            ),
+            Discrepancy::Insolvent {
+                liability,
+                pool_balance,
+            } => write!(
+                f,
+                "liability {} exceeds reward pool balance {}",
+                liability, pool_balance
+            ),
+        }
+    }
+}
+
+/// The result of an audit
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Report {
+    /// The time the rewards were brought up to
+    pub now: UnixTimestamp,
+    pub pool_balance: u64,
+    /// The yield owed to all beneficiaries that can claim it, calculated from the
+    /// Beneficiary accounts
+    pub liability: u64,
+    /// The liability tracked by Settings
+    pub settings_liability: u64,
+    /// The amount deposited with `FundRewardPool`
+    pub total_funded: u64,
+    pub total_stake: u64,
+    pub discrepancies: Vec<Discrepancy>,
+}
+
+impl Report {
+    pub fn is_consistent(&self) -> bool {
+        self.discrepancies.is_empty()
+    }
+}
+
+/// Audit a snapshot of the program's accounts against the reward pool balance.
+/// The rewards are brought up to `now` the same way the program would.
+pub fn audit(state: &ProgramState, pool_balance: u64, now: UnixTimestamp) -> Report {
+    let mut report = Report {
+        now,
+        pool_balance,
+        ..Report::default()
+    };
+    let discrepancies = &mut report.discrepancies;
+
+    for (address, error) in &state.invalid {
+        discrepancies.push(Discrepancy::InvalidAccount {
+            address: *address,
+            error: error.clone(),
+        });
+    }
+    for (address, _) in &state.orphaned_stakes {
+        discrepancies.push(Discrepancy::OrphanedStake { address: *address });
+    }
+
+    let mut settings: Settings = match state.settings {
+        Some(settings) => settings,
+        None => {
+            discrepancies.push(Discrepancy::MissingSettings);
+            return report;
+        }
+    };
+    if settings.emission_schedule != ZERO_KEY && state.emission_schedule.is_none() {
+        discrepancies.push(Discrepancy::MissingEmissionSchedule);
+    }
+    settings.accrue(now, state.emission_schedule.as_ref(), pool_balance);
+    report.settings_liability = settings.liability;
+    report.total_funded = settings.total_funded;
+    report.total_stake = settings.total_stake;
+
+    // stake
+    let beneficiaries: u64 = state
+        .beneficiaries
+        .values()
+        .filter(|b| b.earns_rewards())
+        .map(|b| b.staked)
+        .sum();
+    if beneficiaries != settings.total_stake {
+        discrepancies.push(Discrepancy::TotalStake {
+            settings: settings.total_stake,
+            beneficiaries,
+        });
+    }
+
+    let mut endpoint_keys: Vec<&Pubkey> = state.endpoints.keys().collect();
+    endpoint_keys.sort();
+    for key in endpoint_keys {
+        let stakes = state
+            .stakes_by_endpoint(key)
+            .map(|s| s.stake.total_stake)
+            .sum();
+        let recorded = state.endpoints[key].total_stake;
+        if recorded != stakes {
+            discrepancies.push(Discrepancy::EndpointStake {
+                endpoint: *key,
+                recorded,
+                stakes,
+            });
+        }
+    }
+
+    let mut expected: Vec<(Pubkey, u64)> = expected_staked(state).into_iter().collect();
+    for authority in state.beneficiaries.keys() {
+        if !expected.iter().any(|(a, _)| a == authority) {
+            expected.push((*authority, 0));
+        }
+    }
+    expected.sort();
+    for (authority, expected) in expected {
+        match state.beneficiaries.get(&authority) {
+            Some(beneficiary) if beneficiary.staked != expected => {
+                discrepancies.push(Discrepancy::BeneficiaryStake {
+                    authority,
+                    staked: beneficiary.staked,
+                    expected,
+                })
+            }
+            None if expected > 0 => discrepancies.push(Discrepancy::MissingBeneficiary {
+                authority,
+                expected,
+            }),
+            _ => {}
+        }
+    }
+
+    // yield
+    report.liability = state
+        .beneficiaries
+        .values()
+        .filter(|b| b.earns_rewards())
+        .map(|b| {
+            b.calculate_holding(settings.reward_per_share)
+                .saturating_sub(b.reward_debt)
+                + b.holding
+        })
+        .sum();
+    if report.liability != settings.liability {
+        discrepancies.push(Discrepancy::Liability {
+            settings: settings.liability,
+            beneficiaries: report.liability,
+        });
+    }
+    if report.liability > pool_balance {
+        discrepancies.push(Discrepancy::Insolvent {
+            liability: report.liability,
+            pool_balance,
+        });
+    }
+
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::account::{
+        Authority, Beneficiary, EmissionDecay, Endpoint, Split, SplitBounds, Stake, Versioned,
+    };
+    use crate::client::Addresses;
+    use crate::PRECISION;
+
+    #[test]
+    pub fn test_audit() {
+        let program_id = Pubkey::new_unique();
+        let addresses = Addresses::new(program_id);
+        let staker = Pubkey::new_unique();
+        let primary = Pubkey::new_unique();
+        let endpoint_key = Pubkey::new_unique();
+
+        let mut settings = Settings {
+            token: Pubkey::new_unique(),
+            admin: Authority::Basic(Pubkey::new_unique()),
+            unbonding_duration: 0,
+            split_bounds: SplitBounds::UNRESTRICTED,
+            next_emission_change: 1_000_000,
+            emission: 0,
+            decay: EmissionDecay::DEFAULT,
+            emission_schedule: ZERO_KEY,
+            total_stake: 950,
+            reward_per_share: 2 * PRECISION,
+            last_reward: 0,
+            liability: 1_920,
+            total_funded: 0,
+        };
+        let endpoint = Endpoint {
+            creation_date: 0,
+            total_stake: 1_000,
+            owner: Authority::Basic(primary),
+            primary,
+            secondary: ZERO_KEY,
+            split: Split::DEFAULT,
+            primary_stake: 475,
+            secondary_stake: 50,
+            sunset: ZERO_KEY,
+        };
+        let stake = Stake {
+            creation_date: 0,
+            total_stake: 1_000,
+            staker,
+            split: Split::DEFAULT,
+            unbonding_amount: 0,
+            unbonding: vec![],
+        };
+        let beneficiary = |authority: Pubkey, staked: u64| Beneficiary {
+            authority,
+            staked,
+            reward_debt: 0,
+            holding: 10,
+        };
+
+        let snapshot = |settings: &Settings, primary_staked: u64| {
+            let accounts = vec![
+                (addresses.settings(), settings.to_account_data().unwrap()),
+                (endpoint_key, endpoint.to_account_data().unwrap()),
+                (
+                    addresses.stake(&endpoint_key, &staker),
+                    stake.to_account_data().unwrap(),
+                ),
+                (
+                    addresses.beneficiary(&staker),
+                    beneficiary(staker, 475).to_account_data().unwrap(),
+                ),
+                (
+                    addresses.beneficiary(&primary),
+                    beneficiary(primary, primary_staked)
+                        .to_account_data()
+                        .unwrap(),
+                ),
+                (
+                    addresses.beneficiary(&ZERO_KEY),
+                    beneficiary(ZERO_KEY, 50).to_account_data().unwrap(),
+                ),
+            ];
+            ProgramState::from_accounts(&program_id, accounts)
+        };
+
+        // nobody can claim the yield on the share of the missing secondary
+        let report = audit(&snapshot(&settings, 475), 1_920, 100);
+        assert_eq!(report.discrepancies, vec![]);
+        assert_eq!(report.liability, 950 + 950 + 20);
+
+        settings.total_stake = 900;
+        let report = audit(&snapshot(&settings, 470), 1_900, 100);
+        assert_eq!(
+            report.discrepancies,
+            vec![
+                Discrepancy::TotalStake {
+                    settings: 900,
+                    beneficiaries: 945
+                },
+                Discrepancy::BeneficiaryStake {
+                    authority: primary,
+                    staked: 470,
+                    expected: 475
+                },
+                Discrepancy::Liability {
+                    settings: 1_920,
+                    beneficiaries: 1_910
+                },
+                Discrepancy::Insolvent {
+                    liability: 1_910,
+                    pool_balance: 1_900
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,269 @@
+//! Off-chain access to program state.
+//!
+//! Works on raw account data as returned by `getProgramAccounts` or
+//! `getMultipleAccounts`, so it does not depend on any particular RPC client.
+//! Only available with the `client` feature.
+
+use std::collections::HashMap;
+
+use solana_program::pubkey::Pubkey;
+
+use crate::account::{
+    AccountType, Beneficiary, EmissionSchedule, Endpoint, PoolAuthority, RewardPool, Settings,
+    Stake, Versioned,
+};
+use crate::error::{AccountDataError, StakingError};
+
+/// A decoded account owned by the program
+#[derive(Debug, PartialEq, Clone)]
+pub enum ProgramAccount {
+    Settings(Settings),
+    Endpoint(Endpoint),
+    Beneficiary(Beneficiary),
+    Stake(Stake),
+    EmissionSchedule(EmissionSchedule),
+}
+
+impl ProgramAccount {
+    /// Decode the data of any account owned by the program.
+    /// Accounts that still need to be migrated return `AccountRequiresMigration`.
+    pub fn decode(data: &[u8]) -> Result<ProgramAccount, AccountDataError> {
+        let account_type =
+            AccountType::from_discriminator(data).ok_or(StakingError::AccountRequiresMigration)?;
+        Ok(match account_type {
+            AccountType::Settings => ProgramAccount::Settings(Settings::from_account_data(data)?),
+            AccountType::Endpoint => ProgramAccount::Endpoint(Endpoint::from_account_data(data)?),
+            AccountType::Beneficiary => {
+                ProgramAccount::Beneficiary(Beneficiary::from_account_data(data)?)
+            }
+            AccountType::Stake => ProgramAccount::Stake(Stake::from_account_data(data)?),
+            AccountType::EmissionSchedule => {
+                ProgramAccount::EmissionSchedule(EmissionSchedule::from_account_data(data)?)
+            }
+        })
+    }
+}
+
+/// The program derived addresses of a deployment
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Addresses {
+    pub program_id: Pubkey,
+}
+
+impl Addresses {
+    pub fn new(program_id: Pubkey) -> Addresses {
+        Addresses { program_id }
+    }
+
+    pub fn settings(&self) -> Pubkey {
+        Settings::program_address(&self.program_id).0
+    }
+
+    pub fn pool_authority(&self) -> Pubkey {
+        PoolAuthority::program_address(&self.program_id).0
+    }
+
+    pub fn reward_pool(&self) -> Pubkey {
+        RewardPool::program_address(&self.program_id).0
+    }
+
+    pub fn emission_schedule(&self) -> Pubkey {
+        EmissionSchedule::program_address(&self.program_id).0
+    }
+
+    pub fn beneficiary(&self, authority: &Pubkey) -> Pubkey {
+        Beneficiary::program_address(authority, &self.program_id).0
+    }
+
+    pub fn stake(&self, endpoint: &Pubkey, staker: &Pubkey) -> Pubkey {
+        Stake::program_address(endpoint, staker, &self.program_id).0
+    }
+
+    pub fn stake_fund(&self, endpoint: &Pubkey, staker: &Pubkey) -> Pubkey {
+        Stake::fund_address(endpoint, staker, &self.program_id).0
+    }
+}
+
+/// A Stake account together with its address and Endpoint
+#[derive(Debug, PartialEq, Clone)]
+pub struct StakeEntry {
+    pub address: Pubkey,
+    pub endpoint: Pubkey,
+    pub stake: Stake,
+}
+
+/// A snapshot of all accounts of a deployment
+#[derive(Debug, Default, Clone)]
+pub struct ProgramState {
+    pub settings: Option<Settings>,
+    pub emission_schedule: Option<EmissionSchedule>,
+    pub endpoints: HashMap<Pubkey, Endpoint>,
+    /// Beneficiaries by authority
+    pub beneficiaries: HashMap<Pubkey, Beneficiary>,
+    pub stakes: Vec<StakeEntry>,
+    /// Stake accounts whose Endpoint was not part of the snapshot
+    pub orphaned_stakes: Vec<(Pubkey, Stake)>,
+    /// Accounts that could not be decoded
+    pub invalid: Vec<(Pubkey, AccountDataError)>,
+}
+
+impl ProgramState {
+    /// Decode a list of `(address, data)` pairs of accounts owned by the program.
+    ///
+    /// A Stake account does not store its Endpoint, so it is matched against the
+    /// Endpoints in the list by deriving the Stake's address.
+    pub fn from_accounts<I>(program_id: &Pubkey, accounts: I) -> ProgramState
+    where
+        I: IntoIterator<Item = (Pubkey, Vec<u8>)>,
+    {
+        let addresses = Addresses::new(*program_id);
+        let mut state = ProgramState::default();
+        let mut stakes = vec![];
+
+        for (key, data) in accounts {
+            match ProgramAccount::decode(&data) {
+                Ok(ProgramAccount::Settings(settings)) if key == addresses.settings() => {
+                    state.settings = Some(settings)
+                }
+                Ok(ProgramAccount::EmissionSchedule(schedule))
+                    if key == addresses.emission_schedule() =>
+                {
+                    state.emission_schedule = Some(schedule)
+                }
+                Ok(ProgramAccount::Endpoint(endpoint)) => {
+                    state.endpoints.insert(key, endpoint);
+                }
+                Ok(ProgramAccount::Beneficiary(beneficiary))
+                    if key == addresses.beneficiary(&beneficiary.authority) =>
+                {
+                    state
+                        .beneficiaries
+                        .insert(beneficiary.authority, beneficiary);
+                }
+                Ok(ProgramAccount::Stake(stake)) => stakes.push((key, stake)),
+                // singletons and beneficiaries at the wrong address
+                Ok(ProgramAccount::Settings(_)) => state
+                    .invalid
+                    .push((key, StakingError::InvalidSettingsAccount.into())),
+                Ok(ProgramAccount::EmissionSchedule(_)) => state
+                    .invalid
+                    .push((key, StakingError::InvalidEmissionScheduleAccount.into())),
+                Ok(ProgramAccount::Beneficiary(_)) => state
+                    .invalid
+                    .push((key, StakingError::InvalidBeneficiaryAccount.into())),
+                Err(err) => state.invalid.push((key, err)),
+            }
+        }
+
+        for (address, stake) in stakes {
+            match state
+                .endpoints
+                .keys()
+                .find(|endpoint| addresses.stake(endpoint, &stake.staker) == address)
+            {
+                Some(endpoint) => state.stakes.push(StakeEntry {
+                    address,
+                    endpoint: *endpoint,
+                    stake,
+                }),
+                None => state.orphaned_stakes.push((address, stake)),
+            }
+        }
+
+        state
+    }
+
+    /// All stakes delegated to an Endpoint
+    pub fn stakes_by_endpoint<'a>(
+        &'a self,
+        endpoint: &'a Pubkey,
+    ) -> impl Iterator<Item = &'a StakeEntry> {
+        self.stakes.iter().filter(move |s| s.endpoint == *endpoint)
+    }
+
+    /// All stakes of a staker
+    pub fn stakes_by_staker<'a>(
+        &'a self,
+        staker: &'a Pubkey,
+    ) -> impl Iterator<Item = &'a StakeEntry> {
+        self.stakes
+            .iter()
+            .filter(move |s| s.stake.staker == *staker)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::account::{Authority, Split};
+    use crate::ZERO_KEY;
+
+    #[test]
+    pub fn test_program_state() {
+        let program_id = Pubkey::new_unique();
+        let addresses = Addresses::new(program_id);
+        let staker = Pubkey::new_unique();
+        let endpoint_a = Pubkey::new_unique();
+        let endpoint_b = Pubkey::new_unique();
+
+        let endpoint = Endpoint {
+            creation_date: 1,
+            total_stake: 0,
+            owner: Authority::Basic(Pubkey::new_unique()),
+            primary: Pubkey::new_unique(),
+            secondary: ZERO_KEY,
+            split: Split::DEFAULT,
+            primary_stake: 0,
+            secondary_stake: 0,
+            sunset: ZERO_KEY,
+        };
+        let stake = Stake {
+            creation_date: 1,
+            total_stake: 5_000,
+            staker,
+            split: Split::DEFAULT,
+            unbonding_amount: 0,
+            unbonding: vec![],
+        };
+        let beneficiary = Beneficiary {
+            authority: staker,
+            staked: 0,
+            reward_debt: 0,
+            holding: 0,
+        };
+
+        let accounts = vec![
+            (endpoint_a, endpoint.to_account_data().unwrap()),
+            (endpoint_b, endpoint.to_account_data().unwrap()),
+            (
+                addresses.stake(&endpoint_a, &staker),
+                stake.to_account_data().unwrap(),
+            ),
+            (
+                addresses.stake(&endpoint_b, &staker),
+                stake.to_account_data().unwrap(),
+            ),
+            (Pubkey::new_unique(), stake.to_account_data().unwrap()),
+            (
+                addresses.beneficiary(&staker),
+                beneficiary.to_account_data().unwrap(),
+            ),
+            (Pubkey::new_unique(), vec![1, 2, 3]),
+        ];
+
+        let state = ProgramState::from_accounts(&program_id, accounts);
+        assert_eq!(state.settings, None);
+        assert_eq!(state.endpoints.len(), 2);
+        assert_eq!(state.beneficiaries.get(&staker), Some(&beneficiary));
+        assert_eq!(state.stakes_by_staker(&staker).count(), 2);
+        assert_eq!(
+            state
+                .stakes_by_endpoint(&endpoint_a)
+                .map(|s| s.address)
+                .collect::<Vec<_>>(),
+            vec![addresses.stake(&endpoint_a, &staker)]
+        );
+        assert_eq!(state.orphaned_stakes.len(), 1);
+        assert_eq!(state.invalid.len(), 1);
+    }
+}
@@ -1,5 +1,8 @@
 //! Program entrypoint
 
+// `entrypoint!` checks cfgs that only exist on the solana toolchain
+#![allow(unexpected_cfgs)]
+
 use crate::{error::StakingError, processor::Processor};
 use solana_program::{
     account_info::AccountInfo, entrypoint, entrypoint::ProgramResult,
@@ -1,9 +1,14 @@
+// the `FromPrimitive` derive of num-derive 0.3 implements the trait inside a const block
+#![allow(non_local_definitions)]
+
 use num_derive::FromPrimitive;
 use solana_program::decode_error::DecodeError;
 use solana_program::msg;
 use solana_program::program_error::{PrintProgramError, ProgramError};
 use thiserror::Error;
 
+use crate::account::AccountType;
+
 #[derive(Clone, Debug, Eq, Error, FromPrimitive, PartialEq)]
 pub enum StakingError {
     /// Missing Authority Signature
@@ -117,6 +122,106 @@ pub enum StakingError {
     /// Secondary Authority Keys Do Not Match
     #[error("Secondary Authority Keys Do Not Match")]
     SecondaryAuthorityKeysDoNotMatch,
+
+    /// Next Emission Change Must Be In The Future
+    #[error("Next Emission Change Must Be In The Future")]
+    InvalidNextEmissionChange,
+
+    /// Account Requires Migration
+    #[error("Account Requires Migration")]
+    AccountRequiresMigration,
+
+    /// Account Has No Legacy Layout To Migrate
+    #[error("Account Has No Legacy Layout To Migrate")]
+    NothingToMigrate,
+
+    /// Invalid Upgrade Authority
+    #[error("Invalid Upgrade Authority")]
+    InvalidUpgradeAuthority,
+
+    /// Account Is Of A Different Type
+    #[error("Account Is Of A Different Type")]
+    InvalidAccountType,
+
+    /// Account Is Not Owned By The Program
+    #[error("Account Is Not Owned By The Program")]
+    InvalidAccountOwner,
+
+    /// Account Is Not Writable
+    #[error("Account Is Not Writable")]
+    AccountNotWritable,
+
+    /// Account Is Not A Signer
+    #[error("Account Is Not A Signer")]
+    AccountNotSigner,
+
+    /// Invalid Rebond Amount
+    #[error("Invalid Rebond Amount")]
+    RebondInvalidAmount,
+
+    /// Cannot Redelegate To The Same Endpoint
+    #[error("Cannot Redelegate To The Same Endpoint")]
+    RedelegateSameEndpoint,
+
+    /// Stake Still Has Staked Or Unbonding Funds
+    #[error("Stake Still Has Staked Or Unbonding Funds")]
+    StakeNotEmpty,
+
+    /// Endpoint Still Has Stake
+    #[error("Endpoint Still Has Stake")]
+    EndpointHasStake,
+
+    /// Endpoint Is Being Sunset
+    #[error("Endpoint Is Being Sunset")]
+    EndpointSunset,
+
+    /// Invalid Sunset Destination
+    #[error("Invalid Sunset Destination")]
+    InvalidSunsetDestination,
+
+    /// Beneficiary Still Has Stake Or Holding
+    #[error("Beneficiary Still Has Stake Or Holding")]
+    BeneficiaryNotEmpty,
+
+    /// Split Does Not Add Up To 100%
+    #[error("Split Does Not Add Up To 100%")]
+    InvalidSplit,
+
+    /// Split Is Outside Of The Allowed Bounds
+    #[error("Split Is Outside Of The Allowed Bounds")]
+    SplitOutOfBounds,
+
+    /// Invalid Split Bounds
+    #[error("Invalid Split Bounds")]
+    InvalidSplitBounds,
+
+    /// Invalid Emission Decay
+    #[error("Invalid Emission Decay")]
+    InvalidEmissionDecay,
+
+    /// Invalid Emission Schedule Account
+    #[error("Invalid Emission Schedule Account")]
+    InvalidEmissionScheduleAccount,
+
+    /// Invalid Emission Schedule
+    #[error("Invalid Emission Schedule")]
+    InvalidEmissionSchedule,
+
+    /// Emission Schedule Account Is Missing
+    #[error("Emission Schedule Account Is Missing")]
+    MissingEmissionSchedule,
+
+    /// Invalid Funding Amount
+    #[error("Invalid Funding Amount")]
+    FundInvalidAmount,
+
+    /// Unbonding Queue Is Full
+    #[error("Unbonding Queue Is Full")]
+    UnbondingQueueFull,
+
+    /// Resync Exceeds The Rounding Tolerance
+    #[error("Resync Exceeds The Rounding Tolerance")]
+    ResyncOutOfBounds,
 }
 
 impl From<StakingError> for ProgramError {
@@ -124,6 +229,29 @@ impl From<StakingError> for ProgramError {
         ProgramError::Custom(e as u32)
     }
 }
+/// Error decoding the data of a program account. Unlike `StakingError`, it
+/// keeps both types of a mismatch for off-chain callers.
+#[derive(Clone, Debug, Eq, Error, PartialEq)]
+pub enum AccountDataError {
+    /// The discriminator belongs to a different type of account
+    #[error("expected {expected} account, found {found} account")]
+    InvalidType {
+        expected: AccountType,
+        found: AccountType,
+    },
+    #[error(transparent)]
+    Staking(#[from] StakingError),
+}
+
+impl From<AccountDataError> for ProgramError {
+    fn from(e: AccountDataError) -> Self {
+        match e {
+            AccountDataError::InvalidType { .. } => StakingError::InvalidAccountType.into(),
+            AccountDataError::Staking(e) => e.into(),
+        }
+    }
+}
+
 impl<T> DecodeError<T> for StakingError {
     fn type_of() -> &'static str {
         "StakingError"
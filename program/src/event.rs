@@ -0,0 +1,152 @@
+//! Structured events emitted by the program.
+//!
+//! Events are Borsh-serialized and emitted with `sol_log_data`, so they show up
+//! as "Program data: <base64>" lines in the transaction logs. After decoding the
+//! base64, `StakingEvent::decode` turns the bytes back into an event.
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::{clock::UnixTimestamp, log::sol_log_data, pubkey::Pubkey};
+
+use crate::account::{Authority, Split};
+
+#[derive(Debug, PartialEq, Eq, Clone, BorshSerialize, BorshDeserialize)]
+pub enum StakingEvent {
+    /// Pending yield the reward pool could not cover. It stays in the
+    /// beneficiary's holding and can be claimed once the pool is refilled.
+    RewardShortfall {
+        /// The beneficiary's authority
+        authority: Pubkey,
+        /// The amount paid out
+        paid: u64,
+        /// The amount that remains in holding
+        unpaid: u64,
+    },
+    /// ZEE was deposited into the reward pool with `FundRewardPool`
+    RewardPoolFunded {
+        funder: Pubkey,
+        amount: u64,
+        /// Total deposited with `FundRewardPool` so far
+        total_funded: u64,
+    },
+    /// ZEE was staked to an Endpoint
+    Staked {
+        staker: Pubkey,
+        endpoint: Pubkey,
+        amount: u64,
+        /// The stake's total after staking
+        total_stake: u64,
+    },
+    /// ZEE was unstaked from an Endpoint and started unbonding
+    Unstaked {
+        staker: Pubkey,
+        endpoint: Pubkey,
+        amount: u64,
+        /// The stake's total after unstaking
+        total_stake: u64,
+        /// The time the funds can be withdrawn
+        unbonding_end: UnixTimestamp,
+    },
+    /// Unbonding ZEE was staked again
+    Rebonded {
+        staker: Pubkey,
+        endpoint: Pubkey,
+        amount: u64,
+    },
+    /// Staked ZEE was moved from one Endpoint to another
+    Redelegated {
+        staker: Pubkey,
+        source: Pubkey,
+        destination: Pubkey,
+        amount: u64,
+    },
+    /// Unbonded ZEE was withdrawn to the staker
+    Withdrawn {
+        staker: Pubkey,
+        endpoint: Pubkey,
+        amount: u64,
+    },
+    /// Yield was paid out to a beneficiary
+    Claimed { authority: Pubkey, amount: u64 },
+    /// A new Endpoint was registered
+    EndpointRegistered {
+        endpoint: Pubkey,
+        owner: Authority,
+        primary: Pubkey,
+        secondary: Pubkey,
+        split: Split,
+    },
+    /// The owner of an Endpoint changed
+    EndpointTransferred {
+        endpoint: Pubkey,
+        old_owner: Authority,
+        new_owner: Authority,
+    },
+    /// The beneficiaries of an Endpoint changed
+    BeneficiariesChanged {
+        endpoint: Pubkey,
+        old_primary: Pubkey,
+        old_secondary: Pubkey,
+        new_primary: Pubkey,
+        new_secondary: Pubkey,
+    },
+    /// The reward per share was brought up to date
+    RewardsUpdated {
+        last_reward: UnixTimestamp,
+        reward_per_share: u128,
+        emission: u64,
+        total_stake: u64,
+        liability: u64,
+    },
+    /// The admin corrected the stake of a beneficiary with `Resync`
+    BeneficiaryResynced {
+        authority: Pubkey,
+        old_staked: u64,
+        new_staked: u64,
+    },
+    /// The split of an Endpoint changed. The stake of the primary and
+    /// secondary moved to the new split right away.
+    SplitChanged {
+        endpoint: Pubkey,
+        old_split: Split,
+        new_split: Split,
+        /// The primary's stake under the new split
+        primary_stake: u64,
+        /// The secondary's stake under the new split
+        secondary_stake: u64,
+    },
+}
+
+impl StakingEvent {
+    /// Emit the event to the transaction log
+    pub fn emit(&self) {
+        sol_log_data(&[&self.try_to_vec().unwrap()]);
+    }
+
+    /// Decode an event from the data of a "Program data:" log line
+    pub fn decode(data: &[u8]) -> Option<StakingEvent> {
+        StakingEvent::try_from_slice(data).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn test_decode() {
+        let event = StakingEvent::Staked {
+            staker: Pubkey::new_unique(),
+            endpoint: Pubkey::new_unique(),
+            amount: 1_000,
+            total_stake: 3_000,
+        };
+        let data = event.try_to_vec().unwrap();
+        assert_eq!(StakingEvent::decode(&data), Some(event));
+
+        // the variant index comes first
+        assert_eq!(data[0], 2);
+        assert_eq!(StakingEvent::decode(&[]), None);
+        assert_eq!(StakingEvent::decode(&[255]), None);
+        assert_eq!(StakingEvent::decode(&data[..data.len() - 1]), None);
+    }
+}
@@ -1,6 +1,16 @@
 use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::{
+    bpf_loader_upgradeable,
+    clock::UnixTimestamp,
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+    system_program, sysvar,
+};
 
-use crate::account::Authority;
+use crate::account::{
+    Authority, Beneficiary, Breakpoint, EmissionDecay, EmissionSchedule, PoolAuthority, RewardPool,
+    Settings, Split, SplitBounds, Stake,
+};
 
 #[repr(C)]
 #[derive(Clone, Debug, PartialEq, BorshSerialize, BorshDeserialize)]
@@ -13,17 +23,24 @@ pub enum StakingInstruction {
     ///     3. `[]` Pool Authority
     ///     4. `[writable]` Reward Pool
     ///     5. `[]` ZEE Token Mint
-    ///     6. `[]` Rent Sysvar
-    ///     7. `[]` SPL Token Program
-    ///     8. `[]` System Program
+    ///     6. `[]` Admin Account (or NFT mint)
+    ///     7. `[]` Rent Sysvar
+    ///     8. `[]` SPL Token Program
+    ///     9. `[]` System Program
+    ///     10. `[]` Program Data account of this program
+    ///     11. `[signer]` Upgrade Authority of this program
     Initialize {
         /// The time after which yields start to pay out
         start_time: i64,
         /// The amount of time (in seconds) to lock unbonded funds
         unbonding_duration: u64,
+        /// The authority allowed to update the settings
+        admin: Authority,
     },
     /// Register a new endpoint.
     ///
+    /// The split has to be within the bounds set in Settings.
+    ///
     /// Expected Accounts:
     ///     1. `[writable,signer]` Transaction payer
     ///     2. `[writable,signer]` Endpoint Account
@@ -35,7 +52,8 @@ pub enum StakingInstruction {
     ///     8. `[]` Rent Sysvar
     ///     9. `[]` Clock Sysvar
     ///     10. `[]` System Program
-    RegisterEndpoint { owner: Authority },
+    ///     11. `[]` Settings
+    RegisterEndpoint { owner: Authority, split: Split },
     /// Initialize a new stake
     ///
     /// Must be done before being able to stake ZEE to an Endpoint
@@ -58,7 +76,15 @@ pub enum StakingInstruction {
     ///
     /// To withdraw, you can stake negative amount. To just harvest yield, you
     /// can stake zero. Unless everything is withdrawn at the same time, there
-    /// must always be at least 1000 ZEE staked.
+    /// must always be at least 1000 ZEE staked. A sunset Endpoint only allows
+    /// withdrawing.
+    ///
+    /// Pending yield is paid out to the staker. Whatever the reward pool cannot
+    /// cover stays in the staker's Beneficiary account.
+    ///
+    /// Every withdrawal adds a tranche to the unbonding queue. If the queue is
+    /// full, the tranches that have ended are paid out to the staker first. It
+    /// fails if there are none.
     ///
     /// Expected Accounts:
     ///     1. `[writable,signer]` Transaction payer
@@ -75,10 +101,15 @@ pub enum StakingInstruction {
     ///     12. `[writable]` Stake Account
     ///     13. `[]` Clock Sysvar
     ///     14. `[]` SPL Token Program
+    ///     15. `[]` System Program
+    ///     16. `[writable]` Sunset destination, only if the Endpoint is sunset.
+    ///         Closes the Endpoint once the last stake is withdrawn
     Stake { amount: i64 },
     /// Withdraw Unbounded Tokens
     ///
     /// Transfer the unbounded tokens to a wallet once the duration has passed.
+    /// Every unstake is locked separately, only the funds whose duration has
+    /// passed are transferred.
     ///
     /// Expected Accounts:
     ///     1. `[writable,signer]` Transaction payer
@@ -93,6 +124,8 @@ pub enum StakingInstruction {
     WithdrawUnbond,
     /// Claim Beneficiary Yield
     ///
+    /// Whatever the reward pool cannot cover stays in the Beneficiary account.
+    ///
     /// Expected Accounts:
     ///     1. `[writable,signer]` Transaction payer
     ///     2. `[signer]` Beneficiary Authority
@@ -107,6 +140,7 @@ pub enum StakingInstruction {
     /// Transfer an Endpoint from one owner to the next. If the recipient is an NFT
     /// then the NFT has to already exist.
     ///
+    /// Expected Accounts:
     ///     1. `[writable,signer]` Transaction payer
     ///     2. `[writable]` The Endpoint
     ///     3. `[]` The endpoint's owner account
@@ -116,6 +150,7 @@ pub enum StakingInstruction {
     /// Change the beneficiaries of an Endpoint. If the primary or secondary
     /// beneficiaries don't exist yet, they will be created
     ///
+    /// Expected Accounts:
     ///     1. `[writable,signer]` Transaction payer
     ///     2. `[writable]` The Endpoint
     ///     3. `[]` The endpoint's owner account
@@ -130,7 +165,895 @@ pub enum StakingInstruction {
     ///    12. `[]` Rent Sysvar
     ///    13. `[]` Clock Sysvar
     ///    14. `[]` System Program
+    ///    15. `[]` Reward Pool
     ChangeBeneficiaries,
+    /// Update the program settings. Only the admin can change settings.
+    /// Fields that are `None` are left unchanged. Pending yield is settled
+    /// up to the current time before any change is applied.
+    ///
+    /// Expected Accounts:
+    ///     1. `[writable,signer]` Transaction payer
+    ///     2. `[writable]` Settings
+    ///     3. `[]` The admin's account
+    ///     4. `[signer]` The admin (or holder of the NFT)
+    ///     5. `[]` Clock Sysvar
+    ///     6. `[]` Reward Pool
+    UpdateSettings {
+        /// The amount of time (in seconds) to lock unbonded funds
+        unbonding_duration: Option<u64>,
+        /// The amount of ZEE distributed as yield during the current period
+        emission: Option<u64>,
+        /// The time at which emissions are reduced next
+        next_emission_change: Option<UnixTimestamp>,
+        /// The range of splits that Endpoints are allowed to use
+        split_bounds: Option<SplitBounds>,
+        /// How emissions are reduced at the end of every period
+        decay: Option<EmissionDecay>,
+    },
+    /// Rewrite an account created before accounts had a discriminator and version
+    /// in the current layout. The account is resized and topped up to stay rent exempt.
+    /// Migrating the Settings account makes the program's upgrade authority the admin.
+    ///
+    /// Expected Accounts:
+    ///     1. `[writable,signer]` Transaction payer
+    ///     2. `[writable]` The account to migrate
+    ///     3. `[]` Rent Sysvar
+    ///     4. `[]` System Program
+    ///
+    /// Additional accounts when migrating the Settings account:
+    ///     5. `[]` The program's ProgramData account
+    ///     6. `[signer]` The program's upgrade authority
+    ///     7+. `[]` Every Beneficiary account, in either layout
+    ///
+    /// Additional accounts when migrating an Endpoint, in either layout:
+    ///     5+. `[]` Every Stake account of the Endpoint
+    Migrate {
+        /// The yield owed to beneficiaries that has not been paid out yet, which
+        /// legacy Settings did not track. Has to match the liability of the
+        /// Beneficiaries passed, see `legacy::Settings::liability`.
+        /// Must be 0 for any other account.
+        liability: u64,
+    },
+    /// Cancel unbonding and restake pending funds
+    ///
+    /// Moves some or all of the unbonding amount back into the stake without
+    /// having to wait for the unbonding duration, starting with the most recently
+    /// unstaked funds. Pending yield is credited to the staker's beneficiary and
+    /// can be claimed.
+    ///
+    /// Expected Accounts:
+    ///     1. `[writable,signer]` Transaction payer
+    ///     2. `[signer]` Staker
+    ///     3. `[writable]` Staker Beneficiary
+    ///     4. `[writable]` Endpoint
+    ///     5. `[writable]` Endpoint Primary Beneficiary
+    ///     6. `[writable]` Endpoint Secondary Beneficiary
+    ///     7. `[writable]` Settings
+    ///     8. `[writable]` Stake Account
+    ///     9. `[]` Clock Sysvar
+    ///    10. `[]` Reward Pool
+    ///    11. `[]` System Program
+    Rebond { amount: u64 },
+    /// Move stake from one Endpoint to another without unbonding
+    ///
+    /// Both Stake accounts have to belong to the same staker and already be
+    /// initialized. Pending yield is credited to the beneficiaries and can be claimed.
+    ///
+    /// Expected Accounts:
+    ///     1. `[writable,signer]` Transaction payer
+    ///     2. `[signer]` Staker
+    ///     3. `[writable]` Staker Beneficiary
+    ///     4. `[writable]` Source Endpoint
+    ///     5. `[writable]` Source Endpoint Primary Beneficiary
+    ///     6. `[writable]` Source Endpoint Secondary Beneficiary
+    ///     7. `[writable]` Source Stake Account
+    ///     8. `[writable]` Source Staker Fund
+    ///     9. `[writable]` Destination Endpoint
+    ///    10. `[writable]` Destination Endpoint Primary Beneficiary
+    ///    11. `[writable]` Destination Endpoint Secondary Beneficiary
+    ///    12. `[writable]` Destination Stake Account
+    ///    13. `[writable]` Destination Staker Fund
+    ///    14. `[writable]` Settings
+    ///    15. `[]` Clock Sysvar
+    ///    16. `[]` SPL Token Program
+    ///    17. `[]` Reward Pool
+    ///    18. `[]` System Program
+    ///    19. `[writable]` Sunset destination, only if the Source Endpoint is
+    ///        sunset. Closes it once the last stake is withdrawn
+    Redelegate { amount: u64 },
+    /// Close a Stake account and its Staker Fund
+    ///
+    /// Everything has to be withdrawn first, there can be neither staked nor
+    /// unbonding funds left. Any ZEE left in the Staker Fund is sent to the
+    /// staker. The rent of both accounts goes to the destination.
+    ///
+    /// Expected Accounts:
+    ///     1. `[writable,signer]` Transaction payer
+    ///     2. `[signer]` Staker
+    ///     3. `[writable]` Stake Account
+    ///     4. `[writable]` Staker Fund
+    ///     5. `[]` Endpoint
+    ///     6. `[writable]` Destination of the rent
+    ///     7. `[]` SPL Token Program
+    ///     8. `[writable]` Staker's ZEE Token Account
+    CloseStake,
+    /// Close an Endpoint
+    ///
+    /// An Endpoint without stake is closed immediately. If there is still stake
+    /// and `sunset` is set, the Endpoint stops accepting new stake instead and
+    /// is closed once the last staker withdraws. The rent goes to the destination,
+    /// which cannot be the zero key when sunsetting.
+    ///
+    /// Expected Accounts:
+    ///     1. `[writable,signer]` Transaction payer
+    ///     2. `[writable]` Endpoint
+    ///     3. `[]` Owner Account
+    ///     4. `[signer]` Owner Signer
+    ///     5. `[writable]` Destination of the rent
+    CloseEndpoint { sunset: bool },
+    /// Close a Beneficiary account
+    ///
+    /// The Beneficiary must have nothing staked and all yield claimed. The
+    /// rent is returned to the authority.
+    ///
+    /// Stakes and Endpoints that still reference the Beneficiary recreate it
+    /// when they need it again. `Stake`, `Rebond`, `Redelegate` and
+    /// `ChangeBeneficiaries` take the System Program at a fixed position for
+    /// that. The payer covers the rent.
+    ///
+    /// Expected Accounts:
+    ///     1. `[writable,signer]` Transaction payer
+    ///     2. `[writable,signer]` Beneficiary Authority
+    ///     3. `[writable]` Beneficiary Account
+    ///     4. `[writable]` Settings
+    ///     5. `[]` Clock Sysvar
+    ///     6. `[]` Reward Pool
+    CloseBeneficiary,
+    /// Change the split of an Endpoint. Only the owner can change the split.
+    ///
+    /// The primary's and secondary's shares of the Endpoint's total stake are
+    /// moved to the new split right away, after paying out the yield accrued
+    /// under the old one. The stakers' shares are moved the next time their
+    /// stake changes, so they are taken out exactly as they were accounted.
+    ///
+    /// Expected Accounts:
+    ///     1. `[writable,signer]` Transaction payer
+    ///     2. `[writable]` Endpoint
+    ///     3. `[]` Owner Account
+    ///     4. `[signer]` Owner Signer
+    ///     5. `[writable]` Settings
+    ///     6. `[writable]` Endpoint Primary Beneficiary
+    ///     7. `[writable]` Endpoint Secondary Beneficiary
+    ///     8. `[]` Clock Sysvar
+    ///     9. `[]` Reward Pool
+    ///    10. `[]` System Program
+    ChangeSplit { split: Split },
+    /// Replace the breakpoints of the emission schedule. Only the admin can
+    /// change the schedule.
+    ///
+    /// The emission of a breakpoint is given per year and converted to the decay
+    /// period when it is reached, so changing the period does not change it.
+    /// Breakpoints have to be in ascending order and in the future. The account
+    /// is created the first time a schedule is set. An empty list removes the
+    /// schedule and closes the account, returning the rent to the payer.
+    ///
+    /// Once a schedule is set, every instruction that updates the rewards also
+    /// needs the Emission Schedule account, anywhere in its list of accounts.
+    ///
+    /// Expected Accounts:
+    ///     1. `[writable,signer]` Transaction payer
+    ///     2. `[writable]` Settings
+    ///     3. `[]` The admin's account
+    ///     4. `[signer]` The admin (or holder of the NFT)
+    ///     5. `[writable]` Emission Schedule
+    ///     6. `[]` Rent Sysvar
+    ///     7. `[]` Clock Sysvar
+    ///     8. `[]` System Program
+    ///     9. `[]` Reward Pool
+    SetEmissionSchedule { breakpoints: Vec<Breakpoint> },
+    /// Deposit ZEE into the reward pool
+    ///
+    /// Anyone can fund the reward pool. The total deposited is recorded in
+    /// Settings and every deposit is emitted as an event.
+    ///
+    /// Expected Accounts:
+    ///     1. `[writable,signer]` Transaction payer
+    ///     2. `[signer]` Funder
+    ///     3. `[writable]` Funder's ZEE Token Account
+    ///     4. `[writable]` Reward Pool
+    ///     5. `[writable]` Settings
+    ///     6. `[]` SPL Token Program
+    FundRewardPool { amount: u64 },
+    /// Calculate the yield a Beneficiary could claim right now, without
+    /// changing any account or emitting events. The amount is returned as a
+    /// little-endian `u64` in the return data.
+    ///
+    /// Expected Accounts:
+    ///     1. `[]` Beneficiary Account
+    ///     2. `[]` Settings
+    ///     3. `[]` Reward Pool
+    ///     4. `[]` Clock Sysvar
+    QueryPending,
+    /// Correct the stake of a Beneficiary that drifted from the expected value,
+    /// see `invariant`. Only the admin can resync beneficiaries.
+    ///
+    /// `tracked` holds the corrected stake the given Endpoints track for the
+    /// authority, one value for every role it holds, the primary's before the
+    /// secondary's. The shares of an Endpoint's stakes add up to within 2 per
+    /// stake of the share of the whole Endpoint, so every value and the rest of
+    /// the correction to `staked` are bounded by 2 per minimum stake of the
+    /// Endpoints. The yield accrued since the last pay out is settled under the
+    /// old stake.
+    ///
+    /// Expected Accounts:
+    ///     1. `[writable,signer]` Transaction payer
+    ///     2. `[writable]` Settings
+    ///     3. `[]` The admin's account
+    ///     4. `[signer]` The admin (or holder of the NFT)
+    ///     5. `[writable]` Beneficiary Account
+    ///     6. `[]` Clock Sysvar
+    ///     7. `[]` Reward Pool
+    ///     8+. `[writable]` Every Endpoint that has the authority as primary or
+    ///        secondary
+    Resync { staked: u64, tracked: Vec<u64> },
+}
+
+fn settings(program_id: &Pubkey) -> Pubkey {
+    Settings::program_address(program_id).0
+}
+
+fn beneficiary(authority: &Pubkey, program_id: &Pubkey) -> Pubkey {
+    Beneficiary::program_address(authority, program_id).0
+}
+
+fn build(
+    program_id: &Pubkey,
+    instruction: StakingInstruction,
+    accounts: Vec<AccountMeta>,
+) -> Instruction {
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data: instruction.try_to_vec().unwrap(),
+    }
+}
+
+/// Append the Emission Schedule account to an instruction that updates the
+/// rewards. Required once Settings references an emission schedule.
+pub fn with_emission_schedule(mut instruction: Instruction) -> Instruction {
+    let schedule = EmissionSchedule::program_address(&instruction.program_id).0;
+    instruction
+        .accounts
+        .push(AccountMeta::new_readonly(schedule, false));
+    instruction
+}
+
+/// Create an `Initialize` instruction
+pub fn initialize(
+    program_id: &Pubkey,
+    payer: &Pubkey,
+    mint: &Pubkey,
+    admin: Authority,
+    upgrade_authority: &Pubkey,
+    start_time: i64,
+    unbonding_duration: u64,
+) -> Instruction {
+    let (program_data, _) =
+        Pubkey::find_program_address(&[program_id.as_ref()], &bpf_loader_upgradeable::id());
+    build(
+        program_id,
+        StakingInstruction::Initialize {
+            start_time,
+            unbonding_duration,
+            admin,
+        },
+        vec![
+            AccountMeta::new(*payer, true),
+            AccountMeta::new(settings(program_id), false),
+            AccountMeta::new_readonly(PoolAuthority::program_address(program_id).0, false),
+            AccountMeta::new(RewardPool::program_address(program_id).0, false),
+            AccountMeta::new_readonly(*mint, false),
+            AccountMeta::new_readonly(admin.address(), false),
+            AccountMeta::new_readonly(sysvar::rent::id(), false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new_readonly(program_data, false),
+            AccountMeta::new_readonly(*upgrade_authority, true),
+        ],
+    )
+}
+
+/// Create a `RegisterEndpoint` instruction. The endpoint is a new keypair that
+/// has to sign.
+pub fn register_endpoint(
+    program_id: &Pubkey,
+    payer: &Pubkey,
+    endpoint: &Pubkey,
+    owner: Authority,
+    primary: &Pubkey,
+    secondary: &Pubkey,
+    split: Split,
+) -> Instruction {
+    build(
+        program_id,
+        StakingInstruction::RegisterEndpoint { owner, split },
+        vec![
+            AccountMeta::new(*payer, true),
+            AccountMeta::new(*endpoint, true),
+            AccountMeta::new_readonly(owner.address(), false),
+            AccountMeta::new_readonly(*primary, false),
+            AccountMeta::new(beneficiary(primary, program_id), false),
+            AccountMeta::new_readonly(*secondary, false),
+            AccountMeta::new(beneficiary(secondary, program_id), false),
+            AccountMeta::new_readonly(sysvar::rent::id(), false),
+            AccountMeta::new_readonly(sysvar::clock::id(), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new_readonly(settings(program_id), false),
+        ],
+    )
+}
+
+/// Create an `InitializeStake` instruction
+pub fn initialize_stake(
+    program_id: &Pubkey,
+    payer: &Pubkey,
+    staker: &Pubkey,
+    endpoint: &Pubkey,
+    mint: &Pubkey,
+) -> Instruction {
+    build(
+        program_id,
+        StakingInstruction::InitializeStake,
+        vec![
+            AccountMeta::new(*payer, true),
+            AccountMeta::new_readonly(*staker, true),
+            AccountMeta::new(Stake::fund_address(endpoint, staker, program_id).0, false),
+            AccountMeta::new(beneficiary(staker, program_id), false),
+            AccountMeta::new_readonly(*endpoint, false),
+            AccountMeta::new(
+                Stake::program_address(endpoint, staker, program_id).0,
+                false,
+            ),
+            AccountMeta::new_readonly(*mint, false),
+            AccountMeta::new_readonly(settings(program_id), false),
+            AccountMeta::new_readonly(sysvar::rent::id(), false),
+            AccountMeta::new_readonly(sysvar::clock::id(), false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    )
+}
+
+/// Create a `Stake` instruction. A negative amount unstakes.
+///
+/// The sunset destination is required once the Endpoint is sunset. It closes
+/// the Endpoint when the last stake is withdrawn.
+#[allow(clippy::too_many_arguments)]
+pub fn stake(
+    program_id: &Pubkey,
+    payer: &Pubkey,
+    staker: &Pubkey,
+    staker_token_account: &Pubkey,
+    endpoint: &Pubkey,
+    primary: &Pubkey,
+    secondary: &Pubkey,
+    amount: i64,
+    sunset_destination: Option<&Pubkey>,
+) -> Instruction {
+    let mut accounts = vec![
+        AccountMeta::new(*payer, true),
+        AccountMeta::new_readonly(*staker, true),
+        AccountMeta::new(beneficiary(staker, program_id), false),
+        AccountMeta::new(Stake::fund_address(endpoint, staker, program_id).0, false),
+        AccountMeta::new(*staker_token_account, false),
+        AccountMeta::new(*endpoint, false),
+        AccountMeta::new(beneficiary(primary, program_id), false),
+        AccountMeta::new(beneficiary(secondary, program_id), false),
+        AccountMeta::new_readonly(PoolAuthority::program_address(program_id).0, false),
+        AccountMeta::new(RewardPool::program_address(program_id).0, false),
+        AccountMeta::new(settings(program_id), false),
+        AccountMeta::new(
+            Stake::program_address(endpoint, staker, program_id).0,
+            false,
+        ),
+        AccountMeta::new_readonly(sysvar::clock::id(), false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new_readonly(system_program::id(), false),
+    ];
+    if let Some(destination) = sunset_destination {
+        accounts.push(AccountMeta::new(*destination, false));
+    }
+
+    build(program_id, StakingInstruction::Stake { amount }, accounts)
+}
+
+/// Create a `WithdrawUnbond` instruction
+pub fn withdraw_unbond(
+    program_id: &Pubkey,
+    payer: &Pubkey,
+    staker: &Pubkey,
+    staker_token_account: &Pubkey,
+    endpoint: &Pubkey,
+) -> Instruction {
+    build(
+        program_id,
+        StakingInstruction::WithdrawUnbond,
+        vec![
+            AccountMeta::new(*payer, true),
+            AccountMeta::new(
+                Stake::program_address(endpoint, staker, program_id).0,
+                false,
+            ),
+            AccountMeta::new_readonly(*staker, true),
+            AccountMeta::new(Stake::fund_address(endpoint, staker, program_id).0, false),
+            AccountMeta::new(*staker_token_account, false),
+            AccountMeta::new_readonly(*endpoint, false),
+            AccountMeta::new_readonly(settings(program_id), false),
+            AccountMeta::new_readonly(sysvar::clock::id(), false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ],
+    )
+}
+
+/// Create a `Claim` instruction
+pub fn claim(
+    program_id: &Pubkey,
+    payer: &Pubkey,
+    authority: &Pubkey,
+    authority_token_account: &Pubkey,
+) -> Instruction {
+    build(
+        program_id,
+        StakingInstruction::Claim,
+        vec![
+            AccountMeta::new(*payer, true),
+            AccountMeta::new_readonly(*authority, true),
+            AccountMeta::new(beneficiary(authority, program_id), false),
+            AccountMeta::new(*authority_token_account, false),
+            AccountMeta::new(settings(program_id), false),
+            AccountMeta::new_readonly(PoolAuthority::program_address(program_id).0, false),
+            AccountMeta::new(RewardPool::program_address(program_id).0, false),
+            AccountMeta::new_readonly(sysvar::clock::id(), false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ],
+    )
+}
+
+/// Create a `TransferEndpoint` instruction.
+///
+/// For a basic owner, `owner` and `owner_signer` are the owner's wallet. For an
+/// NFT owner, `owner` is the token account holding the NFT and `owner_signer`
+/// is the holder.
+pub fn transfer_endpoint(
+    program_id: &Pubkey,
+    payer: &Pubkey,
+    endpoint: &Pubkey,
+    owner: &Pubkey,
+    owner_signer: &Pubkey,
+    new_authority: Authority,
+) -> Instruction {
+    build(
+        program_id,
+        StakingInstruction::TransferEndpoint { new_authority },
+        vec![
+            AccountMeta::new(*payer, true),
+            AccountMeta::new(*endpoint, false),
+            AccountMeta::new_readonly(*owner, false),
+            AccountMeta::new_readonly(*owner_signer, true),
+            AccountMeta::new_readonly(new_authority.address(), false),
+        ],
+    )
+}
+
+/// Create a `ChangeBeneficiaries` instruction.
+/// See `transfer_endpoint` for the owner accounts.
+#[allow(clippy::too_many_arguments)]
+pub fn change_beneficiaries(
+    program_id: &Pubkey,
+    payer: &Pubkey,
+    endpoint: &Pubkey,
+    owner: &Pubkey,
+    owner_signer: &Pubkey,
+    old_primary: &Pubkey,
+    old_secondary: &Pubkey,
+    new_primary: &Pubkey,
+    new_secondary: &Pubkey,
+) -> Instruction {
+    build(
+        program_id,
+        StakingInstruction::ChangeBeneficiaries,
+        vec![
+            AccountMeta::new(*payer, true),
+            AccountMeta::new(*endpoint, false),
+            AccountMeta::new_readonly(*owner, false),
+            AccountMeta::new_readonly(*owner_signer, true),
+            AccountMeta::new(beneficiary(old_primary, program_id), false),
+            AccountMeta::new(beneficiary(old_secondary, program_id), false),
+            AccountMeta::new_readonly(*new_primary, false),
+            AccountMeta::new(beneficiary(new_primary, program_id), false),
+            AccountMeta::new_readonly(*new_secondary, false),
+            AccountMeta::new(beneficiary(new_secondary, program_id), false),
+            AccountMeta::new(settings(program_id), false),
+            AccountMeta::new_readonly(sysvar::rent::id(), false),
+            AccountMeta::new_readonly(sysvar::clock::id(), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new_readonly(RewardPool::program_address(program_id).0, false),
+        ],
+    )
+}
+
+/// Create an `UpdateSettings` instruction.
+///
+/// For a basic admin, `admin` and `admin_signer` are the admin's wallet. For an
+/// NFT admin, `admin` is the token account holding the NFT and `admin_signer`
+/// is the holder.
+#[allow(clippy::too_many_arguments)]
+pub fn update_settings(
+    program_id: &Pubkey,
+    payer: &Pubkey,
+    admin: &Pubkey,
+    admin_signer: &Pubkey,
+    unbonding_duration: Option<u64>,
+    emission: Option<u64>,
+    next_emission_change: Option<UnixTimestamp>,
+    split_bounds: Option<SplitBounds>,
+    decay: Option<EmissionDecay>,
+) -> Instruction {
+    build(
+        program_id,
+        StakingInstruction::UpdateSettings {
+            unbonding_duration,
+            emission,
+            next_emission_change,
+            split_bounds,
+            decay,
+        },
+        vec![
+            AccountMeta::new(*payer, true),
+            AccountMeta::new(settings(program_id), false),
+            AccountMeta::new_readonly(*admin, false),
+            AccountMeta::new_readonly(*admin_signer, true),
+            AccountMeta::new_readonly(sysvar::clock::id(), false),
+            AccountMeta::new_readonly(RewardPool::program_address(program_id).0, false),
+        ],
+    )
+}
+
+/// Create a `Migrate` instruction for an Endpoint, Beneficiary or Stake account.
+/// Migrating an Endpoint needs the addresses of all of its Stake accounts,
+/// `stakes` is empty otherwise.
+pub fn migrate(
+    program_id: &Pubkey,
+    payer: &Pubkey,
+    account: &Pubkey,
+    stakes: &[Pubkey],
+) -> Instruction {
+    let mut accounts = vec![
+        AccountMeta::new(*payer, true),
+        AccountMeta::new(*account, false),
+        AccountMeta::new_readonly(sysvar::rent::id(), false),
+        AccountMeta::new_readonly(system_program::id(), false),
+    ];
+    accounts.extend(
+        stakes
+            .iter()
+            .map(|stake| AccountMeta::new_readonly(*stake, false)),
+    );
+    build(
+        program_id,
+        StakingInstruction::Migrate { liability: 0 },
+        accounts,
+    )
+}
+
+/// Create a `Migrate` instruction for the Settings account, signed by the
+/// program's upgrade authority. `beneficiaries` are the addresses of every
+/// Beneficiary account.
+pub fn migrate_settings(
+    program_id: &Pubkey,
+    payer: &Pubkey,
+    upgrade_authority: &Pubkey,
+    beneficiaries: &[Pubkey],
+    liability: u64,
+) -> Instruction {
+    let (program_data, _) =
+        Pubkey::find_program_address(&[program_id.as_ref()], &bpf_loader_upgradeable::id());
+    let mut accounts = vec![
+        AccountMeta::new(*payer, true),
+        AccountMeta::new(settings(program_id), false),
+        AccountMeta::new_readonly(sysvar::rent::id(), false),
+        AccountMeta::new_readonly(system_program::id(), false),
+        AccountMeta::new_readonly(program_data, false),
+        AccountMeta::new_readonly(*upgrade_authority, true),
+    ];
+    accounts.extend(
+        beneficiaries
+            .iter()
+            .map(|beneficiary| AccountMeta::new_readonly(*beneficiary, false)),
+    );
+    build(
+        program_id,
+        StakingInstruction::Migrate { liability },
+        accounts,
+    )
+}
+
+/// Create a `Rebond` instruction
+pub fn rebond(
+    program_id: &Pubkey,
+    payer: &Pubkey,
+    staker: &Pubkey,
+    endpoint: &Pubkey,
+    primary: &Pubkey,
+    secondary: &Pubkey,
+    amount: u64,
+) -> Instruction {
+    build(
+        program_id,
+        StakingInstruction::Rebond { amount },
+        vec![
+            AccountMeta::new(*payer, true),
+            AccountMeta::new_readonly(*staker, true),
+            AccountMeta::new(beneficiary(staker, program_id), false),
+            AccountMeta::new(*endpoint, false),
+            AccountMeta::new(beneficiary(primary, program_id), false),
+            AccountMeta::new(beneficiary(secondary, program_id), false),
+            AccountMeta::new(settings(program_id), false),
+            AccountMeta::new(
+                Stake::program_address(endpoint, staker, program_id).0,
+                false,
+            ),
+            AccountMeta::new_readonly(sysvar::clock::id(), false),
+            AccountMeta::new_readonly(RewardPool::program_address(program_id).0, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    )
+}
+
+/// Create a `Redelegate` instruction. The endpoints are given as
+/// `(endpoint, primary, secondary)`.
+///
+/// The sunset destination is required once the source Endpoint is sunset. It
+/// closes the Endpoint when the last stake is moved away.
+pub fn redelegate(
+    program_id: &Pubkey,
+    payer: &Pubkey,
+    staker: &Pubkey,
+    (source, source_primary, source_secondary): (&Pubkey, &Pubkey, &Pubkey),
+    (destination, destination_primary, destination_secondary): (&Pubkey, &Pubkey, &Pubkey),
+    amount: u64,
+    sunset_destination: Option<&Pubkey>,
+) -> Instruction {
+    let mut accounts = vec![
+        AccountMeta::new(*payer, true),
+        AccountMeta::new_readonly(*staker, true),
+        AccountMeta::new(beneficiary(staker, program_id), false),
+        AccountMeta::new(*source, false),
+        AccountMeta::new(beneficiary(source_primary, program_id), false),
+        AccountMeta::new(beneficiary(source_secondary, program_id), false),
+        AccountMeta::new(Stake::program_address(source, staker, program_id).0, false),
+        AccountMeta::new(Stake::fund_address(source, staker, program_id).0, false),
+        AccountMeta::new(*destination, false),
+        AccountMeta::new(beneficiary(destination_primary, program_id), false),
+        AccountMeta::new(beneficiary(destination_secondary, program_id), false),
+        AccountMeta::new(
+            Stake::program_address(destination, staker, program_id).0,
+            false,
+        ),
+        AccountMeta::new(
+            Stake::fund_address(destination, staker, program_id).0,
+            false,
+        ),
+        AccountMeta::new(settings(program_id), false),
+        AccountMeta::new_readonly(sysvar::clock::id(), false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new_readonly(RewardPool::program_address(program_id).0, false),
+        AccountMeta::new_readonly(system_program::id(), false),
+    ];
+    if let Some(destination) = sunset_destination {
+        accounts.push(AccountMeta::new(*destination, false));
+    }
+
+    build(
+        program_id,
+        StakingInstruction::Redelegate { amount },
+        accounts,
+    )
+}
+
+/// Create a `CloseStake` instruction
+pub fn close_stake(
+    program_id: &Pubkey,
+    payer: &Pubkey,
+    staker: &Pubkey,
+    staker_associated: &Pubkey,
+    endpoint: &Pubkey,
+    destination: &Pubkey,
+) -> Instruction {
+    build(
+        program_id,
+        StakingInstruction::CloseStake,
+        vec![
+            AccountMeta::new(*payer, true),
+            AccountMeta::new_readonly(*staker, true),
+            AccountMeta::new(
+                Stake::program_address(endpoint, staker, program_id).0,
+                false,
+            ),
+            AccountMeta::new(Stake::fund_address(endpoint, staker, program_id).0, false),
+            AccountMeta::new_readonly(*endpoint, false),
+            AccountMeta::new(*destination, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new(*staker_associated, false),
+        ],
+    )
+}
+
+/// Create a `CloseEndpoint` instruction.
+/// See `transfer_endpoint` for the owner accounts.
+pub fn close_endpoint(
+    program_id: &Pubkey,
+    payer: &Pubkey,
+    endpoint: &Pubkey,
+    owner: &Pubkey,
+    owner_signer: &Pubkey,
+    destination: &Pubkey,
+    sunset: bool,
+) -> Instruction {
+    build(
+        program_id,
+        StakingInstruction::CloseEndpoint { sunset },
+        vec![
+            AccountMeta::new(*payer, true),
+            AccountMeta::new(*endpoint, false),
+            AccountMeta::new_readonly(*owner, false),
+            AccountMeta::new_readonly(*owner_signer, true),
+            AccountMeta::new(*destination, false),
+        ],
+    )
+}
+
+/// Create a `CloseBeneficiary` instruction
+pub fn close_beneficiary(program_id: &Pubkey, payer: &Pubkey, authority: &Pubkey) -> Instruction {
+    build(
+        program_id,
+        StakingInstruction::CloseBeneficiary,
+        vec![
+            AccountMeta::new(*payer, true),
+            AccountMeta::new(*authority, true),
+            AccountMeta::new(beneficiary(authority, program_id), false),
+            AccountMeta::new(settings(program_id), false),
+            AccountMeta::new_readonly(sysvar::clock::id(), false),
+            AccountMeta::new_readonly(RewardPool::program_address(program_id).0, false),
+        ],
+    )
+}
+
+/// Create a `ChangeSplit` instruction. `primary` and `secondary` are the
+/// Endpoint's beneficiaries. See `transfer_endpoint` for the owner accounts.
+#[allow(clippy::too_many_arguments)]
+pub fn change_split(
+    program_id: &Pubkey,
+    payer: &Pubkey,
+    endpoint: &Pubkey,
+    owner: &Pubkey,
+    owner_signer: &Pubkey,
+    primary: &Pubkey,
+    secondary: &Pubkey,
+    split: Split,
+) -> Instruction {
+    build(
+        program_id,
+        StakingInstruction::ChangeSplit { split },
+        vec![
+            AccountMeta::new(*payer, true),
+            AccountMeta::new(*endpoint, false),
+            AccountMeta::new_readonly(*owner, false),
+            AccountMeta::new_readonly(*owner_signer, true),
+            AccountMeta::new(settings(program_id), false),
+            AccountMeta::new(beneficiary(primary, program_id), false),
+            AccountMeta::new(beneficiary(secondary, program_id), false),
+            AccountMeta::new_readonly(sysvar::clock::id(), false),
+            AccountMeta::new_readonly(RewardPool::program_address(program_id).0, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    )
+}
+
+/// Create a `SetEmissionSchedule` instruction.
+/// See `update_settings` for the admin accounts.
+pub fn set_emission_schedule(
+    program_id: &Pubkey,
+    payer: &Pubkey,
+    admin: &Pubkey,
+    admin_signer: &Pubkey,
+    breakpoints: Vec<Breakpoint>,
+) -> Instruction {
+    build(
+        program_id,
+        StakingInstruction::SetEmissionSchedule { breakpoints },
+        vec![
+            AccountMeta::new(*payer, true),
+            AccountMeta::new(settings(program_id), false),
+            AccountMeta::new_readonly(*admin, false),
+            AccountMeta::new_readonly(*admin_signer, true),
+            AccountMeta::new(EmissionSchedule::program_address(program_id).0, false),
+            AccountMeta::new_readonly(sysvar::rent::id(), false),
+            AccountMeta::new_readonly(sysvar::clock::id(), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new_readonly(RewardPool::program_address(program_id).0, false),
+        ],
+    )
+}
+
+/// Create a `FundRewardPool` instruction
+pub fn fund_reward_pool(
+    program_id: &Pubkey,
+    payer: &Pubkey,
+    funder: &Pubkey,
+    funder_token_account: &Pubkey,
+    amount: u64,
+) -> Instruction {
+    build(
+        program_id,
+        StakingInstruction::FundRewardPool { amount },
+        vec![
+            AccountMeta::new(*payer, true),
+            AccountMeta::new_readonly(*funder, true),
+            AccountMeta::new(*funder_token_account, false),
+            AccountMeta::new(RewardPool::program_address(program_id).0, false),
+            AccountMeta::new(settings(program_id), false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ],
+    )
+}
+
+/// Create a `QueryPending` instruction for the Beneficiary of `authority`
+pub fn query_pending(program_id: &Pubkey, authority: &Pubkey) -> Instruction {
+    build(
+        program_id,
+        StakingInstruction::QueryPending,
+        vec![
+            AccountMeta::new_readonly(beneficiary(authority, program_id), false),
+            AccountMeta::new_readonly(settings(program_id), false),
+            AccountMeta::new_readonly(RewardPool::program_address(program_id).0, false),
+            AccountMeta::new_readonly(sysvar::clock::id(), false),
+        ],
+    )
+}
+
+/// Create a `Resync` instruction for the Beneficiary of `authority`.
+/// See `update_settings` for the admin accounts.
+#[allow(clippy::too_many_arguments)]
+pub fn resync(
+    program_id: &Pubkey,
+    payer: &Pubkey,
+    admin: &Pubkey,
+    admin_signer: &Pubkey,
+    authority: &Pubkey,
+    endpoints: &[Pubkey],
+    staked: u64,
+    tracked: Vec<u64>,
+) -> Instruction {
+    let mut accounts = vec![
+        AccountMeta::new(*payer, true),
+        AccountMeta::new(settings(program_id), false),
+        AccountMeta::new_readonly(*admin, false),
+        AccountMeta::new_readonly(*admin_signer, true),
+        AccountMeta::new(beneficiary(authority, program_id), false),
+        AccountMeta::new_readonly(sysvar::clock::id(), false),
+        AccountMeta::new_readonly(RewardPool::program_address(program_id).0, false),
+    ];
+    accounts.extend(
+        endpoints
+            .iter()
+            .map(|endpoint| AccountMeta::new(*endpoint, false)),
+    );
+    build(
+        program_id,
+        StakingInstruction::Resync { staked, tracked },
+        accounts,
+    )
 }
 
 #[cfg(test)]
@@ -147,4 +1070,60 @@ mod tests {
 
         assert_eq!(data, should);
     }
+
+    #[test]
+    pub fn test_stake_builder() {
+        let program_id = Pubkey::new_unique();
+        let payer = Pubkey::new_unique();
+        let staker = Pubkey::new_unique();
+        let endpoint = Pubkey::new_unique();
+        let destination = Pubkey::new_unique();
+
+        let ix = stake(
+            &program_id,
+            &payer,
+            &staker,
+            &Pubkey::new_unique(),
+            &endpoint,
+            &Pubkey::new_unique(),
+            &Pubkey::new_unique(),
+            -500,
+            None,
+        );
+        assert_eq!(ix.program_id, program_id);
+        assert_eq!(ix.accounts.len(), 15);
+        assert_eq!(
+            StakingInstruction::try_from_slice(&ix.data).unwrap(),
+            StakingInstruction::Stake { amount: -500 }
+        );
+        assert!(ix.accounts[0].is_signer && ix.accounts[0].is_writable);
+        assert!(ix.accounts[1].is_signer && !ix.accounts[1].is_writable);
+        assert_eq!(ix.accounts[1].pubkey, staker);
+        assert_eq!(
+            ix.accounts[11].pubkey,
+            Stake::program_address(&endpoint, &staker, &program_id).0
+        );
+        assert_eq!(ix.accounts[10].pubkey, settings(&program_id));
+        assert_eq!(ix.accounts[14].pubkey, system_program::id());
+
+        let ix = with_emission_schedule(stake(
+            &program_id,
+            &payer,
+            &staker,
+            &Pubkey::new_unique(),
+            &endpoint,
+            &Pubkey::new_unique(),
+            &Pubkey::new_unique(),
+            -500,
+            Some(&destination),
+        ));
+        assert_eq!(ix.accounts.len(), 17);
+        assert_eq!(ix.accounts[14].pubkey, system_program::id());
+        assert_eq!(ix.accounts[15].pubkey, destination);
+        assert!(ix.accounts[15].is_writable);
+        assert_eq!(
+            ix.accounts[16].pubkey,
+            EmissionSchedule::program_address(&program_id).0
+        );
+    }
 }
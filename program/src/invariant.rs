@@ -0,0 +1,352 @@
+//! Invariants of the stake split.
+//!
+//! The stake of an Endpoint is split between the stakers and the Endpoint's
+//! beneficiaries. A staker's Beneficiary holds
+//! `stake.split.apply(stake.total_stake).0` of each of their stakes, under the
+//! split the stake was last changed with. The primary and secondary hold the
+//! `primary_stake` and `secondary_stake` the Endpoint tracks, which follow the
+//! Endpoint's split right away when it changes.
+//!
+//! The tracked stake should be the sum of `.1` and `.2` of every stake under
+//! the Endpoint's split, but `ChangeSplit` moves the whole Endpoint at once,
+//! which rounds differently. An Endpoint migrated from the unversioned layout is
+//! seeded with the sum over its stakes. Beneficiaries and tracked stake that
+//! differ from the expected value can be corrected with the `Resync`
+//! instruction.
+//! Only available with the `client` feature.
+
+use std::collections::HashMap;
+
+use solana_program::{instruction::Instruction, pubkey::Pubkey};
+
+use crate::client::ProgramState;
+use crate::instruction;
+
+/// The shares of an Endpoint's stake
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct EndpointShares {
+    pub endpoint: Pubkey,
+    pub total_stake: u64,
+    /// The sum of the stakers' shares of the individual stakes
+    pub stakers: u64,
+    /// The sum of the primary's shares of the individual stakes under the
+    /// Endpoint's split
+    pub primary: u64,
+    /// The sum of the secondary's shares of the individual stakes under the
+    /// Endpoint's split
+    pub secondary: u64,
+    /// The primary's stake as tracked by the Endpoint
+    pub tracked_primary: u64,
+    /// The secondary's stake as tracked by the Endpoint
+    pub tracked_secondary: u64,
+}
+
+impl EndpointShares {
+    /// The amount of stake that is not held by any beneficiary. Negative if the
+    /// beneficiaries hold more than the Endpoint's total stake. Stakes that are
+    /// still accounted under an older split count towards it as well.
+    pub fn drift(&self) -> i64 {
+        self.total_stake as i64 - (self.stakers + self.primary + self.secondary) as i64
+    }
+
+    /// How much more stake the Endpoint tracks for the primary and secondary
+    /// than the sum of their shares of the individual stakes, the rounding of
+    /// `ChangeSplit`
+    pub fn tracking_drift(&self) -> i64 {
+        (self.tracked_primary + self.tracked_secondary) as i64
+            - (self.primary + self.secondary) as i64
+    }
+}
+
+/// A Beneficiary whose stake, or the stake its Endpoints track for it, differs
+/// from the expected value
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct BeneficiaryDrift {
+    pub authority: Pubkey,
+    pub staked: u64,
+    pub expected: u64,
+    /// The Endpoints that have the authority as primary or secondary, sorted
+    pub endpoints: Vec<Pubkey>,
+    /// The stake the `endpoints` should track for the authority, one value for
+    /// every role it holds, the primary's before the secondary's
+    pub tracked: Vec<u64>,
+}
+
+impl BeneficiaryDrift {
+    pub fn drift(&self) -> i64 {
+        self.staked as i64 - self.expected as i64
+    }
+}
+
+/// The result of checking the invariants
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Invariants {
+    /// The expected stake of every authority
+    pub expected: HashMap<Pubkey, u64>,
+    /// The shares of every Endpoint, sorted by address
+    pub endpoints: Vec<EndpointShares>,
+    /// The beneficiaries that drifted, sorted by authority
+    pub drifted: Vec<BeneficiaryDrift>,
+}
+
+impl Invariants {
+    /// The stake of all Endpoints combined that is not held by any beneficiary
+    pub fn split_drift(&self) -> i64 {
+        self.endpoints.iter().map(|e| e.drift()).sum()
+    }
+
+    /// The tracking drift of all Endpoints combined, see
+    /// `EndpointShares::tracking_drift`
+    pub fn tracking_drift(&self) -> i64 {
+        self.endpoints.iter().map(|e| e.tracking_drift()).sum()
+    }
+
+    /// `Resync` instructions that correct every drifted Beneficiary.
+    /// See `instruction::update_settings` for the admin accounts.
+    pub fn resync_instructions(
+        &self,
+        program_id: &Pubkey,
+        payer: &Pubkey,
+        admin: &Pubkey,
+        admin_signer: &Pubkey,
+    ) -> Vec<Instruction> {
+        self.drifted
+            .iter()
+            .map(|d| {
+                instruction::resync(
+                    program_id,
+                    payer,
+                    admin,
+                    admin_signer,
+                    &d.authority,
+                    &d.endpoints,
+                    d.expected,
+                    d.tracked.clone(),
+                )
+            })
+            .collect()
+    }
+}
+
+/// The shares of an Endpoint, calculated from its Stake accounts
+pub fn endpoint_shares(state: &ProgramState, endpoint: &Pubkey) -> EndpointShares {
+    let account = &state.endpoints[endpoint];
+    let mut shares = EndpointShares {
+        endpoint: *endpoint,
+        total_stake: account.total_stake,
+        stakers: 0,
+        primary: 0,
+        secondary: 0,
+        tracked_primary: account.primary_stake,
+        tracked_secondary: account.secondary_stake,
+    };
+    for entry in state.stakes_by_endpoint(endpoint) {
+        let (staker, _, _) = entry.stake.split.apply(entry.stake.total_stake);
+        let (_, primary, secondary) = account.split.apply(entry.stake.total_stake);
+        shares.stakers += staker;
+        shares.primary += primary;
+        shares.secondary += secondary;
+    }
+    shares
+}
+
+/// The stake every authority's Beneficiary should have
+pub fn expected_staked(state: &ProgramState) -> HashMap<Pubkey, u64> {
+    let mut expected = HashMap::new();
+    for (key, endpoint) in &state.endpoints {
+        let shares = endpoint_shares(state, key);
+        *expected.entry(endpoint.primary).or_insert(0) += shares.primary;
+        *expected.entry(endpoint.secondary).or_insert(0) += shares.secondary;
+    }
+    for entry in &state.stakes {
+        let (staker, _, _) = entry.stake.split.apply(entry.stake.total_stake);
+        *expected.entry(entry.stake.staker).or_insert(0) += staker;
+    }
+    expected
+}
+
+/// The Endpoints that have `authority` as primary or secondary, sorted
+pub fn endpoints_of(state: &ProgramState, authority: &Pubkey) -> Vec<Pubkey> {
+    let mut endpoints: Vec<Pubkey> = state
+        .endpoints
+        .iter()
+        .filter(|(_, e)| e.primary == *authority || e.secondary == *authority)
+        .map(|(key, _)| *key)
+        .collect();
+    endpoints.sort();
+    endpoints
+}
+
+/// Check the split invariants of a snapshot of the program's accounts
+pub fn check(state: &ProgramState) -> Invariants {
+    let expected = expected_staked(state);
+
+    let mut endpoints: Vec<EndpointShares> = state
+        .endpoints
+        .keys()
+        .map(|endpoint| endpoint_shares(state, endpoint))
+        .collect();
+    endpoints.sort_by_key(|e| e.endpoint);
+
+    let mut drifted = vec![];
+    for beneficiary in state.beneficiaries.values() {
+        let authority = beneficiary.authority;
+        let endpoint_keys = endpoints_of(state, &authority);
+        let mut tracked = vec![];
+        let mut tracking_drifted = false;
+        for key in &endpoint_keys {
+            let endpoint = &state.endpoints[key];
+            let shares = endpoints.iter().find(|e| e.endpoint == *key).unwrap();
+            if endpoint.primary == authority {
+                tracked.push(shares.primary);
+                tracking_drifted |= shares.primary != shares.tracked_primary;
+            }
+            if endpoint.secondary == authority {
+                tracked.push(shares.secondary);
+                tracking_drifted |= shares.secondary != shares.tracked_secondary;
+            }
+        }
+
+        let expected = expected.get(&authority).copied().unwrap_or(0);
+        if beneficiary.staked != expected || tracking_drifted {
+            drifted.push(BeneficiaryDrift {
+                authority,
+                staked: beneficiary.staked,
+                expected,
+                endpoints: endpoint_keys,
+                tracked,
+            });
+        }
+    }
+    drifted.sort_by_key(|d| d.authority);
+
+    Invariants {
+        expected,
+        endpoints,
+        drifted,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::account::{Authority, Beneficiary, Endpoint, Split, Stake, Versioned};
+    use crate::client::Addresses;
+    use crate::instruction::StakingInstruction;
+    use crate::{split_stake, ZERO_KEY};
+    use borsh::BorshDeserialize;
+
+    #[test]
+    pub fn test_invariants() {
+        let program_id = Pubkey::new_unique();
+        let addresses = Addresses::new(program_id);
+        let endpoint_key = Pubkey::new_unique();
+        let primary = Pubkey::new_unique();
+        let amounts = [1_001u64; 3];
+        let total: u64 = amounts.iter().sum();
+
+        // the shares of the individual stakes, as seeded by `Migrate`
+        let per_stake: (u64, u64) = amounts.iter().fold((0, 0), |(p, s), a| {
+            let (_, primary, secondary) = split_stake(*a);
+            (p + primary, s + secondary)
+        });
+        // a primary that drifted to its share of the whole endpoint, which
+        // rounds differently from the per stake shares
+        let (_, whole_primary, whole_secondary) = split_stake(total);
+        let endpoint = Endpoint {
+            creation_date: 0,
+            total_stake: total,
+            owner: Authority::Basic(primary),
+            primary,
+            secondary: ZERO_KEY,
+            split: Split::DEFAULT,
+            primary_stake: whole_primary,
+            secondary_stake: per_stake.1,
+            sunset: ZERO_KEY,
+        };
+        let beneficiary = |authority: Pubkey, staked: u64| {
+            Beneficiary {
+                authority,
+                staked,
+                reward_debt: 0,
+                holding: 0,
+            }
+            .to_account_data()
+            .unwrap()
+        };
+
+        let mut accounts = vec![(endpoint_key, endpoint.to_account_data().unwrap())];
+        let mut stakers = vec![];
+        for amount in amounts.iter() {
+            let staker = Pubkey::new_unique();
+            let stake = Stake {
+                creation_date: 0,
+                total_stake: *amount,
+                staker,
+                split: Split::DEFAULT,
+                unbonding_amount: 0,
+                unbonding: vec![],
+            };
+            accounts.push((
+                addresses.stake(&endpoint_key, &staker),
+                stake.to_account_data().unwrap(),
+            ));
+            accounts.push((
+                addresses.beneficiary(&staker),
+                beneficiary(staker, split_stake(*amount).0),
+            ));
+            stakers.push(staker);
+        }
+
+        accounts.push((
+            addresses.beneficiary(&primary),
+            beneficiary(primary, whole_primary),
+        ));
+        accounts.push((
+            addresses.beneficiary(&ZERO_KEY),
+            beneficiary(ZERO_KEY, per_stake.1),
+        ));
+
+        let state = ProgramState::from_accounts(&program_id, accounts);
+        let invariants = check(&state);
+
+        assert_eq!(per_stake, (1_425, 150));
+        assert_eq!((whole_primary, whole_secondary), (1_426, 150));
+        assert_eq!(invariants.expected[&primary], per_stake.0);
+        assert_eq!(invariants.expected[&stakers[1]], 476);
+        assert_eq!(
+            invariants.drifted,
+            vec![BeneficiaryDrift {
+                authority: primary,
+                staked: whole_primary,
+                expected: per_stake.0,
+                endpoints: vec![endpoint_key],
+                tracked: vec![per_stake.0],
+            }]
+        );
+        assert_eq!(invariants.drifted[0].drift(), 1);
+
+        // the per stake shares add up, but round differently from the whole endpoint
+        assert_eq!(invariants.endpoints[0].stakers, 3 * 476);
+        assert_eq!(invariants.endpoints[0].drift(), 0);
+        assert_eq!(invariants.split_drift(), 0);
+        assert_eq!(invariants.endpoints[0].tracking_drift(), 1);
+        assert_eq!(invariants.tracking_drift(), 1);
+
+        let admin = Pubkey::new_unique();
+        let instructions = invariants.resync_instructions(&program_id, &admin, &admin, &admin);
+        assert_eq!(instructions.len(), 1);
+        assert_eq!(
+            instructions[0].accounts[4].pubkey,
+            addresses.beneficiary(&primary)
+        );
+        assert_eq!(instructions[0].accounts[7].pubkey, endpoint_key);
+        assert_eq!(
+            StakingInstruction::try_from_slice(&instructions[0].data).unwrap(),
+            StakingInstruction::Resync {
+                staked: per_stake.0,
+                tracked: vec![per_stake.0],
+            }
+        );
+    }
+}
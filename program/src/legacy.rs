@@ -0,0 +1,283 @@
+//! Account layouts from before accounts were versioned.
+//!
+//! Accounts created by earlier deployments are bare Borsh structs without a
+//! discriminator or version prefix. They can be brought up to date with the
+//! `Migrate` instruction. Each layout has a distinct length, which is used to
+//! identify the type of account during the migration.
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::{clock::UnixTimestamp, pubkey::Pubkey};
+
+use crate::{
+    account::{self, Authority, EmissionDecay, Split, SplitBounds, Unbonding},
+    ZERO_KEY,
+};
+
+/// Unversioned layout of `account::Settings`
+#[derive(Debug, PartialEq, BorshDeserialize, BorshSerialize, Clone, Copy, Eq)]
+pub struct Settings {
+    pub token: Pubkey,
+    pub unbonding_duration: u64,
+    pub next_emission_change: UnixTimestamp,
+    pub emission: u64,
+    pub total_stake: u64,
+    pub reward_per_share: u128,
+    pub last_reward: UnixTimestamp,
+}
+
+impl Settings {
+    pub const LEN: usize = 88;
+
+    /// The unversioned layout had no admin and did not track the unpaid yield.
+    /// The liability and the stake that earns rewards are taken from
+    /// `beneficiaries`, which has to contain every Beneficiary of the deployment.
+    pub fn migrate(
+        &self,
+        admin: Authority,
+        beneficiaries: &[account::Beneficiary],
+    ) -> account::Settings {
+        account::Settings {
+            token: self.token,
+            admin,
+            unbonding_duration: self.unbonding_duration,
+            split_bounds: SplitBounds::UNRESTRICTED,
+            next_emission_change: self.next_emission_change,
+            emission: self.emission,
+            decay: EmissionDecay::DEFAULT,
+            emission_schedule: ZERO_KEY,
+            total_stake: beneficiaries
+                .iter()
+                .filter(|b| b.earns_rewards())
+                .map(|b| b.staked)
+                .sum(),
+            reward_per_share: self.reward_per_share,
+            last_reward: self.last_reward,
+            liability: self.liability(beneficiaries),
+            total_funded: 0,
+        }
+    }
+
+    /// The yield owed to `beneficiaries` as of `last_reward`, the liability to
+    /// supply when migrating. Every Beneficiary of the deployment has to be passed.
+    /// The yield of the `ZERO_KEY` Beneficiary cannot be claimed and is left out.
+    pub fn liability(&self, beneficiaries: &[account::Beneficiary]) -> u64 {
+        beneficiaries
+            .iter()
+            .filter(|b| b.earns_rewards())
+            .map(|b| b.calculate_holding(self.reward_per_share) - b.reward_debt + b.holding)
+            .sum()
+    }
+}
+
+/// Unversioned layout of `account::Endpoint`
+#[derive(Debug, PartialEq, BorshDeserialize, BorshSerialize, Clone, Copy, Eq)]
+pub struct Endpoint {
+    pub creation_date: UnixTimestamp,
+    pub total_stake: u64,
+    pub owner: Authority,
+    pub primary: Pubkey,
+    pub secondary: Pubkey,
+}
+
+impl Endpoint {
+    pub const LEN: usize = 113;
+
+    /// The primary and secondary are seeded with the sum of their shares of the
+    /// individual stakes, the same way withdrawals take them out again. `stakes`
+    /// has to contain every Stake of the Endpoint.
+    pub fn migrate(&self, stakes: &[account::Stake]) -> account::Endpoint {
+        let (primary_stake, secondary_stake) =
+            stakes.iter().fold((0, 0), |(primary, secondary), stake| {
+                let (_, p, s) = stake.split.apply(stake.total_stake);
+                (primary + p, secondary + s)
+            });
+        account::Endpoint {
+            creation_date: self.creation_date,
+            total_stake: self.total_stake,
+            owner: self.owner,
+            primary: self.primary,
+            secondary: self.secondary,
+            split: Split::DEFAULT,
+            primary_stake,
+            secondary_stake,
+            sunset: ZERO_KEY,
+        }
+    }
+}
+
+/// Unversioned layout of `account::Beneficiary`
+#[derive(Debug, PartialEq, BorshDeserialize, BorshSerialize, Clone, Copy, Eq)]
+pub struct Beneficiary {
+    pub authority: Pubkey,
+    pub staked: u64,
+    pub reward_debt: u64,
+    pub holding: u64,
+}
+
+impl Beneficiary {
+    pub const LEN: usize = 56;
+
+    pub fn migrate(&self) -> account::Beneficiary {
+        account::Beneficiary {
+            authority: self.authority,
+            staked: self.staked,
+            reward_debt: self.reward_debt,
+            holding: self.holding,
+        }
+    }
+}
+
+/// Unversioned layout of `account::Stake`
+#[derive(Debug, PartialEq, BorshDeserialize, BorshSerialize, Clone, Copy, Eq)]
+pub struct Stake {
+    pub creation_date: UnixTimestamp,
+    pub total_stake: u64,
+    pub staker: Pubkey,
+    pub unbonding_end: UnixTimestamp,
+    pub unbonding_amount: u64,
+}
+
+impl Stake {
+    pub const LEN: usize = 64;
+
+    pub fn migrate(&self) -> account::Stake {
+        account::Stake {
+            creation_date: self.creation_date,
+            total_stake: self.total_stake,
+            staker: self.staker,
+            split: Split::DEFAULT,
+            unbonding_amount: self.unbonding_amount,
+            unbonding: match self.unbonding_amount {
+                0 => vec![],
+                amount => vec![Unbonding {
+                    end: self.unbonding_end,
+                    amount,
+                }],
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn test_legacy_lengths() {
+        let settings = Settings {
+            token: Pubkey::new_unique(),
+            unbonding_duration: 0,
+            next_emission_change: 0,
+            emission: 0,
+            total_stake: 0,
+            reward_per_share: 0,
+            last_reward: 0,
+        };
+        assert_eq!(settings.try_to_vec().unwrap().len(), Settings::LEN);
+
+        let endpoint = Endpoint {
+            creation_date: 0,
+            total_stake: 0,
+            owner: Authority::Basic(Pubkey::new_unique()),
+            primary: Pubkey::new_unique(),
+            secondary: Pubkey::new_unique(),
+        };
+        assert_eq!(endpoint.try_to_vec().unwrap().len(), Endpoint::LEN);
+
+        let beneficiary = Beneficiary {
+            authority: Pubkey::new_unique(),
+            staked: 0,
+            reward_debt: 0,
+            holding: 0,
+        };
+        assert_eq!(beneficiary.try_to_vec().unwrap().len(), Beneficiary::LEN);
+
+        let stake = Stake {
+            creation_date: 0,
+            total_stake: 0,
+            staker: Pubkey::new_unique(),
+            unbonding_end: 0,
+            unbonding_amount: 0,
+        };
+        assert_eq!(stake.try_to_vec().unwrap().len(), Stake::LEN);
+    }
+
+    #[test]
+    pub fn test_legacy_liability() {
+        let mut settings = Settings {
+            token: Pubkey::new_unique(),
+            unbonding_duration: 0,
+            next_emission_change: 0,
+            emission: 0,
+            total_stake: 350,
+            reward_per_share: 3 * crate::PRECISION,
+            last_reward: 0,
+        };
+        let beneficiaries = [
+            Beneficiary {
+                authority: Pubkey::new_unique(),
+                staked: 100,
+                reward_debt: 100,
+                holding: 7,
+            },
+            Beneficiary {
+                authority: Pubkey::new_unique(),
+                staked: 200,
+                reward_debt: 600,
+                holding: 0,
+            },
+            Beneficiary {
+                authority: ZERO_KEY,
+                staked: 50,
+                reward_debt: 0,
+                holding: 3,
+            },
+        ]
+        .map(|b| b.migrate());
+        assert_eq!(settings.liability(&beneficiaries), 200 + 7);
+
+        // the share of the missing secondary does not earn rewards
+        let admin = Authority::Basic(Pubkey::new_unique());
+        let migrated = settings.migrate(admin, &beneficiaries);
+        assert_eq!(migrated.liability, 207);
+        assert_eq!(migrated.total_stake, 300);
+        assert_eq!(migrated.admin, admin);
+
+        settings.reward_per_share = 6 * crate::PRECISION;
+        assert_eq!(settings.liability(&beneficiaries), 500 + 7 + 600);
+    }
+
+    #[test]
+    pub fn test_legacy_endpoint_shares() {
+        let endpoint = Endpoint {
+            creation_date: 0,
+            total_stake: 2078,
+            owner: Authority::Basic(Pubkey::new_unique()),
+            primary: Pubkey::new_unique(),
+            secondary: Pubkey::new_unique(),
+        };
+        let stake = Stake {
+            creation_date: 0,
+            total_stake: 1039,
+            staker: Pubkey::new_unique(),
+            unbonding_end: 0,
+            unbonding_amount: 0,
+        }
+        .migrate();
+
+        // the share of the whole endpoint rounds down once instead of per stake
+        assert_eq!(Split::DEFAULT.apply(endpoint.total_stake).1, 987);
+
+        let mut migrated = endpoint.migrate(&[stake.clone(), stake.clone()]);
+        assert_eq!(migrated.primary_stake, 988);
+        assert_eq!(migrated.secondary_stake, 102);
+
+        // both stakes can be withdrawn in full
+        for _ in 0..2 {
+            let (_, primary, secondary) = stake.split.apply(stake.total_stake);
+            migrated.primary_stake = migrated.primary_stake.checked_sub(primary).unwrap();
+            migrated.secondary_stake = migrated.secondary_stake.checked_sub(secondary).unwrap();
+        }
+        assert_eq!((migrated.primary_stake, migrated.secondary_stake), (0, 0));
+    }
+}
@@ -1,10 +1,22 @@
 use solana_program::pubkey::Pubkey;
 
+use crate::account::Split;
+
 pub mod account;
+#[cfg(feature = "client")]
+pub mod audit;
+#[cfg(feature = "client")]
+pub mod client;
 mod entrypoint;
 pub mod error;
+pub mod event;
 pub mod instruction;
+#[cfg(feature = "client")]
+pub mod invariant;
+pub mod legacy;
 pub mod processor;
+#[cfg(feature = "client")]
+pub mod projection;
 
 pub const ZERO_KEY: Pubkey = Pubkey::new_from_array([0; 32]);
 
@@ -26,13 +38,10 @@ pub const PRECISION: u128 = 1_000_000_000_000;
 /// Split Stake
 ///
 /// Divides the staked amount of ZEE into three components
-/// for the (staker, owner, referrer) at the
+/// for the (staker, owner, referrer) at the default
 /// rates of (47.5%, 47.5%, 5%). Remainders go to the staker.
 pub fn split_stake(amount: u64) -> (u64, u64, u64) {
-    let secondary = amount / 20;
-    let primary = (amount - secondary) / 2;
-
-    (amount - primary - secondary, primary, secondary)
+    Split::DEFAULT.apply(amount)
 }
 
 #[cfg(test)]
@@ -1,24 +1,32 @@
-use borsh::{BorshDeserialize, BorshSerialize};
+use std::convert::TryInto;
+
+use borsh::BorshDeserialize;
 use solana_program::{
     account_info::{next_account_info, AccountInfo},
+    bpf_loader_upgradeable,
     clock::{Clock, UnixTimestamp},
     entrypoint::ProgramResult,
     msg,
-    program::{invoke, invoke_signed},
+    program::{invoke, invoke_signed, set_return_data},
     program_error::ProgramError,
     program_pack::Pack,
     pubkey::Pubkey,
-    system_instruction::create_account,
+    system_instruction::{create_account, transfer},
+    system_program,
     sysvar::{rent::Rent, Sysvar},
 };
 
 use spl_token::state::{Account, Mint};
 
 use crate::{
-    account::{Authority, Beneficiary, Endpoint, PoolAuthority, RewardPool, Settings, Stake},
+    account::{
+        Access, AccountType, Authority, Beneficiary, Breakpoint, EmissionDecay, EmissionSchedule,
+        Endpoint, PoolAuthority, RewardPool, Settings, Split, SplitBounds, Stake, Versioned,
+    },
     error::StakingError,
+    event::StakingEvent,
     instruction::StakingInstruction,
-    pool_transfer, split_stake, verify_associated, BASE_REWARD, MINIMUM_STAKE, SECONDS_PER_YEAR,
+    legacy, BASE_REWARD, MINIMUM_STAKE, SECONDS_PER_YEAR, ZERO_KEY,
 };
 
 /// Transfer ZEE from the reward pool
@@ -44,6 +52,21 @@ macro_rules! pool_transfer {
             Err(err) => Err(err),
         }
     };
+    // pays as much of the amount as the pool holds, evaluates to the amount paid.
+    // the balance is read before the transfer so the fund is not borrowed during it
+    (partial, $fund_type:ident, $fund:expr, $recipient:expr, $authority:expr, $program_id:expr, $amount:expr) => {{
+        let balance = Account::unpack(&$fund.data.borrow()).map(|fund| fund.amount);
+        match balance {
+            Ok(balance) => match std::cmp::min(balance, $amount) {
+                0 => Ok(0),
+                paid => {
+                    pool_transfer!($fund_type, $fund, $recipient, $authority, $program_id, paid)
+                        .map(|_| paid)
+                }
+            },
+            Err(err) => Err(err),
+        }
+    }};
 }
 /// Verify an Associated Account
 ///
@@ -82,19 +105,16 @@ macro_rules! verify_associated {
 
 #[macro_export]
 macro_rules! create_beneficiary {
-    ($beneficiary_info:expr, $authority_info:expr, $funder_info:expr, $rent:expr, $program_id:expr) => {
-        let seed = Beneficiary::verify_program_address(
-            $beneficiary_info.key,
-            $authority_info.key,
-            $program_id,
-        )?;
+    ($beneficiary_info:expr, $authority:expr, $funder_info:expr, $rent:expr, $program_id:expr) => {
+        let seed =
+            Beneficiary::verify_program_address($beneficiary_info.key, $authority, $program_id)?;
         let beneficiary = Beneficiary {
-            authority: *$authority_info.key,
+            authority: *$authority,
             staked: 0,
             reward_debt: 0,
             holding: 0,
         };
-        let data = beneficiary.try_to_vec()?;
+        let data = beneficiary.to_account_data()?;
 
         let space = data.len();
         let lamports = $rent.minimum_balance(space);
@@ -108,12 +128,38 @@ macro_rules! create_beneficiary {
                 $program_id,
             ),
             &[$funder_info.clone(), $beneficiary_info.clone()],
-            &[&[b"beneficiary", &$authority_info.key.to_bytes(), &[seed]]],
+            &[&[b"beneficiary", &$authority.to_bytes(), &[seed]]],
         )?;
         $beneficiary_info.data.borrow_mut().copy_from_slice(&data);
     };
 }
 
+/// Load a Beneficiary that is referenced by a Stake or Endpoint. If it was closed
+/// with `CloseBeneficiary`, it is recreated at the funder's expense, which needs
+/// the System Program at the position the instruction documents for it.
+fn load_beneficiary<'a>(
+    beneficiary_info: &AccountInfo<'a>,
+    authority: &Pubkey,
+    funder_info: &AccountInfo<'a>,
+    system_program_info: &AccountInfo<'a>,
+    program_id: &Pubkey,
+) -> Result<Beneficiary, ProgramError> {
+    if beneficiary_info.data_is_empty() {
+        if !system_program::check_id(system_program_info.key) {
+            msg!(
+                "beneficiary of {} was closed, the system program is needed to recreate it",
+                authority
+            );
+            return Err(StakingError::InvalidBeneficiaryAccount.into());
+        }
+        let rent = Rent::get()?;
+        create_beneficiary!(beneficiary_info, authority, funder_info, &rent, program_id);
+        msg!("beneficiary of {} recreated", authority);
+    }
+
+    Beneficiary::from_account_info(beneficiary_info, authority, program_id, Access::WRITE)
+}
+
 /// Helper struct to deal with endpoints where multiple beneficiaries are the same.
 /// The additions and subtractions to the stake amount are added up in this helper class
 /// before being applied to the beneficiary account and paid out at the end
@@ -145,6 +191,320 @@ fn insert_beneficiary(
     }
 }
 
+/// Read the upgrade authority of a program from its ProgramData account
+fn upgrade_authority(
+    program_id: &Pubkey,
+    program_data_info: &AccountInfo,
+) -> Result<Option<Pubkey>, ProgramError> {
+    let (program_data, _) =
+        Pubkey::find_program_address(&[program_id.as_ref()], &bpf_loader_upgradeable::id());
+    if program_data != *program_data_info.key
+        || *program_data_info.owner != bpf_loader_upgradeable::id()
+    {
+        return Err(StakingError::InvalidUpgradeAuthority.into());
+    }
+
+    // bincode encoded `UpgradeableLoaderState::ProgramData`:
+    // u32 variant (3), u64 slot, Option<Pubkey> upgrade authority
+    let data = program_data_info.data.borrow();
+    if data.len() < 45 || data[0..4] != [3, 0, 0, 0] {
+        return Err(StakingError::InvalidUpgradeAuthority.into());
+    }
+    match data[12] {
+        1 => Ok(Some(Pubkey::new_from_array(
+            data[13..45].try_into().unwrap(),
+        ))),
+        _ => Ok(None),
+    }
+}
+
+/// Verify that the upgrade authority of the program signed
+fn verify_upgrade_authority(
+    program_id: &Pubkey,
+    program_data_info: &AccountInfo,
+    upgrade_authority_info: &AccountInfo,
+) -> ProgramResult {
+    match upgrade_authority(program_id, program_data_info)? {
+        Some(authority)
+            if authority == *upgrade_authority_info.key && upgrade_authority_info.is_signer =>
+        {
+            Ok(())
+        }
+        _ => Err(StakingError::InvalidUpgradeAuthority.into()),
+    }
+}
+
+/// The stake accounted to the primary or secondary of an Endpoint after its
+/// share changed from `old` to `new`. `ChangeSplit` moves the shares of the
+/// whole Endpoint at once, which rounds differently from the individual stakes,
+/// so the difference is taken out with the last stake.
+fn endpoint_share(tracked: u64, old: u64, new: u64, total_stake: u64) -> u64 {
+    match total_stake {
+        0 => 0,
+        _ => (tracked + new).saturating_sub(old),
+    }
+}
+
+/// Move the beneficiaries' shares of a stake from `old_stake` to its current
+/// total under the Endpoint's split.
+///
+/// The staker's share is taken out under the split the stake was last
+/// accounted under, exactly as it was put in. The primary and secondary were
+/// already moved to the Endpoint's split by `ChangeSplit`, see `change_split`.
+fn reassign_shares(
+    beneficiaries: &mut [WorkingBeneficiary],
+    (staker, primary, secondary): (usize, usize, usize),
+    stake: &mut Stake,
+    endpoint: &mut Endpoint,
+    old_stake: u64,
+) {
+    let (old_staker, _, _) = stake.split.apply(old_stake);
+    let (_, old_primary, old_secondary) = endpoint.split.apply(old_stake);
+    let (new_staker, new_primary, new_secondary) = endpoint.split.apply(stake.total_stake);
+
+    let primary_stake = endpoint_share(
+        endpoint.primary_stake,
+        old_primary,
+        new_primary,
+        endpoint.total_stake,
+    );
+    let secondary_stake = endpoint_share(
+        endpoint.secondary_stake,
+        old_secondary,
+        new_secondary,
+        endpoint.total_stake,
+    );
+
+    beneficiaries[staker].add += new_staker;
+    beneficiaries[staker].sub += old_staker;
+    beneficiaries[primary].add += primary_stake;
+    beneficiaries[primary].sub += endpoint.primary_stake;
+    beneficiaries[secondary].add += secondary_stake;
+    beneficiaries[secondary].sub += endpoint.secondary_stake;
+
+    endpoint.primary_stake = primary_stake;
+    endpoint.secondary_stake = secondary_stake;
+    stake.split = endpoint.split;
+}
+
+/// Move the primary's and secondary's shares of an Endpoint's total stake to a
+/// new split. The stakers' shares move lazily, see `reassign_shares`.
+fn change_split(
+    beneficiaries: &mut [WorkingBeneficiary],
+    (primary, secondary): (usize, usize),
+    endpoint: &mut Endpoint,
+    split: Split,
+) {
+    let (_, old_primary, old_secondary) = endpoint.split.apply(endpoint.total_stake);
+    let (_, new_primary, new_secondary) = split.apply(endpoint.total_stake);
+
+    let primary_stake = endpoint_share(
+        endpoint.primary_stake,
+        old_primary,
+        new_primary,
+        endpoint.total_stake,
+    );
+    let secondary_stake = endpoint_share(
+        endpoint.secondary_stake,
+        old_secondary,
+        new_secondary,
+        endpoint.total_stake,
+    );
+
+    beneficiaries[primary].add += primary_stake;
+    beneficiaries[primary].sub += endpoint.primary_stake;
+    beneficiaries[secondary].add += secondary_stake;
+    beneficiaries[secondary].sub += endpoint.secondary_stake;
+
+    endpoint.split = split;
+    endpoint.primary_stake = primary_stake;
+    endpoint.secondary_stake = secondary_stake;
+}
+
+/// Check a corrected stake that an Endpoint tracks for one of its roles
+/// against the role's share of the whole Endpoint.
+///
+/// The secondary's share of every stake rounds down by less than 1, the
+/// primary's share is taken from what remains of the stake and rounds down by
+/// less than 1 on top, so the shares of `n` stakes add up to within `2n` of the
+/// share of the whole Endpoint.
+fn resync_share(tracked: Option<u64>, share: u64, bound: u64) -> Result<u64, ProgramError> {
+    let tracked = tracked.ok_or(ProgramError::InvalidInstructionData)?;
+    if tracked.max(share) - tracked.min(share) > bound {
+        msg!(
+            "tracked stake {} is more than {} away from the share {}",
+            tracked,
+            bound,
+            share
+        );
+        return Err(StakingError::ResyncOutOfBounds.into());
+    }
+    Ok(tracked)
+}
+
+/// Apply the accumulated stake changes and pay out the pending reward.
+/// `total_stake` follows the stake the beneficiaries that earn rewards hold,
+/// see `Settings::total_stake`.
+fn pay_out_beneficiaries(
+    beneficiaries: &mut [WorkingBeneficiary],
+    reward_per_share: u128,
+    total_stake: &mut u64,
+) -> ProgramResult {
+    for working in beneficiaries {
+        let staked = (working.beneficiary.staked + working.add)
+            .checked_sub(working.sub)
+            .ok_or(ProgramError::InvalidArgument)?;
+        if working.beneficiary.earns_rewards() {
+            *total_stake = (*total_stake + staked)
+                .checked_sub(working.beneficiary.staked)
+                .ok_or(ProgramError::InvalidArgument)?;
+        }
+        working.beneficiary.pay_out(staked, reward_per_share);
+    }
+    Ok(())
+}
+
+/// Close a program account by moving all of its lamports to the destination
+/// and zeroing the data
+fn close_program_account(account_info: &AccountInfo, destination_info: &AccountInfo) {
+    let lamports = account_info.lamports();
+    **destination_info.lamports.borrow_mut() += lamports;
+    **account_info.lamports.borrow_mut() = 0;
+    account_info
+        .data
+        .borrow_mut()
+        .iter_mut()
+        .for_each(|b| *b = 0);
+}
+
+/// The sunset destination of an Endpoint that is being sunset. Instructions
+/// that withdraw from an Endpoint take it as the next account after their
+/// fixed accounts, but only once the Endpoint is sunset.
+fn sunset_destination<'a, 'b>(
+    endpoint: &Endpoint,
+    iter: &mut std::slice::Iter<'a, AccountInfo<'b>>,
+) -> Result<Option<&'a AccountInfo<'b>>, ProgramError> {
+    if !endpoint.is_sunset() {
+        return Ok(None);
+    }
+
+    let destination_info = next_account_info(iter)?;
+    if *destination_info.key != endpoint.sunset {
+        msg!(
+            "sunset destination {} does not match {}",
+            destination_info.key,
+            endpoint.sunset
+        );
+        return Err(StakingError::InvalidSunsetDestination.into());
+    }
+    Access::WRITE.verify(destination_info)?;
+    Ok(Some(destination_info))
+}
+
+/// Save an Endpoint or close it if it is being sunset and all stake has been withdrawn.
+/// See `sunset_destination` for the destination.
+fn save_endpoint(
+    endpoint: &Endpoint,
+    endpoint_info: &AccountInfo,
+    sunset_destination_info: Option<&AccountInfo>,
+) -> ProgramResult {
+    match sunset_destination_info {
+        Some(destination_info) if endpoint.total_stake == 0 => {
+            close_program_account(endpoint_info, destination_info);
+            msg!("sunset endpoint {} closed", endpoint_info.key);
+            Ok(())
+        }
+        _ => endpoint.save(endpoint_info),
+    }
+}
+
+/// A Stake of an Endpoint that is being migrated, in either layout. Stakes
+/// cannot change before their Endpoint is migrated, so it does not matter
+/// whether the Stake was migrated first.
+fn migrating_stake(
+    stake_info: &AccountInfo,
+    endpoint: &Pubkey,
+    program_id: &Pubkey,
+) -> Result<Stake, ProgramError> {
+    if stake_info.owner != program_id {
+        return Err(StakingError::InvalidStakeAccount.into());
+    }
+    let data = stake_info.data.borrow();
+    let stake = match AccountType::from_discriminator(&data) {
+        None if data.len() == legacy::Stake::LEN => legacy::Stake::try_from_slice(&data)?.migrate(),
+        _ => Stake::from_account_data(&data)?,
+    };
+    Stake::verify_program_address(stake_info.key, endpoint, &stake.staker, program_id)?;
+    Ok(stake)
+}
+
+/// A Beneficiary that is being migrated along with the Settings, in either
+/// layout. Beneficiaries cannot change before the Settings are migrated.
+fn migrating_beneficiary(
+    beneficiary_info: &AccountInfo,
+    program_id: &Pubkey,
+) -> Result<Beneficiary, ProgramError> {
+    if beneficiary_info.owner != program_id {
+        return Err(StakingError::InvalidBeneficiaryAccount.into());
+    }
+    let data = beneficiary_info.data.borrow();
+    let beneficiary = match AccountType::from_discriminator(&data) {
+        None if data.len() == legacy::Beneficiary::LEN => {
+            legacy::Beneficiary::try_from_slice(&data)?.migrate()
+        }
+        _ => Beneficiary::from_account_data(&data)?,
+    };
+    Beneficiary::verify_program_address(beneficiary_info.key, &beneficiary.authority, program_id)?;
+    Ok(beneficiary)
+}
+
+/// True if an account is passed more than once
+fn has_duplicates(infos: &[AccountInfo]) -> bool {
+    infos
+        .iter()
+        .enumerate()
+        .any(|(i, info)| infos[..i].iter().any(|other| other.key == info.key))
+}
+
+/// The balance of the reward pool and the EmissionSchedule, if Settings
+/// references one. The schedule account can be passed anywhere in the
+/// instruction's accounts.
+fn emission_sources(
+    settings: &Settings,
+    reward_pool_info: &AccountInfo,
+    accounts: &[AccountInfo],
+    program_id: &Pubkey,
+) -> Result<(u64, Option<EmissionSchedule>), ProgramError> {
+    RewardPool::verify_program_address(reward_pool_info.key, program_id)?;
+    let pool_balance = Account::unpack(&reward_pool_info.data.borrow())?.amount;
+
+    if settings.emission_schedule == ZERO_KEY {
+        return Ok((pool_balance, None));
+    }
+
+    let schedule_info = accounts
+        .iter()
+        .find(|info| *info.key == settings.emission_schedule)
+        .ok_or(StakingError::MissingEmissionSchedule)?;
+    let schedule = EmissionSchedule::from_account_info(schedule_info, program_id, Access::READ)?;
+    Ok((pool_balance, Some(schedule)))
+}
+
+/// Update the reward per share, capped at what the reward pool can cover.
+/// See `emission_sources` for the accounts.
+fn update_rewards(
+    settings: &mut Settings,
+    now: UnixTimestamp,
+    reward_pool_info: &AccountInfo,
+    accounts: &[AccountInfo],
+    program_id: &Pubkey,
+) -> ProgramResult {
+    let (pool_balance, schedule) =
+        emission_sources(settings, reward_pool_info, accounts, program_id)?;
+    settings.update_rewards(now, schedule.as_ref(), pool_balance);
+    Ok(())
+}
+
 pub struct Processor {}
 impl Processor {
     pub fn process(program_id: &Pubkey, accounts: &[AccountInfo], input: &[u8]) -> ProgramResult {
@@ -157,9 +517,16 @@ impl Processor {
             StakingInstruction::Initialize {
                 start_time,
                 unbonding_duration,
-            } => Self::process_initialize(program_id, accounts, start_time, unbonding_duration),
-            StakingInstruction::RegisterEndpoint { owner } => {
-                Self::process_register_endpoint(program_id, accounts, owner)
+                admin,
+            } => Self::process_initialize(
+                program_id,
+                accounts,
+                start_time,
+                unbonding_duration,
+                admin,
+            ),
+            StakingInstruction::RegisterEndpoint { owner, split } => {
+                Self::process_register_endpoint(program_id, accounts, owner, split)
             }
             StakingInstruction::InitializeStake => {
                 Self::process_initialize_stake(program_id, accounts)
@@ -177,6 +544,50 @@ impl Processor {
             StakingInstruction::ChangeBeneficiaries => {
                 Self::process_change_beneficiaries(program_id, accounts)
             }
+            StakingInstruction::UpdateSettings {
+                unbonding_duration,
+                emission,
+                next_emission_change,
+                split_bounds,
+                decay,
+            } => Self::process_update_settings(
+                program_id,
+                accounts,
+                unbonding_duration,
+                emission,
+                next_emission_change,
+                split_bounds,
+                decay,
+            ),
+            StakingInstruction::Migrate { liability } => {
+                Self::process_migrate(program_id, accounts, liability)
+            }
+            StakingInstruction::Rebond { amount } => {
+                Self::process_rebond(program_id, accounts, amount)
+            }
+            StakingInstruction::Redelegate { amount } => {
+                Self::process_redelegate(program_id, accounts, amount)
+            }
+            StakingInstruction::CloseStake => Self::process_close_stake(program_id, accounts),
+            StakingInstruction::CloseEndpoint { sunset } => {
+                Self::process_close_endpoint(program_id, accounts, sunset)
+            }
+            StakingInstruction::CloseBeneficiary => {
+                Self::process_close_beneficiary(program_id, accounts)
+            }
+            StakingInstruction::ChangeSplit { split } => {
+                Self::process_change_split(program_id, accounts, split)
+            }
+            StakingInstruction::SetEmissionSchedule { breakpoints } => {
+                Self::process_set_emission_schedule(program_id, accounts, breakpoints)
+            }
+            StakingInstruction::FundRewardPool { amount } => {
+                Self::process_fund_reward_pool(program_id, accounts, amount)
+            }
+            StakingInstruction::QueryPending => Self::process_query_pending(program_id, accounts),
+            StakingInstruction::Resync { staked, tracked } => {
+                Self::process_resync(program_id, accounts, staked, tracked)
+            }
         }
     }
 
@@ -185,6 +596,7 @@ impl Processor {
         accounts: &[AccountInfo],
         start_time: UnixTimestamp,
         unbonding_duration: u64,
+        admin: Authority,
     ) -> ProgramResult {
         let iter = &mut accounts.iter();
         let funder_info = next_account_info(iter)?;
@@ -192,12 +604,19 @@ impl Processor {
         let pool_authority_info = next_account_info(iter)?;
         let reward_pool_info = next_account_info(iter)?;
         let token_info = next_account_info(iter)?;
+        let admin_info = next_account_info(iter)?;
         let rent_info = next_account_info(iter)?;
         let token_program_info = next_account_info(iter)?;
+        let _system_program_info = next_account_info(iter)?;
+        let program_data_info = next_account_info(iter)?;
+        let upgrade_authority_info = next_account_info(iter)?;
 
         let rent = Rent::from_account_info(rent_info)?;
         spl_token::check_program_account(token_program_info.key)?;
 
+        verify_upgrade_authority(program_id, program_data_info, upgrade_authority_info)?;
+        admin.verify(admin_info)?;
+
         if settings_info.data_len() > 0 {
             return Err(StakingError::ProgramAlreadyInitialized.into());
         }
@@ -207,17 +626,23 @@ impl Processor {
 
         let settings = Settings {
             token: *token_info.key,
+            admin,
             unbonding_duration,
+            split_bounds: SplitBounds::UNRESTRICTED,
             next_emission_change: start_time + SECONDS_PER_YEAR as i64,
             emission: BASE_REWARD as u64,
+            decay: EmissionDecay::DEFAULT,
+            emission_schedule: ZERO_KEY,
             reward_per_share: 0u128,
             last_reward: start_time,
+            liability: 0,
+            total_funded: 0,
             total_stake: 0,
         };
 
         msg!("Settings: {:?}", settings);
 
-        let data = settings.try_to_vec()?;
+        let data = settings.to_account_data()?;
 
         let space = data.len();
         let lamports = rent.minimum_balance(space);
@@ -280,6 +705,7 @@ impl Processor {
         program_id: &Pubkey,
         accounts: &[AccountInfo],
         owner: Authority,
+        split: Split,
     ) -> ProgramResult {
         let iter = &mut accounts.iter();
         let funder_info = next_account_info(iter)?;
@@ -291,15 +717,17 @@ impl Processor {
         let secondary_beneficiary_info = next_account_info(iter)?;
         let rent_info = next_account_info(iter)?;
         let clock_info = next_account_info(iter)?;
+        let _system_program_info = next_account_info(iter)?;
+        let settings_info = next_account_info(iter)?;
 
         let rent = Rent::from_account_info(rent_info)?;
         let clock = Clock::from_account_info(clock_info)?;
+        let settings = Settings::from_account_info(settings_info, program_id, Access::READ)?;
 
-        owner.verify(&owner_info)?;
+        owner.verify(owner_info)?;
+        settings.split_bounds.verify(&split)?;
 
-        if !endpoint_info.is_signer {
-            return Err(StakingError::InvalidEndpointAccount.into());
-        }
+        Access::WRITE_SIGNER.verify(endpoint_info)?;
 
         if !endpoint_info.data_is_empty() {
             return Err(StakingError::EndpointAccountAlreadyExists.into());
@@ -308,7 +736,7 @@ impl Processor {
         if primary_beneficiary_info.data_is_empty() {
             create_beneficiary!(
                 primary_beneficiary_info,
-                primary_info,
+                primary_info.key,
                 funder_info,
                 &rent,
                 program_id
@@ -325,7 +753,7 @@ impl Processor {
         if secondary_beneficiary_info.data_is_empty() {
             create_beneficiary!(
                 secondary_beneficiary_info,
-                secondary_info,
+                secondary_info.key,
                 funder_info,
                 &rent,
                 program_id
@@ -345,9 +773,13 @@ impl Processor {
             owner,
             primary: *primary_info.key,
             secondary: *secondary_info.key,
+            split,
+            primary_stake: 0,
+            secondary_stake: 0,
+            sunset: ZERO_KEY,
         };
 
-        let data = endpoint.try_to_vec()?;
+        let data = endpoint.to_account_data()?;
 
         let lamports = rent.minimum_balance(data.len());
         let space = data.len() as u64;
@@ -366,6 +798,15 @@ impl Processor {
 
         endpoint_info.data.borrow_mut().copy_from_slice(&data);
 
+        StakingEvent::EndpointRegistered {
+            endpoint: *endpoint_info.key,
+            owner,
+            primary: endpoint.primary,
+            secondary: endpoint.secondary,
+            split,
+        }
+        .emit();
+
         Ok(())
     }
 
@@ -398,12 +839,15 @@ impl Processor {
             return Err(StakingError::MissingStakeSignature.into());
         }
 
-        let settings = Settings::from_account_info(settings_info, program_id)?;
+        let settings = Settings::from_account_info(settings_info, program_id, Access::READ)?;
         if settings.token != *token_info.key {
             return Err(StakingError::InvalidToken.into());
         }
 
-        Endpoint::from_account_info(endpoint_info, program_id)?;
+        let endpoint = Endpoint::from_account_info(endpoint_info, program_id, Access::READ)?;
+        if endpoint.is_sunset() {
+            return Err(StakingError::EndpointSunset.into());
+        }
 
         let seed = Stake::verify_program_address(
             stake_info.key,
@@ -416,23 +860,17 @@ impl Processor {
             creation_date: clock.unix_timestamp,
             total_stake: 0,
             staker: *staker_info.key,
-            unbonding_end: clock.unix_timestamp,
+            split: endpoint.split,
             unbonding_amount: 0,
+            unbonding: vec![],
         };
 
-        let data = stake.try_to_vec()?;
-
-        let lamports = rent.minimum_balance(data.len());
-        let space = data.len() as u64;
+        // leave room for a full unbonding queue
+        let lamports = rent.minimum_balance(Stake::LEN);
+        let space = Stake::LEN as u64;
 
         invoke_signed(
-            &create_account(
-                funder_info.key,
-                stake_info.key,
-                lamports,
-                space as u64,
-                program_id,
-            ),
+            &create_account(funder_info.key, stake_info.key, lamports, space, program_id),
             &[funder_info.clone(), stake_info.clone()],
             &[&[
                 b"stake",
@@ -442,15 +880,12 @@ impl Processor {
             ]],
         )?;
 
-        stake_info
-            .data
-            .borrow_mut()
-            .copy_from_slice(&stake.try_to_vec()?);
+        stake.save(stake_info)?;
 
         if staker_beneficiary_info.data_is_empty() {
             create_beneficiary!(
                 staker_beneficiary_info,
-                staker_info,
+                staker_info.key,
                 funder_info,
                 &rent,
                 program_id
@@ -509,7 +944,7 @@ impl Processor {
         raw_amount: i64,
     ) -> ProgramResult {
         let iter = &mut accounts.iter();
-        let _funder_info = next_account_info(iter)?;
+        let funder_info = next_account_info(iter)?;
         let staker_info = next_account_info(iter)?;
         let staker_beneficiary_info = next_account_info(iter)?;
         let staker_fund_info = next_account_info(iter)?;
@@ -522,6 +957,8 @@ impl Processor {
         let settings_info = next_account_info(iter)?;
         let stake_info = next_account_info(iter)?;
         let clock_info = next_account_info(iter)?;
+        let _token_program_info = next_account_info(iter)?;
+        let system_program_info = next_account_info(iter)?;
 
         let clock = Clock::from_account_info(clock_info)?;
 
@@ -529,30 +966,44 @@ impl Processor {
             return Err(StakingError::MissingStakeSignature.into());
         }
 
-        let mut settings = Settings::from_account_info(settings_info, program_id)?;
+        let mut settings = Settings::from_account_info(settings_info, program_id, Access::WRITE)?;
 
-        let mut endpoint = Endpoint::from_account_info(endpoint_info, program_id)?;
+        let mut endpoint = Endpoint::from_account_info(endpoint_info, program_id, Access::WRITE)?;
+        let sunset_destination_info = sunset_destination(&endpoint, iter)?;
 
         let staker_assoc =
             verify_associated!(staker_associated_info, settings.token, *staker_info.key)?;
 
-        let mut stake =
-            Stake::from_account_info(stake_info, endpoint_info.key, staker_info.key, program_id)?;
+        let mut stake = Stake::from_account_info(
+            stake_info,
+            endpoint_info.key,
+            staker_info.key,
+            program_id,
+            Access::WRITE,
+        )?;
 
         // holds the beneficiaries so we don't have duplicate objects
         let mut beneficiaries = vec![];
         let staker_beneficiary = insert_beneficiary(
             &mut beneficiaries,
             *staker_info.key,
-            Beneficiary::from_account_info(staker_beneficiary_info, staker_info.key, program_id)?,
+            load_beneficiary(
+                staker_beneficiary_info,
+                staker_info.key,
+                funder_info,
+                system_program_info,
+                program_id,
+            )?,
         );
 
         let primary_beneficiary = insert_beneficiary(
             &mut beneficiaries,
             endpoint.primary,
-            Beneficiary::from_account_info(
+            load_beneficiary(
                 primary_beneficiary_info,
                 &endpoint.primary,
+                funder_info,
+                system_program_info,
                 program_id,
             )?,
         );
@@ -560,17 +1011,22 @@ impl Processor {
         let secondary_beneficiary = insert_beneficiary(
             &mut beneficiaries,
             endpoint.secondary,
-            Beneficiary::from_account_info(
+            load_beneficiary(
                 secondary_beneficiary_info,
                 &endpoint.secondary,
+                funder_info,
+                system_program_info,
                 program_id,
             )?,
         );
 
         let staking = raw_amount >= 0;
-        let amount = raw_amount.abs() as u64;
+        let amount = raw_amount.unsigned_abs();
 
         if staking {
+            if amount > 0 && endpoint.is_sunset() {
+                return Err(StakingError::EndpointSunset.into());
+            }
             if stake.total_stake + amount < MINIMUM_STAKE {
                 msg!(
                     "existing stake: {}, amount: {}, minimum required: {}",
@@ -589,58 +1045,81 @@ impl Processor {
             }
         }
 
-        settings.update_rewards(clock.unix_timestamp);
+        update_rewards(
+            &mut settings,
+            clock.unix_timestamp,
+            reward_pool_info,
+            accounts,
+            program_id,
+        )?;
 
-        let (old_staker, old_primary, old_secondary) = split_stake(stake.total_stake);
+        let old_stake = stake.total_stake;
         if staking {
             stake.total_stake += amount;
             endpoint.total_stake += amount;
-            settings.total_stake += amount;
         } else {
             stake.total_stake -= amount;
             endpoint.total_stake -= amount;
-            settings.total_stake -= amount;
         }
-        let (new_staker, new_primary, new_secondary) = split_stake(stake.total_stake);
 
         // PROCESS STAKER'S REWARD
 
-        beneficiaries[staker_beneficiary].add += new_staker;
-        beneficiaries[staker_beneficiary].sub += old_staker;
-        beneficiaries[primary_beneficiary].add += new_primary;
-        beneficiaries[primary_beneficiary].sub += old_primary;
-        beneficiaries[secondary_beneficiary].add += new_secondary;
-        beneficiaries[secondary_beneficiary].sub += old_secondary;
-
-        for working in &mut beneficiaries {
-            working.beneficiary.pay_out(
-                (working.beneficiary.staked + working.add)
-                    .checked_sub(working.sub)
-                    .unwrap(),
-                settings.reward_per_share,
-            );
-        }
+        reassign_shares(
+            &mut beneficiaries,
+            (
+                staker_beneficiary,
+                primary_beneficiary,
+                secondary_beneficiary,
+            ),
+            &mut stake,
+            &mut endpoint,
+            old_stake,
+        );
+        pay_out_beneficiaries(
+            &mut beneficiaries,
+            settings.reward_per_share,
+            &mut settings.total_stake,
+        )?;
 
-        // allow them to re-stake their pending reward immediately
-        if staking
-            && staker_assoc.amount + beneficiaries[staker_beneficiary].beneficiary.holding < amount
-        {
-            return Err(StakingError::StakerBalanceTooLow.into());
-        }
-        // pay out pending reward first
-        pool_transfer!(
+        // pay out pending reward first, as far as the pool can cover it.
+        // the rest stays in holding so withdrawing never depends on the pool.
+        // rounding can credit dust beyond the liability, which also stays
+        let holding = beneficiaries[staker_beneficiary].beneficiary.holding;
+        let claimed = pool_transfer!(
+            partial,
             RewardPool,
             reward_pool_info,
             staker_associated_info,
             pool_authority_info,
             program_id,
-            beneficiaries[staker_beneficiary].beneficiary.holding
+            holding.min(settings.liability)
         )?;
-        msg!(
-            "zee claimed: {}",
-            beneficiaries[staker_beneficiary].beneficiary.holding
-        );
-        beneficiaries[staker_beneficiary].beneficiary.holding = 0;
+        if claimed < holding {
+            StakingEvent::RewardShortfall {
+                authority: *staker_info.key,
+                paid: claimed,
+                unpaid: holding - claimed,
+            }
+            .emit();
+        }
+
+        // allow them to re-stake their pending reward immediately
+        if staking && staker_assoc.amount + claimed < amount {
+            return Err(StakingError::StakerBalanceTooLow.into());
+        }
+        msg!("zee claimed: {}", claimed);
+        if claimed > 0 {
+            StakingEvent::Claimed {
+                authority: *staker_info.key,
+                amount: claimed,
+            }
+            .emit();
+        }
+        beneficiaries[staker_beneficiary].beneficiary.holding -= claimed;
+        settings.liability = settings
+            .liability
+            .checked_sub(claimed)
+            .ok_or(ProgramError::InvalidArgument)?;
 
         if staking {
             // transfer the new staked amount to fund pool
@@ -659,412 +1138,1897 @@ impl Processor {
                     staker_info.clone(),
                 ],
             )?;
+            if amount > 0 {
+                StakingEvent::Staked {
+                    staker: *staker_info.key,
+                    endpoint: *endpoint_info.key,
+                    amount,
+                    total_stake: stake.total_stake,
+                }
+                .emit();
+            }
         } else {
-            stake.unbonding_amount += amount;
-            stake.unbonding_end = settings.unbonding_duration as i64 + clock.unix_timestamp;
+            if !stake.can_unbond() {
+                // make room in the queue by paying out the tranches that have ended
+                let released = stake.release_unbonded(clock.unix_timestamp);
+                if released > 0 {
+                    Stake::verify_fund_address(
+                        staker_fund_info.key,
+                        endpoint_info.key,
+                        staker_info.key,
+                        program_id,
+                    )?;
+                    let (_, stake_seed) =
+                        Stake::program_address(endpoint_info.key, staker_info.key, program_id);
+                    invoke_signed(
+                        &spl_token::instruction::transfer(
+                            &spl_token::id(),
+                            staker_fund_info.key,
+                            staker_associated_info.key,
+                            stake_info.key,
+                            &[],
+                            released,
+                        )?,
+                        &[
+                            staker_fund_info.clone(),
+                            staker_associated_info.clone(),
+                            stake_info.clone(),
+                        ],
+                        &[&[
+                            b"stake",
+                            &endpoint_info.key.to_bytes(),
+                            &staker_info.key.to_bytes(),
+                            &[stake_seed],
+                        ]],
+                    )?;
+                    StakingEvent::Withdrawn {
+                        staker: *staker_info.key,
+                        endpoint: *endpoint_info.key,
+                        amount: released,
+                    }
+                    .emit();
+                }
+            }
+
+            let unbonding_end = settings.unbonding_duration as i64 + clock.unix_timestamp;
+            stake.unbond(amount, unbonding_end)?;
+            StakingEvent::Unstaked {
+                staker: *staker_info.key,
+                endpoint: *endpoint_info.key,
+                amount,
+                total_stake: stake.total_stake,
+                unbonding_end,
+            }
+            .emit();
         }
 
-        settings_info
-            .data
-            .borrow_mut()
-            .copy_from_slice(&settings.try_to_vec()?);
-        stake_info
-            .data
-            .borrow_mut()
-            .copy_from_slice(&stake.try_to_vec()?);
+        settings.save(settings_info)?;
+        stake.save(stake_info)?;
 
-        endpoint_info
-            .data
-            .borrow_mut()
-            .copy_from_slice(&endpoint.try_to_vec()?);
+        save_endpoint(&endpoint, endpoint_info, sunset_destination_info)?;
 
         // some of these may be write identical data to the same account
-        staker_beneficiary_info
-            .data
-            .borrow_mut()
-            .copy_from_slice(&beneficiaries[staker_beneficiary].beneficiary.try_to_vec()?);
-        primary_beneficiary_info.data.borrow_mut().copy_from_slice(
-            &beneficiaries[primary_beneficiary]
-                .beneficiary
-                .try_to_vec()?,
-        );
-        secondary_beneficiary_info
-            .data
-            .borrow_mut()
-            .copy_from_slice(
-                &beneficiaries[secondary_beneficiary]
-                    .beneficiary
-                    .try_to_vec()?,
-            );
+        beneficiaries[staker_beneficiary]
+            .beneficiary
+            .save(staker_beneficiary_info)?;
+        beneficiaries[primary_beneficiary]
+            .beneficiary
+            .save(primary_beneficiary_info)?;
+        beneficiaries[secondary_beneficiary]
+            .beneficiary
+            .save(secondary_beneficiary_info)?;
 
         Ok(())
     }
 
-    pub fn process_withdraw_unbond(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    pub fn process_rebond(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        amount: u64,
+    ) -> ProgramResult {
         let iter = &mut accounts.iter();
-        let _funder_info = next_account_info(iter)?;
-
-        let stake_info = next_account_info(iter)?;
-
+        let funder_info = next_account_info(iter)?;
         let staker_info = next_account_info(iter)?;
-        let staker_fund_info = next_account_info(iter)?;
-        let staker_associated_info = next_account_info(iter)?;
-
+        let staker_beneficiary_info = next_account_info(iter)?;
         let endpoint_info = next_account_info(iter)?;
+        let primary_beneficiary_info = next_account_info(iter)?;
+        let secondary_beneficiary_info = next_account_info(iter)?;
         let settings_info = next_account_info(iter)?;
+        let stake_info = next_account_info(iter)?;
         let clock_info = next_account_info(iter)?;
+        let reward_pool_info = next_account_info(iter)?;
+        let system_program_info = next_account_info(iter)?;
 
         let clock = Clock::from_account_info(clock_info)?;
-        let settings = Settings::from_account_info(settings_info, program_id)?;
-        Endpoint::from_account_info(endpoint_info, program_id)?;
 
         if !staker_info.is_signer {
             return Err(StakingError::MissingStakeSignature.into());
         }
 
-        verify_associated!(staker_associated_info, settings.token, *staker_info.key)?;
-
-        let stake_seed = Stake::verify_program_address(
-            stake_info.key,
+        let mut settings = Settings::from_account_info(settings_info, program_id, Access::WRITE)?;
+        let mut endpoint = Endpoint::from_account_info(endpoint_info, program_id, Access::WRITE)?;
+        if endpoint.is_sunset() {
+            return Err(StakingError::EndpointSunset.into());
+        }
+        let mut stake = Stake::from_account_info(
+            stake_info,
             endpoint_info.key,
             staker_info.key,
             program_id,
+            Access::WRITE,
         )?;
-        let mut stake =
-            Stake::from_account_info(stake_info, endpoint_info.key, staker_info.key, program_id)?;
 
-        if stake.unbonding_amount == 0 {
-            return Err(StakingError::WithdrawNothingtowithdraw.into());
-        }
+        let mut beneficiaries = vec![];
+        let staker_beneficiary = insert_beneficiary(
+            &mut beneficiaries,
+            *staker_info.key,
+            load_beneficiary(
+                staker_beneficiary_info,
+                staker_info.key,
+                funder_info,
+                system_program_info,
+                program_id,
+            )?,
+        );
+        let primary_beneficiary = insert_beneficiary(
+            &mut beneficiaries,
+            endpoint.primary,
+            load_beneficiary(
+                primary_beneficiary_info,
+                &endpoint.primary,
+                funder_info,
+                system_program_info,
+                program_id,
+            )?,
+        );
+        let secondary_beneficiary = insert_beneficiary(
+            &mut beneficiaries,
+            endpoint.secondary,
+            load_beneficiary(
+                secondary_beneficiary_info,
+                &endpoint.secondary,
+                funder_info,
+                system_program_info,
+                program_id,
+            )?,
+        );
 
-        if clock.unix_timestamp < stake.unbonding_end {
-            return Err(StakingError::WithdrawUnbondingTimeNotOverYet.into());
+        if amount == 0 || amount > stake.unbonding_amount {
+            msg!(
+                "unbonding amount: {}, amount: {}",
+                stake.unbonding_amount,
+                amount
+            );
+            return Err(StakingError::RebondInvalidAmount.into());
+        }
+        if stake.total_stake + amount < MINIMUM_STAKE {
+            msg!(
+                "existing stake: {}, amount: {}, minimum required: {}",
+                stake.total_stake,
+                amount,
+                MINIMUM_STAKE
+            );
+            return Err(StakingError::StakerMinimumBalanceNotMet.into());
         }
 
-        Stake::verify_fund_address(
-            staker_fund_info.key,
-            endpoint_info.key,
-            staker_info.key,
+        update_rewards(
+            &mut settings,
+            clock.unix_timestamp,
+            reward_pool_info,
+            accounts,
             program_id,
         )?;
 
-        invoke_signed(
-            &spl_token::instruction::transfer(
-                &spl_token::id(),
-                staker_fund_info.key,
-                staker_associated_info.key,
-                stake_info.key,
-                &[],
-                stake.unbonding_amount,
-            )?,
-            &[
-                staker_fund_info.clone(),
-                staker_associated_info.clone(),
-                stake_info.clone(),
-            ],
-            &[&[
-                b"stake",
-                &endpoint_info.key.to_bytes(),
-                &staker_info.key.to_bytes(),
-                &[stake_seed],
-            ]],
+        // the tokens are already in the stake fund, only the accounting changes
+        let old_stake = stake.total_stake;
+        stake.rebond(amount);
+        stake.total_stake += amount;
+        endpoint.total_stake += amount;
+
+        reassign_shares(
+            &mut beneficiaries,
+            (
+                staker_beneficiary,
+                primary_beneficiary,
+                secondary_beneficiary,
+            ),
+            &mut stake,
+            &mut endpoint,
+            old_stake,
+        );
+        pay_out_beneficiaries(
+            &mut beneficiaries,
+            settings.reward_per_share,
+            &mut settings.total_stake,
         )?;
-        msg!("zee amount transferred: {}", stake.unbonding_amount);
 
-        stake.unbonding_amount = 0;
-        stake.unbonding_end = clock.unix_timestamp;
+        msg!("zee rebonded: {}", amount);
+        StakingEvent::Rebonded {
+            staker: *staker_info.key,
+            endpoint: *endpoint_info.key,
+            amount,
+        }
+        .emit();
+
+        settings.save(settings_info)?;
+        stake.save(stake_info)?;
+        endpoint.save(endpoint_info)?;
 
-        stake_info
-            .data
-            .borrow_mut()
-            .copy_from_slice(&stake.try_to_vec()?);
+        // some of these may be write identical data to the same account
+        beneficiaries[staker_beneficiary]
+            .beneficiary
+            .save(staker_beneficiary_info)?;
+        beneficiaries[primary_beneficiary]
+            .beneficiary
+            .save(primary_beneficiary_info)?;
+        beneficiaries[secondary_beneficiary]
+            .beneficiary
+            .save(secondary_beneficiary_info)?;
 
         Ok(())
     }
 
-    pub fn process_claim(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    pub fn process_redelegate(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        amount: u64,
+    ) -> ProgramResult {
         let iter = &mut accounts.iter();
-        let _funder_info = next_account_info(iter)?;
-
-        let authority_info = next_account_info(iter)?;
+        let funder_info = next_account_info(iter)?;
+        let staker_info = next_account_info(iter)?;
+        let staker_beneficiary_info = next_account_info(iter)?;
+
+        let source_endpoint_info = next_account_info(iter)?;
+        let source_primary_beneficiary_info = next_account_info(iter)?;
+        let source_secondary_beneficiary_info = next_account_info(iter)?;
+        let source_stake_info = next_account_info(iter)?;
+        let source_fund_info = next_account_info(iter)?;
+
+        let destination_endpoint_info = next_account_info(iter)?;
+        let destination_primary_beneficiary_info = next_account_info(iter)?;
+        let destination_secondary_beneficiary_info = next_account_info(iter)?;
+        let destination_stake_info = next_account_info(iter)?;
+        let destination_fund_info = next_account_info(iter)?;
+
+        let settings_info = next_account_info(iter)?;
+        let clock_info = next_account_info(iter)?;
+        let token_program_info = next_account_info(iter)?;
+        let reward_pool_info = next_account_info(iter)?;
+        let system_program_info = next_account_info(iter)?;
+
+        let clock = Clock::from_account_info(clock_info)?;
+        spl_token::check_program_account(token_program_info.key)?;
+
+        if !staker_info.is_signer {
+            return Err(StakingError::MissingStakeSignature.into());
+        }
+
+        if source_endpoint_info.key == destination_endpoint_info.key {
+            return Err(StakingError::RedelegateSameEndpoint.into());
+        }
+
+        let mut settings = Settings::from_account_info(settings_info, program_id, Access::WRITE)?;
+
+        let mut source_endpoint =
+            Endpoint::from_account_info(source_endpoint_info, program_id, Access::WRITE)?;
+        let sunset_destination_info = sunset_destination(&source_endpoint, iter)?;
+        let mut source_stake = Stake::from_account_info(
+            source_stake_info,
+            source_endpoint_info.key,
+            staker_info.key,
+            program_id,
+            Access::WRITE,
+        )?;
+        let source_stake_seed = Stake::verify_program_address(
+            source_stake_info.key,
+            source_endpoint_info.key,
+            staker_info.key,
+            program_id,
+        )?;
+        Stake::verify_fund_address(
+            source_fund_info.key,
+            source_endpoint_info.key,
+            staker_info.key,
+            program_id,
+        )?;
+
+        let mut destination_endpoint =
+            Endpoint::from_account_info(destination_endpoint_info, program_id, Access::WRITE)?;
+        if destination_endpoint.is_sunset() {
+            return Err(StakingError::EndpointSunset.into());
+        }
+        let mut destination_stake = Stake::from_account_info(
+            destination_stake_info,
+            destination_endpoint_info.key,
+            staker_info.key,
+            program_id,
+            Access::WRITE,
+        )?;
+        Stake::verify_fund_address(
+            destination_fund_info.key,
+            destination_endpoint_info.key,
+            staker_info.key,
+            program_id,
+        )?;
+
+        // holds the beneficiaries so we don't have duplicate objects
+        let mut beneficiaries = vec![];
+        let staker_beneficiary = insert_beneficiary(
+            &mut beneficiaries,
+            *staker_info.key,
+            load_beneficiary(
+                staker_beneficiary_info,
+                staker_info.key,
+                funder_info,
+                system_program_info,
+                program_id,
+            )?,
+        );
+        let source_primary_beneficiary = insert_beneficiary(
+            &mut beneficiaries,
+            source_endpoint.primary,
+            load_beneficiary(
+                source_primary_beneficiary_info,
+                &source_endpoint.primary,
+                funder_info,
+                system_program_info,
+                program_id,
+            )?,
+        );
+        let source_secondary_beneficiary = insert_beneficiary(
+            &mut beneficiaries,
+            source_endpoint.secondary,
+            load_beneficiary(
+                source_secondary_beneficiary_info,
+                &source_endpoint.secondary,
+                funder_info,
+                system_program_info,
+                program_id,
+            )?,
+        );
+        let destination_primary_beneficiary = insert_beneficiary(
+            &mut beneficiaries,
+            destination_endpoint.primary,
+            load_beneficiary(
+                destination_primary_beneficiary_info,
+                &destination_endpoint.primary,
+                funder_info,
+                system_program_info,
+                program_id,
+            )?,
+        );
+        let destination_secondary_beneficiary = insert_beneficiary(
+            &mut beneficiaries,
+            destination_endpoint.secondary,
+            load_beneficiary(
+                destination_secondary_beneficiary_info,
+                &destination_endpoint.secondary,
+                funder_info,
+                system_program_info,
+                program_id,
+            )?,
+        );
+
+        if amount > source_stake.total_stake {
+            return Err(StakingError::StakerWithdrawingTooMuch.into());
+        } else if amount < source_stake.total_stake
+            && source_stake.total_stake - amount < MINIMUM_STAKE
+        {
+            // allow them to move everything
+            return Err(StakingError::StakerMinimumBalanceNotMet.into());
+        }
+        if destination_stake.total_stake + amount < MINIMUM_STAKE {
+            msg!(
+                "existing stake: {}, amount: {}, minimum required: {}",
+                destination_stake.total_stake,
+                amount,
+                MINIMUM_STAKE
+            );
+            return Err(StakingError::StakerMinimumBalanceNotMet.into());
+        }
+
+        update_rewards(
+            &mut settings,
+            clock.unix_timestamp,
+            reward_pool_info,
+            accounts,
+            program_id,
+        )?;
+
+        // the amount delegated does not change, the beneficiaries' stake only
+        // if the stake lagged behind a split change
+        let old_source_stake = source_stake.total_stake;
+        source_stake.total_stake -= amount;
+        source_endpoint.total_stake -= amount;
+
+        let old_destination_stake = destination_stake.total_stake;
+        destination_stake.total_stake += amount;
+        destination_endpoint.total_stake += amount;
+
+        reassign_shares(
+            &mut beneficiaries,
+            (
+                staker_beneficiary,
+                source_primary_beneficiary,
+                source_secondary_beneficiary,
+            ),
+            &mut source_stake,
+            &mut source_endpoint,
+            old_source_stake,
+        );
+        reassign_shares(
+            &mut beneficiaries,
+            (
+                staker_beneficiary,
+                destination_primary_beneficiary,
+                destination_secondary_beneficiary,
+            ),
+            &mut destination_stake,
+            &mut destination_endpoint,
+            old_destination_stake,
+        );
+        pay_out_beneficiaries(
+            &mut beneficiaries,
+            settings.reward_per_share,
+            &mut settings.total_stake,
+        )?;
+
+        invoke_signed(
+            &spl_token::instruction::transfer(
+                &spl_token::id(),
+                source_fund_info.key,
+                destination_fund_info.key,
+                source_stake_info.key,
+                &[],
+                amount,
+            )?,
+            &[
+                source_fund_info.clone(),
+                destination_fund_info.clone(),
+                source_stake_info.clone(),
+            ],
+            &[&[
+                b"stake",
+                &source_endpoint_info.key.to_bytes(),
+                &staker_info.key.to_bytes(),
+                &[source_stake_seed],
+            ]],
+        )?;
+        msg!(
+            "zee redelegated from {} to {}: {}",
+            source_endpoint_info.key,
+            destination_endpoint_info.key,
+            amount
+        );
+        StakingEvent::Redelegated {
+            staker: *staker_info.key,
+            source: *source_endpoint_info.key,
+            destination: *destination_endpoint_info.key,
+            amount,
+        }
+        .emit();
+
+        settings.save(settings_info)?;
+        source_stake.save(source_stake_info)?;
+        save_endpoint(
+            &source_endpoint,
+            source_endpoint_info,
+            sunset_destination_info,
+        )?;
+        destination_stake.save(destination_stake_info)?;
+        destination_endpoint.save(destination_endpoint_info)?;
+
+        // some of these may be write identical data to the same account
+        beneficiaries[staker_beneficiary]
+            .beneficiary
+            .save(staker_beneficiary_info)?;
+        beneficiaries[source_primary_beneficiary]
+            .beneficiary
+            .save(source_primary_beneficiary_info)?;
+        beneficiaries[source_secondary_beneficiary]
+            .beneficiary
+            .save(source_secondary_beneficiary_info)?;
+        beneficiaries[destination_primary_beneficiary]
+            .beneficiary
+            .save(destination_primary_beneficiary_info)?;
+        beneficiaries[destination_secondary_beneficiary]
+            .beneficiary
+            .save(destination_secondary_beneficiary_info)?;
+
+        Ok(())
+    }
+
+    pub fn process_withdraw_unbond(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+        let iter = &mut accounts.iter();
+        let _funder_info = next_account_info(iter)?;
+
+        let stake_info = next_account_info(iter)?;
+
+        let staker_info = next_account_info(iter)?;
+        let staker_fund_info = next_account_info(iter)?;
+        let staker_associated_info = next_account_info(iter)?;
+
+        let endpoint_info = next_account_info(iter)?;
+        let settings_info = next_account_info(iter)?;
+        let clock_info = next_account_info(iter)?;
+
+        let clock = Clock::from_account_info(clock_info)?;
+        let settings = Settings::from_account_info(settings_info, program_id, Access::READ)?;
+
+        if !staker_info.is_signer {
+            return Err(StakingError::MissingStakeSignature.into());
+        }
+
+        verify_associated!(staker_associated_info, settings.token, *staker_info.key)?;
+
+        // the endpoint is only needed for the address, it may already be closed
+        let stake_seed = Stake::verify_program_address(
+            stake_info.key,
+            endpoint_info.key,
+            staker_info.key,
+            program_id,
+        )?;
+        let mut stake = Stake::from_account_info(
+            stake_info,
+            endpoint_info.key,
+            staker_info.key,
+            program_id,
+            Access::WRITE,
+        )?;
+
+        if stake.unbonding_amount == 0 {
+            return Err(StakingError::WithdrawNothingtowithdraw.into());
+        }
+
+        let amount = stake.release_unbonded(clock.unix_timestamp);
+        if amount == 0 {
+            return Err(StakingError::WithdrawUnbondingTimeNotOverYet.into());
+        }
+
+        Stake::verify_fund_address(
+            staker_fund_info.key,
+            endpoint_info.key,
+            staker_info.key,
+            program_id,
+        )?;
+
+        invoke_signed(
+            &spl_token::instruction::transfer(
+                &spl_token::id(),
+                staker_fund_info.key,
+                staker_associated_info.key,
+                stake_info.key,
+                &[],
+                amount,
+            )?,
+            &[
+                staker_fund_info.clone(),
+                staker_associated_info.clone(),
+                stake_info.clone(),
+            ],
+            &[&[
+                b"stake",
+                &endpoint_info.key.to_bytes(),
+                &staker_info.key.to_bytes(),
+                &[stake_seed],
+            ]],
+        )?;
+        msg!(
+            "zee amount transferred: {}, still unbonding: {}",
+            amount,
+            stake.unbonding_amount
+        );
+        StakingEvent::Withdrawn {
+            staker: *staker_info.key,
+            endpoint: *endpoint_info.key,
+            amount,
+        }
+        .emit();
+
+        stake.save(stake_info)?;
+
+        Ok(())
+    }
+
+    pub fn process_close_stake(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+        let iter = &mut accounts.iter();
+        let _funder_info = next_account_info(iter)?;
+        let staker_info = next_account_info(iter)?;
+        let stake_info = next_account_info(iter)?;
+        let staker_fund_info = next_account_info(iter)?;
+        let endpoint_info = next_account_info(iter)?;
+        let destination_info = next_account_info(iter)?;
+        let token_program_info = next_account_info(iter)?;
+        let staker_associated_info = next_account_info(iter)?;
+
+        spl_token::check_program_account(token_program_info.key)?;
+
+        if !staker_info.is_signer {
+            return Err(StakingError::MissingStakeSignature.into());
+        }
+        Access::WRITE.verify(destination_info)?;
+
+        // the endpoint is only needed for the address, it may already be closed
+        let stake = Stake::from_account_info(
+            stake_info,
+            endpoint_info.key,
+            staker_info.key,
+            program_id,
+            Access::WRITE,
+        )?;
+        let stake_seed = Stake::verify_program_address(
+            stake_info.key,
+            endpoint_info.key,
+            staker_info.key,
+            program_id,
+        )?;
+        Stake::verify_fund_address(
+            staker_fund_info.key,
+            endpoint_info.key,
+            staker_info.key,
+            program_id,
+        )?;
+
+        if stake.total_stake > 0 || stake.unbonding_amount > 0 {
+            msg!(
+                "total stake: {}, unbonding amount: {}",
+                stake.total_stake,
+                stake.unbonding_amount
+            );
+            return Err(StakingError::StakeNotEmpty.into());
+        }
+
+        let stake_signer: &[&[u8]] = &[
+            b"stake",
+            &endpoint_info.key.to_bytes(),
+            &staker_info.key.to_bytes(),
+            &[stake_seed],
+        ];
+
+        // tokens sent directly to the fund would otherwise make closing it fail
+        let residual = Account::unpack(&staker_fund_info.data.borrow())?;
+        if residual.amount > 0 {
+            verify_associated!(staker_associated_info, residual.mint, *staker_info.key)?;
+            invoke_signed(
+                &spl_token::instruction::transfer(
+                    &spl_token::id(),
+                    staker_fund_info.key,
+                    staker_associated_info.key,
+                    stake_info.key,
+                    &[],
+                    residual.amount,
+                )?,
+                &[
+                    staker_fund_info.clone(),
+                    staker_associated_info.clone(),
+                    stake_info.clone(),
+                ],
+                &[stake_signer],
+            )?;
+            msg!("residual zee transferred: {}", residual.amount);
+        }
+
+        invoke_signed(
+            &spl_token::instruction::close_account(
+                &spl_token::id(),
+                staker_fund_info.key,
+                destination_info.key,
+                stake_info.key,
+                &[],
+            )?,
+            &[
+                staker_fund_info.clone(),
+                destination_info.clone(),
+                stake_info.clone(),
+            ],
+            &[stake_signer],
+        )?;
+        msg!("staker fund closed");
+
+        close_program_account(stake_info, destination_info);
+        msg!("stake account closed");
+
+        Ok(())
+    }
+
+    pub fn process_close_endpoint(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        sunset: bool,
+    ) -> ProgramResult {
+        let iter = &mut accounts.iter();
+        let _funder_info = next_account_info(iter)?;
+        let endpoint_info = next_account_info(iter)?;
+        let owner_info = next_account_info(iter)?;
+        let owner_signer_info = next_account_info(iter)?;
+        let destination_info = next_account_info(iter)?;
+
+        let mut endpoint = Endpoint::from_account_info(endpoint_info, program_id, Access::WRITE)?;
+        if !endpoint.owner.has_signed(owner_info, owner_signer_info) {
+            return Err(StakingError::MissingAuthoritySignature.into());
+        }
+        Access::WRITE.verify(destination_info)?;
+
+        if endpoint.total_stake == 0 {
+            close_program_account(endpoint_info, destination_info);
+            msg!("endpoint closed");
+            return Ok(());
+        }
+
+        if !sunset {
+            msg!("endpoint still has {} stake", endpoint.total_stake);
+            return Err(StakingError::EndpointHasStake.into());
+        }
+        // the zero key marks an Endpoint that is not being sunset
+        if *destination_info.key == ZERO_KEY {
+            return Err(StakingError::InvalidSunsetDestination.into());
+        }
+
+        endpoint.sunset = *destination_info.key;
+        msg!(
+            "endpoint sunset with {} stake remaining",
+            endpoint.total_stake
+        );
+
+        endpoint.save(endpoint_info)?;
+
+        Ok(())
+    }
+
+    pub fn process_close_beneficiary(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+    ) -> ProgramResult {
+        let iter = &mut accounts.iter();
+        let _funder_info = next_account_info(iter)?;
+        let authority_info = next_account_info(iter)?;
+        let beneficiary_info = next_account_info(iter)?;
+        let settings_info = next_account_info(iter)?;
+        let clock_info = next_account_info(iter)?;
+        let reward_pool_info = next_account_info(iter)?;
+
+        let clock = Clock::from_account_info(clock_info)?;
+
+        if !authority_info.is_signer {
+            return Err(StakingError::MissingAuthoritySignature.into());
+        }
+        Access::WRITE.verify(authority_info)?;
+
+        let mut settings = Settings::from_account_info(settings_info, program_id, Access::WRITE)?;
+        let mut beneficiary = Beneficiary::from_account_info(
+            beneficiary_info,
+            authority_info.key,
+            program_id,
+            Access::WRITE,
+        )?;
+
+        // settle anything still owed so it isn't lost with the account
+        update_rewards(
+            &mut settings,
+            clock.unix_timestamp,
+            reward_pool_info,
+            accounts,
+            program_id,
+        )?;
+        beneficiary.pay_out(beneficiary.staked, settings.reward_per_share);
+
+        if beneficiary.staked > 0 || beneficiary.holding > 0 {
+            msg!(
+                "staked: {}, holding: {}",
+                beneficiary.staked,
+                beneficiary.holding
+            );
+            return Err(StakingError::BeneficiaryNotEmpty.into());
+        }
+
+        settings.save(settings_info)?;
+        close_program_account(beneficiary_info, authority_info);
+        msg!("beneficiary closed");
+
+        Ok(())
+    }
+
+    pub fn process_claim(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+        let iter = &mut accounts.iter();
+        let _funder_info = next_account_info(iter)?;
+
+        let authority_info = next_account_info(iter)?;
         let beneficiary_info = next_account_info(iter)?;
         let authority_associated_info = next_account_info(iter)?;
 
         let settings_info = next_account_info(iter)?;
-        let pool_authority_info = next_account_info(iter)?;
+        let pool_authority_info = next_account_info(iter)?;
+        let reward_pool_info = next_account_info(iter)?;
+        let clock_info = next_account_info(iter)?;
+
+        let clock = Clock::from_account_info(clock_info)?;
+        let mut settings = Settings::from_account_info(settings_info, program_id, Access::WRITE)?;
+
+        let mut beneficiary = Beneficiary::from_account_info(
+            beneficiary_info,
+            authority_info.key,
+            program_id,
+            Access::WRITE,
+        )?;
+
+        if !authority_info.is_signer {
+            return Err(StakingError::MissingAuthoritySignature.into());
+        }
+
+        verify_associated!(
+            authority_associated_info,
+            settings.token,
+            *authority_info.key
+        )?;
+
+        update_rewards(
+            &mut settings,
+            clock.unix_timestamp,
+            reward_pool_info,
+            accounts,
+            program_id,
+        )?;
+
+        // the stake amount doesn't change, so there's no need to update staker
+        beneficiary.pay_out(beneficiary.staked, settings.reward_per_share);
+        // pay out pending reward, as far as the pool can cover it.
+        // rounding can credit dust beyond the liability, which stays in holding
+        let claimed = pool_transfer!(
+            partial,
+            RewardPool,
+            reward_pool_info,
+            authority_associated_info,
+            pool_authority_info,
+            program_id,
+            beneficiary.holding.min(settings.liability)
+        )?;
+        if claimed < beneficiary.holding {
+            StakingEvent::RewardShortfall {
+                authority: *authority_info.key,
+                paid: claimed,
+                unpaid: beneficiary.holding - claimed,
+            }
+            .emit();
+        }
+        msg!("zee claimed: {}", claimed);
+        if claimed > 0 {
+            StakingEvent::Claimed {
+                authority: *authority_info.key,
+                amount: claimed,
+            }
+            .emit();
+        }
+        settings.liability = settings
+            .liability
+            .checked_sub(claimed)
+            .ok_or(ProgramError::InvalidArgument)?;
+        beneficiary.holding -= claimed;
+
+        settings.save(settings_info)?;
+        beneficiary.save(beneficiary_info)?;
+
+        Ok(())
+    }
+
+    pub fn process_transfer_endpoint(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        new_owner: Authority,
+    ) -> ProgramResult {
+        let iter = &mut accounts.iter();
+        let _funder_info = next_account_info(iter)?;
+        let endpoint_info = next_account_info(iter)?;
+        let owner_info = next_account_info(iter)?;
+        let owner_signer_info = next_account_info(iter)?;
+        let recipient_info = next_account_info(iter)?;
+
+        let mut endpoint = Endpoint::from_account_info(endpoint_info, program_id, Access::WRITE)?;
+        if !endpoint.owner.has_signed(owner_info, owner_signer_info) {
+            return Err(StakingError::MissingAuthoritySignature.into());
+        }
+
+        new_owner.verify(recipient_info)?;
+
+        msg!("transfer endpoint {:?} to {:?}", endpoint, new_owner);
+        StakingEvent::EndpointTransferred {
+            endpoint: *endpoint_info.key,
+            old_owner: endpoint.owner,
+            new_owner,
+        }
+        .emit();
+
+        endpoint.owner = new_owner;
+
+        endpoint.save(endpoint_info)?;
+
+        Ok(())
+    }
+
+    pub fn process_change_beneficiaries(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+    ) -> ProgramResult {
+        let iter = &mut accounts.iter();
+        let funder_info = next_account_info(iter)?;
+        let endpoint_info = next_account_info(iter)?;
+        let owner_info = next_account_info(iter)?;
+        let owner_signer_info = next_account_info(iter)?;
+
+        let old_primary_beneficiary_info = next_account_info(iter)?;
+        let old_secondary_beneficiary_info = next_account_info(iter)?;
+
+        let new_primary_info = next_account_info(iter)?;
+        let new_primary_beneficiary_info = next_account_info(iter)?;
+        let new_secondary_info = next_account_info(iter)?;
+        let new_secondary_beneficiary_info = next_account_info(iter)?;
+
+        let settings_info = next_account_info(iter)?;
+
+        let rent_info = next_account_info(iter)?;
+        let clock_info = next_account_info(iter)?;
+        let system_program_info = next_account_info(iter)?;
+        let reward_pool_info = next_account_info(iter)?;
+
+        let rent = Rent::from_account_info(rent_info)?;
+        let clock = Clock::from_account_info(clock_info)?;
+
+        let mut settings = Settings::from_account_info(settings_info, program_id, Access::WRITE)?;
+
+        let mut endpoint = Endpoint::from_account_info(endpoint_info, program_id, Access::WRITE)?;
+        if !endpoint.owner.has_signed(owner_info, owner_signer_info) {
+            return Err(StakingError::MissingAuthoritySignature.into());
+        }
+
+        let mut beneficiaries = vec![];
+
+        let old_primary_beneficiary = insert_beneficiary(
+            &mut beneficiaries,
+            endpoint.primary,
+            load_beneficiary(
+                old_primary_beneficiary_info,
+                &endpoint.primary,
+                funder_info,
+                system_program_info,
+                program_id,
+            )?,
+        );
+
+        let old_secondary_beneficiary = insert_beneficiary(
+            &mut beneficiaries,
+            endpoint.secondary,
+            load_beneficiary(
+                old_secondary_beneficiary_info,
+                &endpoint.secondary,
+                funder_info,
+                system_program_info,
+                program_id,
+            )?,
+        );
+
+        if new_primary_beneficiary_info.data_is_empty() {
+            create_beneficiary!(
+                new_primary_beneficiary_info,
+                new_primary_info.key,
+                funder_info,
+                &rent,
+                program_id
+            );
+            msg!("Primary Beneficiary account created");
+        } else {
+            Beneficiary::verify_program_address(
+                new_primary_beneficiary_info.key,
+                new_primary_info.key,
+                program_id,
+            )?;
+        }
+
+        if new_secondary_beneficiary_info.data_is_empty() {
+            create_beneficiary!(
+                new_secondary_beneficiary_info,
+                new_secondary_info.key,
+                funder_info,
+                &rent,
+                program_id
+            );
+            msg!("Secondary Beneficiary account created");
+        } else {
+            Beneficiary::verify_program_address(
+                new_secondary_beneficiary_info.key,
+                new_secondary_info.key,
+                program_id,
+            )?;
+        }
+
+        let new_primary_beneficiary = insert_beneficiary(
+            &mut beneficiaries,
+            *new_primary_info.key,
+            Beneficiary::from_account_info(
+                new_primary_beneficiary_info,
+                new_primary_info.key,
+                program_id,
+                Access::WRITE,
+            )?,
+        );
+        let new_secondary_beneficiary = insert_beneficiary(
+            &mut beneficiaries,
+            *new_secondary_info.key,
+            Beneficiary::from_account_info(
+                new_secondary_beneficiary_info,
+                new_secondary_info.key,
+                program_id,
+                Access::WRITE,
+            )?,
+        );
+
+        update_rewards(
+            &mut settings,
+            clock.unix_timestamp,
+            reward_pool_info,
+            accounts,
+            program_id,
+        )?;
+
+        let primary_share = endpoint.primary_stake;
+        let secondary_share = endpoint.secondary_stake;
+
+        msg!(
+            "transfering {} stake from old primary to new primary",
+            primary_share
+        );
+        msg!(
+            "transfering {} stake from old secondary to new secondary",
+            secondary_share
+        );
+
+        beneficiaries[old_primary_beneficiary].sub += primary_share;
+        beneficiaries[new_primary_beneficiary].add += primary_share;
+        beneficiaries[old_secondary_beneficiary].sub += secondary_share;
+        beneficiaries[new_secondary_beneficiary].add += secondary_share;
+
+        pay_out_beneficiaries(
+            &mut beneficiaries,
+            settings.reward_per_share,
+            &mut settings.total_stake,
+        )?;
+
+        msg!(
+            "changing endpoint primary from {} to {}",
+            endpoint.primary,
+            new_primary_info.key
+        );
+        msg!(
+            "changing endpoint secondary from {} to {}",
+            endpoint.secondary,
+            new_secondary_info.key
+        );
+
+        StakingEvent::BeneficiariesChanged {
+            endpoint: *endpoint_info.key,
+            old_primary: endpoint.primary,
+            old_secondary: endpoint.secondary,
+            new_primary: *new_primary_info.key,
+            new_secondary: *new_secondary_info.key,
+        }
+        .emit();
+
+        endpoint.primary = *new_primary_info.key;
+        endpoint.secondary = *new_secondary_info.key;
+
+        settings.save(settings_info)?;
+        beneficiaries[old_primary_beneficiary]
+            .beneficiary
+            .save(old_primary_beneficiary_info)?;
+        beneficiaries[old_secondary_beneficiary]
+            .beneficiary
+            .save(old_secondary_beneficiary_info)?;
+
+        beneficiaries[new_primary_beneficiary]
+            .beneficiary
+            .save(new_primary_beneficiary_info)?;
+        beneficiaries[new_secondary_beneficiary]
+            .beneficiary
+            .save(new_secondary_beneficiary_info)?;
+
+        endpoint.save(endpoint_info)?;
+
+        Ok(())
+    }
+
+    pub fn process_change_split(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        split: Split,
+    ) -> ProgramResult {
+        let iter = &mut accounts.iter();
+        let funder_info = next_account_info(iter)?;
+        let endpoint_info = next_account_info(iter)?;
+        let owner_info = next_account_info(iter)?;
+        let owner_signer_info = next_account_info(iter)?;
+        let settings_info = next_account_info(iter)?;
+        let primary_beneficiary_info = next_account_info(iter)?;
+        let secondary_beneficiary_info = next_account_info(iter)?;
+        let clock_info = next_account_info(iter)?;
         let reward_pool_info = next_account_info(iter)?;
+        let system_program_info = next_account_info(iter)?;
+
+        let clock = Clock::from_account_info(clock_info)?;
+        let mut settings = Settings::from_account_info(settings_info, program_id, Access::WRITE)?;
+
+        let mut endpoint = Endpoint::from_account_info(endpoint_info, program_id, Access::WRITE)?;
+        if !endpoint.owner.has_signed(owner_info, owner_signer_info) {
+            return Err(StakingError::MissingAuthoritySignature.into());
+        }
+
+        settings.split_bounds.verify(&split)?;
+
+        let mut beneficiaries = vec![];
+        let primary_beneficiary = insert_beneficiary(
+            &mut beneficiaries,
+            endpoint.primary,
+            load_beneficiary(
+                primary_beneficiary_info,
+                &endpoint.primary,
+                funder_info,
+                system_program_info,
+                program_id,
+            )?,
+        );
+        let secondary_beneficiary = insert_beneficiary(
+            &mut beneficiaries,
+            endpoint.secondary,
+            load_beneficiary(
+                secondary_beneficiary_info,
+                &endpoint.secondary,
+                funder_info,
+                system_program_info,
+                program_id,
+            )?,
+        );
+
+        // settle the yield accrued under the old split first
+        update_rewards(
+            &mut settings,
+            clock.unix_timestamp,
+            reward_pool_info,
+            accounts,
+            program_id,
+        )?;
+
+        msg!(
+            "changing endpoint split from {:?} to {:?}",
+            endpoint.split,
+            split
+        );
+        let old_split = endpoint.split;
+        change_split(
+            &mut beneficiaries,
+            (primary_beneficiary, secondary_beneficiary),
+            &mut endpoint,
+            split,
+        );
+        pay_out_beneficiaries(
+            &mut beneficiaries,
+            settings.reward_per_share,
+            &mut settings.total_stake,
+        )?;
+
+        StakingEvent::SplitChanged {
+            endpoint: *endpoint_info.key,
+            old_split,
+            new_split: split,
+            primary_stake: endpoint.primary_stake,
+            secondary_stake: endpoint.secondary_stake,
+        }
+        .emit();
+
+        settings.save(settings_info)?;
+        endpoint.save(endpoint_info)?;
+
+        // the primary and secondary may be the same account
+        beneficiaries[primary_beneficiary]
+            .beneficiary
+            .save(primary_beneficiary_info)?;
+        beneficiaries[secondary_beneficiary]
+            .beneficiary
+            .save(secondary_beneficiary_info)?;
+
+        Ok(())
+    }
+
+    pub fn process_update_settings(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        unbonding_duration: Option<u64>,
+        emission: Option<u64>,
+        next_emission_change: Option<UnixTimestamp>,
+        split_bounds: Option<SplitBounds>,
+        decay: Option<EmissionDecay>,
+    ) -> ProgramResult {
+        let iter = &mut accounts.iter();
+        let _funder_info = next_account_info(iter)?;
+        let settings_info = next_account_info(iter)?;
+        let admin_info = next_account_info(iter)?;
+        let admin_signer_info = next_account_info(iter)?;
         let clock_info = next_account_info(iter)?;
+        let reward_pool_info = next_account_info(iter)?;
 
         let clock = Clock::from_account_info(clock_info)?;
-        let mut settings = Settings::from_account_info(settings_info, program_id)?;
+        let mut settings = Settings::from_account_info(settings_info, program_id, Access::WRITE)?;
+
+        if !settings.admin.has_signed(admin_info, admin_signer_info) {
+            return Err(StakingError::MissingAuthoritySignature.into());
+        }
+
+        // settle the yield accrued under the old settings first
+        update_rewards(
+            &mut settings,
+            clock.unix_timestamp,
+            reward_pool_info,
+            accounts,
+            program_id,
+        )?;
 
-        let mut beneficiary =
-            Beneficiary::from_account_info(beneficiary_info, authority_info.key, program_id)?;
+        if let Some(unbonding_duration) = unbonding_duration {
+            msg!(
+                "changing unbonding duration from {} to {}",
+                settings.unbonding_duration,
+                unbonding_duration
+            );
+            settings.unbonding_duration = unbonding_duration;
+        }
 
-        if !authority_info.is_signer {
+        if let Some(emission) = emission {
+            msg!(
+                "changing emission from {} to {}",
+                settings.emission,
+                emission
+            );
+            settings.emission = emission;
+        }
+
+        if let Some(next_emission_change) = next_emission_change {
+            if next_emission_change <= settings.last_reward {
+                return Err(StakingError::InvalidNextEmissionChange.into());
+            }
+            msg!(
+                "changing next emission change from {} to {}",
+                settings.next_emission_change,
+                next_emission_change
+            );
+            settings.next_emission_change = next_emission_change;
+        }
+
+        if let Some(decay) = decay {
+            if !decay.is_valid() {
+                return Err(StakingError::InvalidEmissionDecay.into());
+            }
+            msg!(
+                "changing emission decay from {:?} to {:?}",
+                settings.decay,
+                decay
+            );
+            settings.decay = decay;
+        }
+
+        if let Some(split_bounds) = split_bounds {
+            if !split_bounds.is_valid() {
+                return Err(StakingError::InvalidSplitBounds.into());
+            }
+            msg!(
+                "changing split bounds from {:?} to {:?}",
+                settings.split_bounds,
+                split_bounds
+            );
+            settings.split_bounds = split_bounds;
+        }
+
+        settings.save(settings_info)?;
+
+        Ok(())
+    }
+
+    pub fn process_set_emission_schedule(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        breakpoints: Vec<Breakpoint>,
+    ) -> ProgramResult {
+        let iter = &mut accounts.iter();
+        let funder_info = next_account_info(iter)?;
+        let settings_info = next_account_info(iter)?;
+        let admin_info = next_account_info(iter)?;
+        let admin_signer_info = next_account_info(iter)?;
+        let schedule_info = next_account_info(iter)?;
+        let rent_info = next_account_info(iter)?;
+        let clock_info = next_account_info(iter)?;
+        let _system_program_info = next_account_info(iter)?;
+        let reward_pool_info = next_account_info(iter)?;
+
+        let rent = Rent::from_account_info(rent_info)?;
+        let clock = Clock::from_account_info(clock_info)?;
+        let mut settings = Settings::from_account_info(settings_info, program_id, Access::WRITE)?;
+
+        if !settings.admin.has_signed(admin_info, admin_signer_info) {
             return Err(StakingError::MissingAuthoritySignature.into());
         }
 
-        verify_associated!(
-            authority_associated_info,
-            settings.token,
-            *authority_info.key
-        )?;
-
-        settings.update_rewards(clock.unix_timestamp);
+        let seed = EmissionSchedule::verify_program_address(schedule_info.key, program_id)?;
+        Access::WRITE.verify(schedule_info)?;
 
-        // the stake amount doesn't change, so there's no need to update staker
-        beneficiary.pay_out(beneficiary.staked, settings.reward_per_share);
-        // pay out pending reward
-        pool_transfer!(
-            RewardPool,
+        // settle the yield accrued under the old schedule first
+        update_rewards(
+            &mut settings,
+            clock.unix_timestamp,
             reward_pool_info,
-            authority_associated_info,
-            pool_authority_info,
+            accounts,
             program_id,
-            beneficiary.holding
         )?;
-        msg!("zee claimed: {}", beneficiary.holding);
-        beneficiary.holding = 0;
-
-        settings_info
-            .data
-            .borrow_mut()
-            .copy_from_slice(&settings.try_to_vec()?);
-        beneficiary_info
-            .data
-            .borrow_mut()
-            .copy_from_slice(&beneficiary.try_to_vec()?);
+
+        let schedule = EmissionSchedule { breakpoints };
+        if !schedule.is_valid(settings.last_reward) {
+            return Err(StakingError::InvalidEmissionSchedule.into());
+        }
+
+        if schedule.breakpoints.is_empty() {
+            msg!("removing emission schedule");
+            settings.emission_schedule = ZERO_KEY;
+            if !schedule_info.data_is_empty() {
+                close_program_account(schedule_info, funder_info);
+            }
+            settings.save(settings_info)?;
+            return Ok(());
+        }
+
+        if schedule_info.data_is_empty() {
+            // leave room for the maximum number of breakpoints
+            let lamports = rent.minimum_balance(EmissionSchedule::LEN);
+            let space = EmissionSchedule::LEN as u64;
+
+            invoke_signed(
+                &create_account(
+                    funder_info.key,
+                    schedule_info.key,
+                    lamports,
+                    space,
+                    program_id,
+                ),
+                &[funder_info.clone(), schedule_info.clone()],
+                &[&[b"emission schedule", &[seed]]],
+            )?;
+            msg!("Emission Schedule account created");
+        }
+
+        msg!("setting emission schedule: {:?}", schedule);
+        settings.emission_schedule = *schedule_info.key;
+
+        schedule.save(schedule_info)?;
+        settings.save(settings_info)?;
 
         Ok(())
     }
 
-    pub fn process_transfer_endpoint(
+    pub fn process_fund_reward_pool(
         program_id: &Pubkey,
         accounts: &[AccountInfo],
-        new_owner: Authority,
+        amount: u64,
     ) -> ProgramResult {
         let iter = &mut accounts.iter();
         let _funder_info = next_account_info(iter)?;
-        let endpoint_info = next_account_info(iter)?;
-        let owner_info = next_account_info(iter)?;
-        let owner_signer_info = next_account_info(iter)?;
-        let recipient_info = next_account_info(iter)?;
+        let depositor_info = next_account_info(iter)?;
+        let depositor_associated_info = next_account_info(iter)?;
+        let reward_pool_info = next_account_info(iter)?;
+        let settings_info = next_account_info(iter)?;
+        let token_program_info = next_account_info(iter)?;
+
+        spl_token::check_program_account(token_program_info.key)?;
 
-        let mut endpoint = Endpoint::from_account_info(&endpoint_info, program_id)?;
-        if !endpoint.owner.has_signed(&owner_info, &owner_signer_info) {
+        if !depositor_info.is_signer {
             return Err(StakingError::MissingAuthoritySignature.into());
         }
+        if amount == 0 {
+            return Err(StakingError::FundInvalidAmount.into());
+        }
 
-        new_owner.verify(&recipient_info)?;
+        let mut settings = Settings::from_account_info(settings_info, program_id, Access::WRITE)?;
+        RewardPool::verify_program_address(reward_pool_info.key, program_id)?;
+        verify_associated!(depositor_associated_info, settings.token)?;
 
-        msg!("transfer endpoint {:?} to {:?}", endpoint, new_owner);
+        invoke(
+            &spl_token::instruction::transfer(
+                &spl_token::id(),
+                depositor_associated_info.key,
+                reward_pool_info.key,
+                depositor_info.key,
+                &[],
+                amount,
+            )?,
+            &[
+                depositor_associated_info.clone(),
+                reward_pool_info.clone(),
+                depositor_info.clone(),
+            ],
+        )?;
 
-        endpoint.owner = new_owner;
+        settings.total_funded += amount;
+
+        StakingEvent::RewardPoolFunded {
+            funder: *depositor_info.key,
+            amount,
+            total_funded: settings.total_funded,
+        }
+        .emit();
 
-        endpoint_info
-            .data
-            .borrow_mut()
-            .copy_from_slice(&endpoint.try_to_vec()?);
+        settings.save(settings_info)?;
 
         Ok(())
     }
 
-    pub fn process_change_beneficiaries(
+    pub fn process_query_pending(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+        let iter = &mut accounts.iter();
+        let beneficiary_info = next_account_info(iter)?;
+        let settings_info = next_account_info(iter)?;
+        let reward_pool_info = next_account_info(iter)?;
+        let clock_info = next_account_info(iter)?;
+
+        let clock = Clock::from_account_info(clock_info)?;
+        let mut settings = Settings::from_account_info(settings_info, program_id, Access::READ)?;
+
+        let mut beneficiary = Beneficiary::load(beneficiary_info, program_id, Access::READ)?;
+        Beneficiary::verify_program_address(
+            beneficiary_info.key,
+            &beneficiary.authority,
+            program_id,
+        )?;
+
+        // same math as a claim, but nothing is saved, logged or emitted
+        let (pool_balance, schedule) =
+            emission_sources(&settings, reward_pool_info, accounts, program_id)?;
+        settings.accrue(clock.unix_timestamp, schedule.as_ref(), pool_balance);
+        beneficiary.pay_out(beneficiary.staked, settings.reward_per_share);
+
+        set_return_data(&beneficiary.holding.to_le_bytes());
+
+        Ok(())
+    }
+
+    pub fn process_resync(
         program_id: &Pubkey,
         accounts: &[AccountInfo],
+        staked: u64,
+        tracked: Vec<u64>,
     ) -> ProgramResult {
         let iter = &mut accounts.iter();
-        let funder_info = next_account_info(iter)?;
-        let endpoint_info = next_account_info(iter)?;
-        let owner_info = next_account_info(iter)?;
-        let owner_signer_info = next_account_info(iter)?;
+        let _funder_info = next_account_info(iter)?;
+        let settings_info = next_account_info(iter)?;
+        let admin_info = next_account_info(iter)?;
+        let admin_signer_info = next_account_info(iter)?;
+        let beneficiary_info = next_account_info(iter)?;
+        let clock_info = next_account_info(iter)?;
+        let reward_pool_info = next_account_info(iter)?;
 
-        let old_primary_beneficiary_info = next_account_info(iter)?;
-        let old_secondary_beneficiary_info = next_account_info(iter)?;
+        let clock = Clock::from_account_info(clock_info)?;
+        let mut settings = Settings::from_account_info(settings_info, program_id, Access::WRITE)?;
 
-        let new_primary_info = next_account_info(iter)?;
-        let new_primary_beneficiary_info = next_account_info(iter)?;
-        let new_secondary_info = next_account_info(iter)?;
-        let new_secondary_beneficiary_info = next_account_info(iter)?;
+        if !settings.admin.has_signed(admin_info, admin_signer_info) {
+            return Err(StakingError::MissingAuthoritySignature.into());
+        }
 
-        let settings_info = next_account_info(iter)?;
+        let mut beneficiary = Beneficiary::load(beneficiary_info, program_id, Access::WRITE)?;
+        Beneficiary::verify_program_address(
+            beneficiary_info.key,
+            &beneficiary.authority,
+            program_id,
+        )?;
 
-        let rent_info = next_account_info(iter)?;
-        let clock_info = next_account_info(iter)?;
+        // the stake the Beneficiary holds once the tracked stake is corrected
+        let mut tracked = tracked.into_iter();
+        let mut corrected = beneficiary.staked;
+        let mut tolerance: u64 = 0;
+        let mut endpoints: Vec<&Pubkey> = vec![];
+        for endpoint_info in iter {
+            if endpoint_info.key == &settings.emission_schedule {
+                continue;
+            }
+            if endpoints.contains(&endpoint_info.key) {
+                return Err(StakingError::InvalidEndpointAccount.into());
+            }
+            endpoints.push(endpoint_info.key);
 
-        let rent = Rent::from_account_info(rent_info)?;
-        let clock = Clock::from_account_info(clock_info)?;
+            let mut endpoint =
+                Endpoint::from_account_info(endpoint_info, program_id, Access::WRITE)?;
+            if endpoint.primary != beneficiary.authority
+                && endpoint.secondary != beneficiary.authority
+            {
+                msg!(
+                    "endpoint {} does not reference {}",
+                    endpoint_info.key,
+                    beneficiary.authority
+                );
+                return Err(StakingError::InvalidEndpointAccount.into());
+            }
 
-        let mut settings = Settings::from_account_info(settings_info, program_id)?;
+            // stakes are at least the minimum stake, so there are at most
+            // `total_stake / MINIMUM_STAKE` of them
+            let bound = 2 * (endpoint.total_stake / MINIMUM_STAKE);
+            let (_, primary, secondary) = endpoint.split.apply(endpoint.total_stake);
+            if endpoint.primary == beneficiary.authority {
+                let primary_stake = resync_share(tracked.next(), primary, bound)?;
+                corrected = (corrected + primary_stake)
+                    .checked_sub(endpoint.primary_stake)
+                    .ok_or(StakingError::ResyncOutOfBounds)?;
+                endpoint.primary_stake = primary_stake;
+                tolerance += bound;
+            }
+            if endpoint.secondary == beneficiary.authority {
+                let secondary_stake = resync_share(tracked.next(), secondary, bound)?;
+                corrected = (corrected + secondary_stake)
+                    .checked_sub(endpoint.secondary_stake)
+                    .ok_or(StakingError::ResyncOutOfBounds)?;
+                endpoint.secondary_stake = secondary_stake;
+                tolerance += bound;
+            }
+            endpoint.save(endpoint_info)?;
+        }
+        if tracked.next().is_some() {
+            return Err(ProgramError::InvalidInstructionData);
+        }
 
-        let mut endpoint = Endpoint::from_account_info(&endpoint_info, program_id)?;
-        if !endpoint.owner.has_signed(&owner_info, &owner_signer_info) {
-            return Err(StakingError::MissingAuthoritySignature.into());
+        // what remains is the rounding of beneficiaries that were accounted
+        // under the shares of whole Endpoints before they were migrated
+        let old_staked = beneficiary.staked;
+        let difference = staked.max(corrected) - staked.min(corrected);
+        if difference > tolerance {
+            msg!(
+                "resync from {} to {} exceeds the rounding tolerance of {}",
+                corrected,
+                staked,
+                tolerance
+            );
+            return Err(StakingError::ResyncOutOfBounds.into());
         }
 
-        let mut beneficiaries = vec![];
+        update_rewards(
+            &mut settings,
+            clock.unix_timestamp,
+            reward_pool_info,
+            accounts,
+            program_id,
+        )?;
 
-        let old_primary_beneficiary = insert_beneficiary(
-            &mut beneficiaries,
-            endpoint.primary,
-            Beneficiary::from_account_info(
-                old_primary_beneficiary_info,
-                &endpoint.primary,
-                program_id,
-            )?,
-        );
+        // the emissions follow the stake the beneficiaries hold
+        if beneficiary.earns_rewards() {
+            settings.total_stake = (settings.total_stake + staked)
+                .checked_sub(old_staked)
+                .ok_or(ProgramError::InvalidArgument)?;
+        }
+        beneficiary.pay_out(staked, settings.reward_per_share);
 
-        let old_secondary_beneficiary = insert_beneficiary(
-            &mut beneficiaries,
-            endpoint.secondary,
-            Beneficiary::from_account_info(
-                old_secondary_beneficiary_info,
-                &endpoint.secondary,
-                program_id,
-            )?,
+        msg!(
+            "resynced beneficiary {} from {} to {}",
+            beneficiary.authority,
+            old_staked,
+            staked
         );
+        StakingEvent::BeneficiaryResynced {
+            authority: beneficiary.authority,
+            old_staked,
+            new_staked: staked,
+        }
+        .emit();
 
-        if new_primary_beneficiary_info.data_is_empty() {
-            create_beneficiary!(
-                new_primary_beneficiary_info,
-                new_primary_info,
-                funder_info,
-                &rent,
-                program_id
-            );
-            msg!("Primary Beneficiary account created");
-        } else {
-            Beneficiary::verify_program_address(
-                new_primary_beneficiary_info.key,
-                new_primary_info.key,
-                program_id,
-            )?;
+        settings.save(settings_info)?;
+        beneficiary.save(beneficiary_info)?;
+
+        Ok(())
+    }
+
+    pub fn process_migrate(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        liability: u64,
+    ) -> ProgramResult {
+        let iter = &mut accounts.iter();
+        let funder_info = next_account_info(iter)?;
+        let account_info = next_account_info(iter)?;
+        let rent_info = next_account_info(iter)?;
+        let _system_program_info = next_account_info(iter)?;
+
+        let rent = Rent::from_account_info(rent_info)?;
+
+        if account_info.owner != program_id
+            || AccountType::from_discriminator(&account_info.data.borrow()).is_some()
+        {
+            return Err(StakingError::NothingToMigrate.into());
         }
 
-        if new_secondary_beneficiary_info.data_is_empty() {
-            create_beneficiary!(
-                new_secondary_beneficiary_info,
-                new_secondary_info,
-                funder_info,
-                &rent,
-                program_id
-            );
-            msg!("Secondary Beneficiary account created");
-        } else {
-            Beneficiary::verify_program_address(
-                new_secondary_beneficiary_info.key,
-                new_secondary_info.key,
-                program_id,
+        // legacy accounts have no discriminator but every type has a distinct length
+        let (data, space) = match account_info.data_len() {
+            legacy::Settings::LEN => {
+                Settings::verify_program_address(account_info.key, program_id)?;
+                let program_data_info = next_account_info(iter)?;
+                let upgrade_authority_info = next_account_info(iter)?;
+
+                // the legacy layout has no admin, the upgrade authority becomes the admin
+                verify_upgrade_authority(program_id, program_data_info, upgrade_authority_info)?;
+
+                let old = legacy::Settings::try_from_slice(&account_info.data.borrow())?;
+                msg!("migrating settings: {:?}", old);
+                let beneficiary_infos = iter.as_slice();
+                if has_duplicates(beneficiary_infos) {
+                    msg!("beneficiary accounts passed more than once");
+                    return Err(StakingError::InvalidBeneficiaryAccount.into());
+                }
+                let beneficiaries = beneficiary_infos
+                    .iter()
+                    .map(|info| migrating_beneficiary(info, program_id))
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                // every Beneficiary is needed for the liability. their stake can
+                // only differ from the total by the rounding of the primary's and
+                // secondary's shares, 2 per minimum stake each, see `Resync`
+                let staked: u64 = beneficiaries.iter().map(|b| b.staked).sum();
+                let tolerance = 4 * (old.total_stake / MINIMUM_STAKE);
+                if staked.max(old.total_stake) - staked.min(old.total_stake) > tolerance {
+                    msg!(
+                        "beneficiaries hold {}, settings have {}",
+                        staked,
+                        old.total_stake
+                    );
+                    return Err(StakingError::InvalidBeneficiaryAccount.into());
+                }
+
+                let settings = old.migrate(
+                    Authority::Basic(*upgrade_authority_info.key),
+                    &beneficiaries,
+                );
+                if settings.liability != liability {
+                    msg!(
+                        "beneficiaries are owed {}, not {}",
+                        settings.liability,
+                        liability
+                    );
+                    return Err(ProgramError::InvalidInstructionData);
+                }
+                msg!("seeding liability: {}", liability);
+                let data = settings.to_account_data()?;
+                let space = data.len();
+                (data, space)
+            }
+            _ if liability > 0 => return Err(ProgramError::InvalidInstructionData),
+            legacy::Endpoint::LEN => {
+                let old = legacy::Endpoint::try_from_slice(&account_info.data.borrow())?;
+                msg!("migrating endpoint: {:?}", old);
+                let stake_infos = iter.as_slice();
+                if has_duplicates(stake_infos) {
+                    msg!("stake accounts passed more than once");
+                    return Err(StakingError::InvalidStakeAccount.into());
+                }
+                let stakes = stake_infos
+                    .iter()
+                    .map(|info| migrating_stake(info, account_info.key, program_id))
+                    .collect::<Result<Vec<_>, _>>()?;
+                let staked: u64 = stakes.iter().map(|stake| stake.total_stake).sum();
+                if staked != old.total_stake {
+                    msg!(
+                        "stakes add up to {}, endpoint has {}",
+                        staked,
+                        old.total_stake
+                    );
+                    return Err(StakingError::InvalidStakeAccount.into());
+                }
+                let data = old.migrate(&stakes).to_account_data()?;
+                let space = data.len();
+                (data, space)
+            }
+            legacy::Beneficiary::LEN => {
+                let old = legacy::Beneficiary::try_from_slice(&account_info.data.borrow())?;
+                msg!("migrating beneficiary: {:?}", old);
+                let data = old.migrate().to_account_data()?;
+                let space = data.len();
+                (data, space)
+            }
+            legacy::Stake::LEN => {
+                let old = legacy::Stake::try_from_slice(&account_info.data.borrow())?;
+                msg!("migrating stake: {:?}", old);
+                (old.migrate().to_account_data()?, Stake::LEN)
+            }
+            _ => return Err(StakingError::NothingToMigrate.into()),
+        };
+
+        let lamports = rent
+            .minimum_balance(space)
+            .saturating_sub(account_info.lamports());
+        if lamports > 0 {
+            invoke(
+                &transfer(funder_info.key, account_info.key, lamports),
+                &[funder_info.clone(), account_info.clone()],
             )?;
         }
 
-        let new_primary_beneficiary = insert_beneficiary(
-            &mut beneficiaries,
-            *new_primary_info.key,
-            Beneficiary::from_account_info(
-                new_primary_beneficiary_info,
-                new_primary_info.key,
-                program_id,
-            )?,
-        );
-        let new_secondary_beneficiary = insert_beneficiary(
-            &mut beneficiaries,
-            *new_secondary_info.key,
-            Beneficiary::from_account_info(
-                new_secondary_beneficiary_info,
-                new_secondary_info.key,
-                program_id,
-            )?,
-        );
+        account_info.realloc(space, true)?;
+        account_info.data.borrow_mut()[..data.len()].copy_from_slice(&data);
 
-        settings.update_rewards(clock.unix_timestamp);
+        Ok(())
+    }
+}
 
-        let (_, primary_share, secondary_share) = split_stake(endpoint.total_stake);
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn working(staked: u64) -> WorkingBeneficiary {
+        WorkingBeneficiary {
+            beneficiary: Beneficiary {
+                authority: Pubkey::new_unique(),
+                staked,
+                reward_debt: 0,
+                holding: 0,
+            },
+            add: 0,
+            sub: 0,
+        }
+    }
 
-        msg!(
-            "transfering {} stake from old primary to new primary",
-            primary_share
-        );
-        msg!(
-            "transfering {} stake from old secondary to new secondary",
-            secondary_share
-        );
+    /// the stake the rewards are emitted for
+    fn total_stake(beneficiaries: &[WorkingBeneficiary]) -> u64 {
+        beneficiaries
+            .iter()
+            .filter(|w| w.beneficiary.earns_rewards())
+            .map(|w| w.beneficiary.staked)
+            .sum()
+    }
 
-        beneficiaries[old_primary_beneficiary].sub += primary_share;
-        beneficiaries[new_primary_beneficiary].add += primary_share;
-        beneficiaries[old_secondary_beneficiary].sub += secondary_share;
-        beneficiaries[new_secondary_beneficiary].add += secondary_share;
+    /// apply the changes like the end of an instruction would
+    fn settle(beneficiaries: &mut [WorkingBeneficiary]) {
+        let mut total = total_stake(beneficiaries);
+        pay_out_beneficiaries(beneficiaries, 0, &mut total).unwrap();
+        assert_eq!(total, total_stake(beneficiaries));
+        for working in beneficiaries.iter_mut() {
+            working.add = 0;
+            working.sub = 0;
+        }
+    }
 
-        for working in &mut beneficiaries {
-            working.beneficiary.pay_out(
-                (working.beneficiary.staked + working.add)
-                    .checked_sub(working.sub)
-                    .unwrap(),
-                settings.reward_per_share,
-            );
+    /// change the stake like `Stake` does
+    fn restake(
+        beneficiaries: &mut [WorkingBeneficiary],
+        stake: &mut Stake,
+        endpoint: &mut Endpoint,
+        total_stake: u64,
+    ) {
+        let old_stake = stake.total_stake;
+        endpoint.total_stake = endpoint.total_stake + total_stake - old_stake;
+        stake.total_stake = total_stake;
+        reassign_shares(beneficiaries, (0, 1, 2), stake, endpoint, old_stake);
+        settle(beneficiaries);
+    }
+
+    #[test]
+    pub fn test_reassign_shares_after_split_change() {
+        let new_split = Split {
+            staker: 8_000,
+            primary: 1_500,
+            secondary: 500,
+        };
+        let mut endpoint = Endpoint {
+            creation_date: 0,
+            total_stake: 0,
+            owner: Authority::Basic(Pubkey::new_unique()),
+            primary: Pubkey::new_unique(),
+            secondary: Pubkey::new_unique(),
+            split: Split::DEFAULT,
+            primary_stake: 0,
+            secondary_stake: 0,
+            sunset: ZERO_KEY,
+        };
+        let mut stakes: Vec<Stake> = [1_001u64, 2_503, 7_777]
+            .iter()
+            .map(|_| Stake {
+                creation_date: 0,
+                total_stake: 0,
+                staker: Pubkey::new_unique(),
+                split: Split::DEFAULT,
+                unbonding_amount: 0,
+                unbonding: vec![],
+            })
+            .collect();
+
+        // staker, primary, secondary as accounted under the old split
+        let mut beneficiaries = vec![working(0), working(0), working(0)];
+        for (stake, amount) in stakes.iter_mut().zip([1_001u64, 2_503, 7_777].iter()) {
+            restake(&mut beneficiaries, stake, &mut endpoint, *amount);
         }
+        let primary_shares = [1_001u64, 2_503, 7_777].map(|a| Split::DEFAULT.apply(a).1);
+        assert_eq!(endpoint.primary_stake, primary_shares.iter().sum::<u64>());
+        assert_eq!(endpoint.primary_stake, beneficiaries[1].beneficiary.staked);
+        assert_eq!(
+            endpoint.secondary_stake,
+            beneficiaries[2].beneficiary.staked
+        );
 
-        msg!(
-            "changing endpoint primary from {} to {}",
-            endpoint.primary,
-            new_primary_info.key
+        // the primary and secondary move to the new split right away
+        let total = endpoint.total_stake;
+        let primary_stake = endpoint.primary_stake;
+        change_split(&mut beneficiaries, (1, 2), &mut endpoint, new_split);
+        settle(&mut beneficiaries);
+        assert_eq!(endpoint.split, new_split);
+        assert_eq!(
+            endpoint.primary_stake,
+            primary_stake + new_split.apply(total).1 - Split::DEFAULT.apply(total).1
         );
-        msg!(
-            "changing endpoint secondary from {} to {}",
-            endpoint.secondary,
-            new_secondary_info.key
+        assert_eq!(endpoint.primary_stake, beneficiaries[1].beneficiary.staked);
+        assert_eq!(
+            endpoint.secondary_stake,
+            beneficiaries[2].beneficiary.staked
         );
 
-        endpoint.primary = *new_primary_info.key;
-        endpoint.secondary = *new_secondary_info.key;
+        // the stakers move when their stake changes
+        restake(&mut beneficiaries, &mut stakes[1], &mut endpoint, 3_000);
+        assert_eq!(stakes[1].split, new_split);
+        assert_eq!(stakes[0].split, Split::DEFAULT);
+        assert_eq!(endpoint.primary_stake, beneficiaries[1].beneficiary.staked);
 
-        settings_info
-            .data
-            .borrow_mut()
-            .copy_from_slice(&settings.try_to_vec()?);
-        old_primary_beneficiary_info
-            .data
-            .borrow_mut()
-            .copy_from_slice(
-                &beneficiaries[old_primary_beneficiary]
-                    .beneficiary
-                    .try_to_vec()?,
-            );
-        old_secondary_beneficiary_info
-            .data
-            .borrow_mut()
-            .copy_from_slice(
-                &beneficiaries[old_secondary_beneficiary]
-                    .beneficiary
-                    .try_to_vec()?,
-            );
+        // every staker withdraws under the split they were accounted under
+        for stake in stakes.iter_mut() {
+            restake(&mut beneficiaries, stake, &mut endpoint, 0);
+        }
+
+        assert_eq!(endpoint.total_stake, 0);
+        assert_eq!((endpoint.primary_stake, endpoint.secondary_stake), (0, 0));
+        for working in beneficiaries.iter() {
+            assert_eq!(working.beneficiary.staked, 0);
+        }
+
+        // taking out more than was accounted is an error
+        beneficiaries[1].sub += 1;
+        assert_eq!(
+            pay_out_beneficiaries(&mut beneficiaries, 0, &mut 0),
+            Err(ProgramError::InvalidArgument)
+        );
+    }
 
-        new_primary_beneficiary_info
-            .data
-            .borrow_mut()
-            .copy_from_slice(
-                &beneficiaries[new_primary_beneficiary]
-                    .beneficiary
-                    .try_to_vec()?,
+    #[test]
+    pub fn test_resync_share_bound() {
+        let splits = [
+            Split::DEFAULT,
+            Split {
+                staker: 0,
+                primary: 3_333,
+                secondary: 6_667,
+            },
+            Split {
+                staker: 1_111,
+                primary: 7_777,
+                secondary: 1_112,
+            },
+        ];
+        // stakes that round differently under every split
+        let stakes: Vec<u64> = (0..50).map(|i| MINIMUM_STAKE + 7 * i + 3).collect();
+        let total: u64 = stakes.iter().sum();
+        let bound = 2 * (total / MINIMUM_STAKE);
+
+        for split in splits.iter() {
+            let (_, primary, secondary) = split.apply(total);
+            let (per_primary, per_secondary) = stakes.iter().fold((0, 0), |(p, s), a| {
+                let (_, primary, secondary) = split.apply(*a);
+                (p + primary, s + secondary)
+            });
+            assert_eq!(
+                resync_share(Some(per_primary), primary, bound),
+                Ok(per_primary)
             );
-        new_secondary_beneficiary_info
-            .data
-            .borrow_mut()
-            .copy_from_slice(
-                &beneficiaries[new_secondary_beneficiary]
-                    .beneficiary
-                    .try_to_vec()?,
+            assert_eq!(
+                resync_share(Some(per_secondary), secondary, bound),
+                Ok(per_secondary)
             );
+        }
 
-        endpoint_info
-            .data
-            .borrow_mut()
-            .copy_from_slice(&endpoint.try_to_vec()?);
-
-        Ok(())
+        assert_eq!(
+            resync_share(Some(1_000 + bound + 1), 1_000, bound),
+            Err(StakingError::ResyncOutOfBounds.into())
+        );
+        assert_eq!(
+            resync_share(None, 1_000, bound),
+            Err(ProgramError::InvalidInstructionData)
+        );
     }
 }
-
-#[cfg(test)]
-mod tests {}
@@ -0,0 +1,237 @@
+//! Off-chain projection of future yield.
+//!
+//! Runs the same reward math as the program (`Settings::accrue`) on a copy of
+//! the Settings, so projections follow the decay and the emission schedule
+//! exactly. Projections assume that the total stake stays the same over the
+//! horizon. Only available with the `client` feature.
+
+use solana_program::clock::UnixTimestamp;
+
+use crate::account::{EmissionSchedule, Endpoint, Settings, Stake};
+use crate::{PRECISION, SECONDS_PER_YEAR, ZERO_KEY};
+
+/// The projected yield of a stake for each of its beneficiaries
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub struct Projection {
+    pub staker: u64,
+    pub primary: u64,
+    pub secondary: u64,
+}
+
+impl Projection {
+    pub fn total(&self) -> u64 {
+        self.staker + self.primary + self.secondary
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RewardProjector {
+    settings: Settings,
+    schedule: Option<EmissionSchedule>,
+    pool_balance: u64,
+}
+
+impl RewardProjector {
+    /// Create a projector from a snapshot of the Settings. Without a pool balance,
+    /// the reward pool is assumed to cover all emissions.
+    pub fn new(settings: Settings) -> RewardProjector {
+        RewardProjector {
+            settings,
+            schedule: None,
+            pool_balance: u64::MAX,
+        }
+    }
+
+    /// The EmissionSchedule referenced by the Settings
+    pub fn with_schedule(mut self, schedule: EmissionSchedule) -> RewardProjector {
+        self.schedule = Some(schedule);
+        self
+    }
+
+    /// Cap emissions at the balance of the reward pool, like the program does
+    pub fn with_pool_balance(mut self, pool_balance: u64) -> RewardProjector {
+        self.pool_balance = pool_balance;
+        self
+    }
+
+    /// The Settings as they will be at `time`
+    pub fn settings_at(&self, time: UnixTimestamp) -> Settings {
+        let mut settings = self.settings;
+        settings.accrue(time, self.schedule.as_ref(), self.pool_balance);
+        settings
+    }
+
+    /// The reward per share accrued between `from` and `from + horizon` seconds
+    fn reward(&self, from: UnixTimestamp, horizon: i64) -> u128 {
+        let start = self.settings_at(from);
+        let mut end = start;
+        end.accrue(
+            from.max(start.last_reward) + horizon,
+            self.schedule.as_ref(),
+            self.pool_balance,
+        );
+        end.reward_per_share - start.reward_per_share
+    }
+
+    /// Project the yield of a stake with `endpoint` between `from` and
+    /// `from + horizon` seconds.
+    ///
+    /// The primary and secondary earn their shares under the Endpoint's split.
+    /// The staker earns under the split of the stake, which only adopts the
+    /// Endpoint's split the next time the stake changes. An Endpoint without a
+    /// secondary earns nothing for it.
+    pub fn project(
+        &self,
+        stake: &Stake,
+        endpoint: &Endpoint,
+        from: UnixTimestamp,
+        horizon: i64,
+    ) -> Projection {
+        let reward = self.reward(from, horizon);
+        let earned = |share: u64| (share as u128 * reward / PRECISION) as u64;
+
+        let (staker, _, _) = stake.split.apply(stake.total_stake);
+        let (_, primary, secondary) = endpoint.split.apply(stake.total_stake);
+        Projection {
+            staker: earned(staker),
+            primary: earned(primary),
+            secondary: match endpoint.secondary {
+                ZERO_KEY => 0,
+                _ => earned(secondary),
+            },
+        }
+    }
+
+    /// The yield per staked ZEE over the year following `now`, before it is
+    /// divided between the staker and the beneficiaries. Follows the decay, the
+    /// emission schedule and the cap of the reward pool like `project`. Yield is
+    /// not restaked automatically, so there is no compounding.
+    /// Returns `None` if nothing is staked.
+    pub fn apy(&self, now: UnixTimestamp) -> Option<f64> {
+        if self.settings_at(now).total_stake == 0 {
+            return None;
+        }
+        let reward = self.reward(now, SECONDS_PER_YEAR as i64);
+        Some(reward as f64 / PRECISION as f64)
+    }
+
+    /// The yearly yield per staked ZEE if the emission in effect at `now` stayed
+    /// the same, ignoring the decay, the schedule and the reward pool.
+    /// Returns `None` if nothing is staked.
+    pub fn nominal_apy(&self, now: UnixTimestamp) -> Option<f64> {
+        let settings = self.settings_at(now);
+        if settings.total_stake == 0 {
+            return None;
+        }
+        let per_year =
+            settings.emission as f64 * SECONDS_PER_YEAR as f64 / settings.decay.period as f64;
+        Some(per_year / settings.total_stake as f64)
+    }
+
+    /// The yearly yield of the staker's share relative to the staked amount
+    pub fn staker_apy(&self, stake: &Stake, now: UnixTimestamp) -> Option<f64> {
+        if stake.total_stake == 0 {
+            return None;
+        }
+        let (staker, _, _) = stake.split.apply(stake.total_stake);
+        self.apy(now)
+            .map(|apy| apy * staker as f64 / stake.total_stake as f64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::account::{Authority, EmissionDecay, Split, SplitBounds};
+    use solana_program::pubkey::Pubkey;
+
+    #[test]
+    pub fn test_projection() {
+        let year = SECONDS_PER_YEAR as i64;
+        let settings = Settings {
+            token: Pubkey::new_unique(),
+            admin: Authority::Basic(Pubkey::new_unique()),
+            unbonding_duration: 0,
+            split_bounds: SplitBounds::UNRESTRICTED,
+            next_emission_change: year,
+            // divides evenly by the seconds of a year and the total stake
+            emission: SECONDS_PER_YEAR as u64 * 100,
+            decay: EmissionDecay::DEFAULT,
+            emission_schedule: ZERO_KEY,
+            total_stake: 10_000,
+            reward_per_share: 0,
+            last_reward: 0,
+            liability: 0,
+            total_funded: 0,
+        };
+        let stake = Stake {
+            creation_date: 0,
+            total_stake: 10_000,
+            staker: Pubkey::new_unique(),
+            split: Split::DEFAULT,
+            unbonding_amount: 0,
+            unbonding: vec![],
+        };
+        let mut endpoint = Endpoint {
+            creation_date: 0,
+            total_stake: 10_000,
+            owner: Authority::Basic(Pubkey::new_unique()),
+            primary: Pubkey::new_unique(),
+            secondary: Pubkey::new_unique(),
+            split: Split::DEFAULT,
+            primary_stake: 4_750,
+            secondary_stake: 500,
+            sunset: ZERO_KEY,
+        };
+        let projector = RewardProjector::new(settings);
+        assert_eq!(projector.apy(0), Some(315_360.0));
+        assert_eq!(projector.nominal_apy(0), Some(315_360.0));
+        assert_eq!(projector.staker_apy(&stake, 0), Some(149_796.0));
+
+        // the only stake receives the full emission
+        let p = projector.project(&stake, &endpoint, 0, year);
+        assert_eq!(
+            (p.staker, p.primary, p.secondary),
+            (1_497_960_000, 1_497_960_000, 157_680_000)
+        );
+
+        // the second year decays by 10%
+        let p = projector.project(&stake, &endpoint, year, year);
+        assert_eq!(p.total(), 2_838_240_000);
+        assert_eq!(projector.apy(year), Some(283_824.0));
+        // half a year at the first emission, half at the decayed one
+        assert_eq!(projector.apy(year / 2), Some(299_592.0));
+        assert_eq!(projector.nominal_apy(year / 2), Some(315_360.0));
+
+        // matches update_rewards
+        let mut on_chain = settings;
+        on_chain.update_rewards(2 * year, None, u64::MAX);
+        assert_eq!(
+            on_chain.reward_per_share,
+            projector.settings_at(2 * year).reward_per_share
+        );
+
+        // the pool caps the projection and the apy, but not the nominal apy
+        let capped = projector.clone().with_pool_balance(250_000);
+        assert_eq!(capped.project(&stake, &endpoint, 0, year).total(), 250_000);
+        assert_eq!(capped.apy(0), Some(25.0));
+        assert_eq!(capped.nominal_apy(0), Some(315_360.0));
+
+        // the primary and secondary move to a new split right away, the staker
+        // once the stake changes
+        endpoint.split = Split {
+            staker: 8_000,
+            primary: 1_500,
+            secondary: 500,
+        };
+        let p = projector.project(&stake, &endpoint, 0, year);
+        assert_eq!(
+            (p.staker, p.primary, p.secondary),
+            (1_497_960_000, 473_040_000, 157_680_000)
+        );
+
+        // nobody earns the share of a missing secondary
+        endpoint.secondary = ZERO_KEY;
+        assert_eq!(projector.project(&stake, &endpoint, 0, year).secondary, 0);
+    }
+}
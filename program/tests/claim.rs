@@ -0,0 +1,85 @@
+mod common;
+
+use solana_program::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use staking::{
+    account::{Beneficiary, RewardPool, Settings},
+    instruction,
+};
+
+#[tokio::test]
+async fn test_stake_and_claim() {
+    let program_id = Pubkey::new_unique();
+    let upgrade_authority = Keypair::new();
+
+    let (mut context, mint) = common::setup(&program_id, &upgrade_authority).await;
+    let payer = context.payer.pubkey();
+
+    // an endpoint with a single staker
+    let primary = Pubkey::new_unique();
+    let secondary = Pubkey::new_unique();
+    let endpoint =
+        common::register_endpoint(&mut context, &program_id, &primary, &primary, &secondary).await;
+
+    let staker = Keypair::new();
+    let staker_tokens =
+        common::create_token_account(&mut context, &mint, &staker.pubkey(), 5_000).await;
+    common::process(
+        &mut context,
+        &[
+            instruction::initialize_stake(&program_id, &payer, &staker.pubkey(), &endpoint, &mint),
+            // the reward pool is still empty, so nothing is paid out
+            instruction::stake(
+                &program_id,
+                &payer,
+                &staker.pubkey(),
+                &staker_tokens,
+                &endpoint,
+                &primary,
+                &secondary,
+                1_000,
+                None,
+            ),
+        ],
+        &[&staker],
+    )
+    .await;
+    assert_eq!(
+        common::token_balance(&mut context, &staker_tokens).await,
+        4_000
+    );
+
+    common::fund_reward_pool(&mut context, &program_id, &mint, 1_000_000).await;
+    common::advance_clock(&mut context, 86_400).await;
+
+    let claim = instruction::claim(&program_id, &payer, &staker.pubkey(), &staker_tokens);
+    common::process(&mut context, &[claim.clone()], &[&staker]).await;
+
+    let reward_pool = RewardPool::program_address(&program_id).0;
+    let claimed = common::token_balance(&mut context, &staker_tokens).await - 4_000;
+    assert!(claimed > 0);
+    assert_eq!(
+        common::token_balance(&mut context, &reward_pool).await,
+        1_000_000 - claimed
+    );
+
+    let settings: Settings = common::get(&mut context, &Settings::program_address(&program_id).0)
+        .await
+        .unwrap();
+    assert_eq!(settings.liability, 1_000_000 - claimed);
+
+    let beneficiary: Beneficiary = common::get(
+        &mut context,
+        &Beneficiary::program_address(&staker.pubkey(), &program_id).0,
+    )
+    .await
+    .unwrap();
+    assert_eq!(beneficiary.holding, 0);
+
+    // claiming again at the same time has nothing to pay out
+    common::process(&mut context, &[claim], &[&staker]).await;
+    assert_eq!(
+        common::token_balance(&mut context, &staker_tokens).await,
+        4_000 + claimed
+    );
+}
@@ -0,0 +1,72 @@
+mod common;
+
+use solana_program::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use staking::{
+    account::{Beneficiary, Split},
+    error::StakingError,
+    instruction,
+};
+
+#[tokio::test]
+async fn test_close_and_recreate_beneficiary() {
+    let program_id = Pubkey::new_unique();
+    let upgrade_authority = Keypair::new();
+
+    let (mut context, mint) = common::setup(&program_id, &upgrade_authority).await;
+    let payer = context.payer.pubkey();
+
+    let primary = Pubkey::new_unique();
+    let secondary = Pubkey::new_unique();
+    let endpoint =
+        common::register_endpoint(&mut context, &program_id, &primary, &primary, &secondary).await;
+
+    let staker = Keypair::new();
+    let staker_tokens = common::create_stake(
+        &mut context,
+        &program_id,
+        &mint,
+        &staker,
+        (&endpoint, &primary, &secondary),
+        1_000,
+        2_000,
+    )
+    .await;
+    let stake = |amount| {
+        instruction::stake(
+            &program_id,
+            &payer,
+            &staker.pubkey(),
+            &staker_tokens,
+            &endpoint,
+            &primary,
+            &secondary,
+            amount,
+            None,
+        )
+    };
+    let beneficiary = Beneficiary::program_address(&staker.pubkey(), &program_id).0;
+    let close = instruction::close_beneficiary(&program_id, &payer, &staker.pubkey());
+
+    // a beneficiary with stake cannot be closed
+    let err = common::try_process(&mut context, &[close.clone()], &[&staker])
+        .await
+        .unwrap_err();
+    assert_eq!(
+        common::custom_error(err),
+        StakingError::BeneficiaryNotEmpty as u32
+    );
+
+    // the reward pool is empty, so nothing is held after unstaking
+    common::process(&mut context, &[stake(-1_000)], &[&staker]).await;
+    common::process(&mut context, &[close], &[&staker]).await;
+    assert!(!common::exists(&mut context, &beneficiary).await);
+    assert!(common::lamports(&mut context, &staker.pubkey()).await > 0);
+
+    // staking again creates it anew
+    common::process(&mut context, &[stake(1_000)], &[&staker]).await;
+    let recreated: Beneficiary = common::get(&mut context, &beneficiary).await.unwrap();
+    assert_eq!(recreated.authority, staker.pubkey());
+    assert_eq!(recreated.staked, Split::DEFAULT.apply(1_000).0);
+    assert_eq!(recreated.holding, 0);
+}
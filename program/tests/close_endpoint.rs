@@ -0,0 +1,142 @@
+mod common;
+
+use solana_program::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use staking::{account::Endpoint, error::StakingError, instruction};
+
+#[tokio::test]
+async fn test_close_endpoint() {
+    let program_id = Pubkey::new_unique();
+    let upgrade_authority = Keypair::new();
+
+    let (mut context, _) = common::setup(&program_id, &upgrade_authority).await;
+    let payer = context.payer.pubkey();
+
+    let owner = Keypair::new();
+    let endpoint = common::register_endpoint(
+        &mut context,
+        &program_id,
+        &owner.pubkey(),
+        &Pubkey::new_unique(),
+        &Pubkey::new_unique(),
+    )
+    .await;
+
+    // an endpoint without stake is closed right away
+    let destination = Pubkey::new_unique();
+    common::process(
+        &mut context,
+        &[instruction::close_endpoint(
+            &program_id,
+            &payer,
+            &endpoint,
+            &owner.pubkey(),
+            &owner.pubkey(),
+            &destination,
+            false,
+        )],
+        &[&owner],
+    )
+    .await;
+    assert!(!common::exists(&mut context, &endpoint).await);
+    assert!(common::lamports(&mut context, &destination).await > 0);
+}
+
+#[tokio::test]
+async fn test_sunset_endpoint() {
+    let program_id = Pubkey::new_unique();
+    let upgrade_authority = Keypair::new();
+
+    let (mut context, mint) = common::setup(&program_id, &upgrade_authority).await;
+    let payer = context.payer.pubkey();
+
+    let owner = Keypair::new();
+    let primary = Pubkey::new_unique();
+    let secondary = Pubkey::new_unique();
+    let endpoint = common::register_endpoint(
+        &mut context,
+        &program_id,
+        &owner.pubkey(),
+        &primary,
+        &secondary,
+    )
+    .await;
+
+    let staker = Keypair::new();
+    let staker_tokens = common::create_stake(
+        &mut context,
+        &program_id,
+        &mint,
+        &staker,
+        (&endpoint, &primary, &secondary),
+        1_000,
+        2_000,
+    )
+    .await;
+
+    let destination = Pubkey::new_unique();
+    let close = |sunset| {
+        instruction::close_endpoint(
+            &program_id,
+            &payer,
+            &endpoint,
+            &owner.pubkey(),
+            &owner.pubkey(),
+            &destination,
+            sunset,
+        )
+    };
+    let err = common::try_process(&mut context, &[close(false)], &[&owner])
+        .await
+        .unwrap_err();
+    assert_eq!(
+        common::custom_error(err),
+        StakingError::EndpointHasStake as u32
+    );
+
+    common::process(&mut context, &[close(true)], &[&owner]).await;
+    let sunset: Endpoint = common::get(&mut context, &endpoint).await.unwrap();
+    assert!(sunset.is_sunset());
+    assert_eq!(sunset.sunset, destination);
+
+    let stake = |amount: i64, sunset_destination: &Pubkey| {
+        instruction::stake(
+            &program_id,
+            &payer,
+            &staker.pubkey(),
+            &staker_tokens,
+            &endpoint,
+            &primary,
+            &secondary,
+            amount,
+            Some(sunset_destination),
+        )
+    };
+
+    // no new stake is accepted
+    let err = common::try_process(&mut context, &[stake(1_000, &destination)], &[&staker])
+        .await
+        .unwrap_err();
+    assert_eq!(
+        common::custom_error(err),
+        StakingError::EndpointSunset as u32
+    );
+
+    // withdrawals have to pass the destination the owner chose
+    let err = common::try_process(
+        &mut context,
+        &[stake(-1_000, &Pubkey::new_unique())],
+        &[&staker],
+    )
+    .await
+    .unwrap_err();
+    assert_eq!(
+        common::custom_error(err),
+        StakingError::InvalidSunsetDestination as u32
+    );
+
+    // the last withdrawal closes the endpoint
+    common::process(&mut context, &[stake(-1_000, &destination)], &[&staker]).await;
+    assert!(!common::exists(&mut context, &endpoint).await);
+    assert!(common::lamports(&mut context, &destination).await > 0);
+}
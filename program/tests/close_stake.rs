@@ -0,0 +1,95 @@
+mod common;
+
+use solana_program::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use staking::{account::Stake, error::StakingError, instruction};
+
+#[tokio::test]
+async fn test_close_stake() {
+    let program_id = Pubkey::new_unique();
+    let upgrade_authority = Keypair::new();
+
+    let (mut context, mint) = common::setup(&program_id, &upgrade_authority).await;
+    let payer = context.payer.pubkey();
+
+    let primary = Pubkey::new_unique();
+    let secondary = Pubkey::new_unique();
+    let endpoint =
+        common::register_endpoint(&mut context, &program_id, &primary, &primary, &secondary).await;
+
+    let staker = Keypair::new();
+    let staker_tokens = common::create_stake(
+        &mut context,
+        &program_id,
+        &mint,
+        &staker,
+        (&endpoint, &primary, &secondary),
+        1_000,
+        1_000,
+    )
+    .await;
+    common::process(
+        &mut context,
+        &[instruction::stake(
+            &program_id,
+            &payer,
+            &staker.pubkey(),
+            &staker_tokens,
+            &endpoint,
+            &primary,
+            &secondary,
+            -1_000,
+            None,
+        )],
+        &[&staker],
+    )
+    .await;
+
+    let stake = Stake::program_address(&endpoint, &staker.pubkey(), &program_id).0;
+    let fund = Stake::fund_address(&endpoint, &staker.pubkey(), &program_id).0;
+    let destination = Pubkey::new_unique();
+    let close = instruction::close_stake(
+        &program_id,
+        &payer,
+        &staker.pubkey(),
+        &staker_tokens,
+        &endpoint,
+        &destination,
+    );
+
+    // the unbonding funds still have to be withdrawn
+    let err = common::try_process(&mut context, &[close.clone()], &[&staker])
+        .await
+        .unwrap_err();
+    assert_eq!(
+        common::custom_error(err),
+        StakingError::StakeNotEmpty as u32
+    );
+
+    common::process(
+        &mut context,
+        &[
+            instruction::withdraw_unbond(
+                &program_id,
+                &payer,
+                &staker.pubkey(),
+                &staker_tokens,
+                &endpoint,
+            ),
+            // tokens sent to the fund directly are returned to the staker
+            spl_token::instruction::mint_to(&spl_token::id(), &mint, &fund, &payer, &[], 7)
+                .unwrap(),
+        ],
+        &[&staker],
+    )
+    .await;
+    common::process(&mut context, &[close], &[&staker]).await;
+
+    assert!(!common::exists(&mut context, &stake).await);
+    assert!(!common::exists(&mut context, &fund).await);
+    assert!(common::lamports(&mut context, &destination).await > 0);
+    assert_eq!(
+        common::token_balance(&mut context, &staker_tokens).await,
+        1_007
+    );
+}
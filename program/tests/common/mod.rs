@@ -0,0 +1,354 @@
+//! Helpers shared by the program tests
+#![allow(dead_code)]
+
+use solana_program::{
+    bpf_loader_upgradeable,
+    clock::Clock,
+    instruction::{Instruction, InstructionError},
+    program_pack::Pack,
+    pubkey::Pubkey,
+    rent::Rent,
+    system_instruction,
+};
+use solana_program_test::{processor, BanksClientError, ProgramTest, ProgramTestContext};
+use solana_sdk::{
+    account::Account as SolanaAccount,
+    signature::{Keypair, Signer},
+    transaction::{Transaction, TransactionError},
+};
+use spl_token::state::{Account, AccountState, Mint};
+use staking::{
+    account::{Authority, Split, Versioned},
+    instruction,
+    processor::Processor,
+};
+
+pub async fn try_process(
+    context: &mut ProgramTestContext,
+    instructions: &[Instruction],
+    signers: &[&Keypair],
+) -> Result<(), BanksClientError> {
+    let blockhash = context
+        .banks_client
+        .get_new_latest_blockhash(&context.last_blockhash)
+        .await
+        .unwrap();
+    context.last_blockhash = blockhash;
+
+    let mut all_signers = vec![&context.payer];
+    all_signers.extend_from_slice(signers);
+    let transaction = Transaction::new_signed_with_payer(
+        instructions,
+        Some(&context.payer.pubkey()),
+        &all_signers,
+        blockhash,
+    );
+    context.banks_client.process_transaction(transaction).await
+}
+
+pub async fn process(
+    context: &mut ProgramTestContext,
+    instructions: &[Instruction],
+    signers: &[&Keypair],
+) {
+    try_process(context, instructions, signers).await.unwrap();
+}
+
+/// The custom error code of a transaction that failed in the program
+pub fn custom_error(err: BanksClientError) -> u32 {
+    match err.unwrap() {
+        TransactionError::InstructionError(_, InstructionError::Custom(code)) => code,
+        err => panic!("unexpected error: {:?}", err),
+    }
+}
+
+/// A program account, `None` if it does not exist
+pub async fn get<T: Versioned>(context: &mut ProgramTestContext, address: &Pubkey) -> Option<T> {
+    context
+        .banks_client
+        .get_account(*address)
+        .await
+        .unwrap()
+        .map(|account| T::from_account_data(&account.data).unwrap())
+}
+
+pub async fn exists(context: &mut ProgramTestContext, address: &Pubkey) -> bool {
+    context
+        .banks_client
+        .get_account(*address)
+        .await
+        .unwrap()
+        .is_some()
+}
+
+pub async fn lamports(context: &mut ProgramTestContext, address: &Pubkey) -> u64 {
+    context.banks_client.get_balance(*address).await.unwrap()
+}
+
+pub async fn token_balance(context: &mut ProgramTestContext, address: &Pubkey) -> u64 {
+    let account = context
+        .banks_client
+        .get_account(*address)
+        .await
+        .unwrap()
+        .unwrap();
+    Account::unpack(&account.data).unwrap().amount
+}
+
+pub async fn create_token_account(
+    context: &mut ProgramTestContext,
+    mint: &Pubkey,
+    owner: &Pubkey,
+    amount: u64,
+) -> Pubkey {
+    let account = Keypair::new();
+    let rent = context.banks_client.get_rent().await.unwrap();
+    let payer = context.payer.pubkey();
+    process(
+        context,
+        &[
+            system_instruction::create_account(
+                &payer,
+                &account.pubkey(),
+                rent.minimum_balance(Account::LEN),
+                Account::LEN as u64,
+                &spl_token::id(),
+            ),
+            spl_token::instruction::initialize_account(
+                &spl_token::id(),
+                &account.pubkey(),
+                mint,
+                owner,
+            )
+            .unwrap(),
+            spl_token::instruction::mint_to(
+                &spl_token::id(),
+                mint,
+                &account.pubkey(),
+                &payer,
+                &[],
+                amount,
+            )
+            .unwrap(),
+        ],
+        &[&account],
+    )
+    .await;
+    account.pubkey()
+}
+
+/// A rent exempt account to add before the test starts
+pub fn rent_exempt(owner: &Pubkey, data: Vec<u8>) -> SolanaAccount {
+    SolanaAccount {
+        lamports: Rent::default().minimum_balance(data.len()),
+        data,
+        owner: *owner,
+        executable: false,
+        rent_epoch: 0,
+    }
+}
+
+/// A token mint to add before the test starts
+pub fn mint_account(mint_authority: &Pubkey, supply: u64) -> SolanaAccount {
+    let mut data = vec![0; Mint::LEN];
+    Mint::pack(
+        Mint {
+            mint_authority: Some(*mint_authority).into(),
+            supply,
+            is_initialized: true,
+            ..Mint::default()
+        },
+        &mut data,
+    )
+    .unwrap();
+    rent_exempt(&spl_token::id(), data)
+}
+
+/// A token account to add before the test starts
+pub fn token_account(mint: &Pubkey, owner: &Pubkey, amount: u64) -> SolanaAccount {
+    let mut data = vec![0; Account::LEN];
+    Account::pack(
+        Account {
+            mint: *mint,
+            owner: *owner,
+            amount,
+            state: AccountState::Initialized,
+            ..Account::default()
+        },
+        &mut data,
+    )
+    .unwrap();
+    rent_exempt(&spl_token::id(), data)
+}
+
+/// The ProgramData account of an upgradeable program, which `Initialize` reads
+/// the upgrade authority from
+pub fn program_data(upgrade_authority: &Pubkey) -> SolanaAccount {
+    let mut data = vec![3, 0, 0, 0];
+    data.extend_from_slice(&0u64.to_le_bytes());
+    data.push(1);
+    data.extend_from_slice(upgrade_authority.as_ref());
+    SolanaAccount {
+        lamports: 1_000_000_000,
+        data,
+        owner: bpf_loader_upgradeable::id(),
+        executable: false,
+        rent_epoch: 0,
+    }
+}
+
+/// The program, deployed with `upgrade_authority`
+pub fn program_test(program_id: &Pubkey, upgrade_authority: &Pubkey) -> ProgramTest {
+    let mut program_test = ProgramTest::new("staking", *program_id, processor!(Processor::process));
+    program_test.add_account(
+        Pubkey::find_program_address(&[program_id.as_ref()], &bpf_loader_upgradeable::id()).0,
+        program_data(upgrade_authority),
+    );
+    program_test
+}
+
+/// Start the program with a new token, minted by the payer, and initialize it
+/// without an unbonding duration. Returns the mint.
+pub async fn setup(
+    program_id: &Pubkey,
+    upgrade_authority: &Keypair,
+) -> (ProgramTestContext, Pubkey) {
+    let mut context = program_test(program_id, &upgrade_authority.pubkey())
+        .start_with_context()
+        .await;
+    let payer = context.payer.pubkey();
+
+    let mint = Keypair::new();
+    let rent = context.banks_client.get_rent().await.unwrap();
+    process(
+        &mut context,
+        &[
+            system_instruction::create_account(
+                &payer,
+                &mint.pubkey(),
+                rent.minimum_balance(Mint::LEN),
+                Mint::LEN as u64,
+                &spl_token::id(),
+            ),
+            spl_token::instruction::initialize_mint(
+                &spl_token::id(),
+                &mint.pubkey(),
+                &payer,
+                None,
+                0,
+            )
+            .unwrap(),
+        ],
+        &[&mint],
+    )
+    .await;
+
+    let clock: Clock = context.banks_client.get_sysvar().await.unwrap();
+    process(
+        &mut context,
+        &[instruction::initialize(
+            program_id,
+            &payer,
+            &mint.pubkey(),
+            Authority::Basic(upgrade_authority.pubkey()),
+            &upgrade_authority.pubkey(),
+            clock.unix_timestamp,
+            0,
+        )],
+        &[upgrade_authority],
+    )
+    .await;
+
+    (context, mint.pubkey())
+}
+
+pub async fn advance_clock(context: &mut ProgramTestContext, seconds: i64) {
+    let mut clock: Clock = context.banks_client.get_sysvar().await.unwrap();
+    clock.unix_timestamp += seconds;
+    context.set_sysvar(&clock);
+}
+
+/// Register an Endpoint owned by `owner` with the default split
+pub async fn register_endpoint(
+    context: &mut ProgramTestContext,
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    primary: &Pubkey,
+    secondary: &Pubkey,
+) -> Pubkey {
+    let endpoint = Keypair::new();
+    let payer = context.payer.pubkey();
+    process(
+        context,
+        &[instruction::register_endpoint(
+            program_id,
+            &payer,
+            &endpoint.pubkey(),
+            Authority::Basic(*owner),
+            primary,
+            secondary,
+            Split::DEFAULT,
+        )],
+        &[&endpoint],
+    )
+    .await;
+    endpoint.pubkey()
+}
+
+/// Fund the reward pool from a new token account of the payer
+pub async fn fund_reward_pool(
+    context: &mut ProgramTestContext,
+    program_id: &Pubkey,
+    mint: &Pubkey,
+    amount: u64,
+) {
+    let payer = context.payer.pubkey();
+    let funder_tokens = create_token_account(context, mint, &payer, amount).await;
+    process(
+        context,
+        &[instruction::fund_reward_pool(
+            program_id,
+            &payer,
+            &payer,
+            &funder_tokens,
+            amount,
+        )],
+        &[],
+    )
+    .await;
+}
+
+/// Initialize the Stake of `staker` with an Endpoint and stake `amount` from a
+/// new token account that starts out with `balance`. Returns the token account.
+pub async fn create_stake(
+    context: &mut ProgramTestContext,
+    program_id: &Pubkey,
+    mint: &Pubkey,
+    staker: &Keypair,
+    (endpoint, primary, secondary): (&Pubkey, &Pubkey, &Pubkey),
+    amount: u64,
+    balance: u64,
+) -> Pubkey {
+    let payer = context.payer.pubkey();
+    let staker_tokens = create_token_account(context, mint, &staker.pubkey(), balance).await;
+    process(
+        context,
+        &[
+            instruction::initialize_stake(program_id, &payer, &staker.pubkey(), endpoint, mint),
+            instruction::stake(
+                program_id,
+                &payer,
+                &staker.pubkey(),
+                &staker_tokens,
+                endpoint,
+                primary,
+                secondary,
+                amount as i64,
+                None,
+            ),
+        ],
+        &[staker],
+    )
+    .await;
+    staker_tokens
+}
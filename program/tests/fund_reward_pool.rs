@@ -0,0 +1,56 @@
+mod common;
+
+use solana_program::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use staking::{
+    account::{RewardPool, Settings},
+    error::StakingError,
+    instruction,
+};
+
+#[tokio::test]
+async fn test_fund_reward_pool() {
+    let program_id = Pubkey::new_unique();
+    let upgrade_authority = Keypair::new();
+
+    let (mut context, mint) = common::setup(&program_id, &upgrade_authority).await;
+    let payer = context.payer.pubkey();
+
+    common::fund_reward_pool(&mut context, &program_id, &mint, 1_000).await;
+
+    // anyone can fund the pool
+    let funder = Keypair::new();
+    let funder_tokens =
+        common::create_token_account(&mut context, &mint, &funder.pubkey(), 500).await;
+    let fund = |amount| {
+        instruction::fund_reward_pool(
+            &program_id,
+            &payer,
+            &funder.pubkey(),
+            &funder_tokens,
+            amount,
+        )
+    };
+
+    let err = common::try_process(&mut context, &[fund(0)], &[&funder])
+        .await
+        .unwrap_err();
+    assert_eq!(
+        common::custom_error(err),
+        StakingError::FundInvalidAmount as u32
+    );
+
+    common::process(&mut context, &[fund(500)], &[&funder]).await;
+    assert_eq!(common::token_balance(&mut context, &funder_tokens).await, 0);
+    assert_eq!(
+        common::token_balance(&mut context, &RewardPool::program_address(&program_id).0).await,
+        1_500
+    );
+
+    let settings: Settings = common::get(&mut context, &Settings::program_address(&program_id).0)
+        .await
+        .unwrap();
+    assert_eq!(settings.total_funded, 1_500);
+    // nothing has been emitted yet
+    assert_eq!(settings.liability, 0);
+}
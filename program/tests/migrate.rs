@@ -0,0 +1,200 @@
+mod common;
+
+use borsh::BorshSerialize;
+use solana_program::pubkey::Pubkey;
+use solana_sdk::{
+    account::Account as SolanaAccount,
+    signature::{Keypair, Signer},
+};
+use staking::{
+    account::{
+        Authority, Beneficiary, Endpoint, PoolAuthority, RewardPool, Settings, Split, Stake,
+    },
+    instruction, legacy,
+};
+
+/// A program account in a layout from before accounts were versioned
+fn legacy_account<T: BorshSerialize>(program_id: &Pubkey, account: &T) -> SolanaAccount {
+    common::rent_exempt(program_id, account.try_to_vec().unwrap())
+}
+
+#[tokio::test]
+async fn test_migrate_and_unstake() {
+    let program_id = Pubkey::new_unique();
+    let upgrade_authority = Keypair::new();
+    let mut program_test = common::program_test(&program_id, &upgrade_authority.pubkey());
+
+    // a deployment from before accounts were versioned, with two stakes on an
+    // endpoint. the primary and secondary hold the sum of their shares of each
+    // stake, which differs from their share of the whole endpoint
+    let stake_amount = 1_039;
+    let (staker_share, primary_share, secondary_share) = Split::DEFAULT.apply(stake_amount);
+    assert_ne!(2 * primary_share, Split::DEFAULT.apply(2 * stake_amount).1);
+
+    let mint = Pubkey::new_unique();
+    program_test.add_account(
+        mint,
+        common::mint_account(&Pubkey::new_unique(), 2 * stake_amount),
+    );
+    program_test.add_account(
+        RewardPool::program_address(&program_id).0,
+        common::token_account(&mint, &PoolAuthority::program_address(&program_id).0, 0),
+    );
+    // no emissions, so nothing is owed
+    program_test.add_account(
+        Settings::program_address(&program_id).0,
+        legacy_account(
+            &program_id,
+            &legacy::Settings {
+                token: mint,
+                unbonding_duration: 0,
+                next_emission_change: i64::MAX,
+                emission: 0,
+                total_stake: 2 * stake_amount,
+                reward_per_share: 0,
+                last_reward: 0,
+            },
+        ),
+    );
+
+    let endpoint = Pubkey::new_unique();
+    let primary = Pubkey::new_unique();
+    let secondary = Pubkey::new_unique();
+    program_test.add_account(
+        endpoint,
+        legacy_account(
+            &program_id,
+            &legacy::Endpoint {
+                creation_date: 0,
+                total_stake: 2 * stake_amount,
+                owner: Authority::Basic(primary),
+                primary,
+                secondary,
+            },
+        ),
+    );
+
+    let stakers = [Keypair::new(), Keypair::new()];
+    let mut stakes = vec![];
+    let mut staker_tokens = vec![];
+    let mut beneficiaries = vec![];
+    for staker in &stakers {
+        let stake = Stake::program_address(&endpoint, &staker.pubkey(), &program_id).0;
+        program_test.add_account(
+            stake,
+            legacy_account(
+                &program_id,
+                &legacy::Stake {
+                    creation_date: 0,
+                    total_stake: stake_amount,
+                    staker: staker.pubkey(),
+                    unbonding_end: 0,
+                    unbonding_amount: 0,
+                },
+            ),
+        );
+        program_test.add_account(
+            Stake::fund_address(&endpoint, &staker.pubkey(), &program_id).0,
+            common::token_account(&mint, &stake, stake_amount),
+        );
+        let tokens = Pubkey::new_unique();
+        program_test.add_account(tokens, common::token_account(&mint, &staker.pubkey(), 0));
+
+        stakes.push(stake);
+        staker_tokens.push(tokens);
+        beneficiaries.push((staker.pubkey(), staker_share));
+    }
+    beneficiaries.push((primary, 2 * primary_share));
+    beneficiaries.push((secondary, 2 * secondary_share));
+
+    let beneficiary_addresses: Vec<Pubkey> = beneficiaries
+        .iter()
+        .map(|(authority, _)| Beneficiary::program_address(authority, &program_id).0)
+        .collect();
+    for (address, (authority, staked)) in beneficiary_addresses.iter().zip(&beneficiaries) {
+        program_test.add_account(
+            *address,
+            legacy_account(
+                &program_id,
+                &legacy::Beneficiary {
+                    authority: *authority,
+                    staked: *staked,
+                    reward_debt: 0,
+                    holding: 0,
+                },
+            ),
+        );
+    }
+
+    let mut context = program_test.start_with_context().await;
+    let payer = context.payer.pubkey();
+
+    common::process(
+        &mut context,
+        &[instruction::migrate_settings(
+            &program_id,
+            &payer,
+            &upgrade_authority.pubkey(),
+            &beneficiary_addresses,
+            0,
+        )],
+        &[&upgrade_authority],
+    )
+    .await;
+    common::process(
+        &mut context,
+        &[instruction::migrate(
+            &program_id,
+            &payer,
+            &endpoint,
+            &stakes,
+        )],
+        &[],
+    )
+    .await;
+    for address in stakes.iter().chain(&beneficiary_addresses) {
+        common::process(
+            &mut context,
+            &[instruction::migrate(&program_id, &payer, address, &[])],
+            &[],
+        )
+        .await;
+    }
+
+    let migrated: Endpoint = common::get(&mut context, &endpoint).await.unwrap();
+    assert_eq!(migrated.primary_stake, 2 * primary_share);
+    assert_eq!(migrated.secondary_stake, 2 * secondary_share);
+
+    // withdrawing every stake takes out exactly what the endpoint was seeded with
+    for (staker, tokens) in stakers.iter().zip(&staker_tokens) {
+        common::process(
+            &mut context,
+            &[instruction::stake(
+                &program_id,
+                &payer,
+                &staker.pubkey(),
+                tokens,
+                &endpoint,
+                &primary,
+                &secondary,
+                -(stake_amount as i64),
+                None,
+            )],
+            &[staker],
+        )
+        .await;
+    }
+
+    let endpoint: Endpoint = common::get(&mut context, &endpoint).await.unwrap();
+    assert_eq!(endpoint.total_stake, 0);
+    assert_eq!(endpoint.primary_stake, 0);
+    assert_eq!(endpoint.secondary_stake, 0);
+    for address in &beneficiary_addresses {
+        let beneficiary: Beneficiary = common::get(&mut context, address).await.unwrap();
+        assert_eq!(beneficiary.staked, 0);
+    }
+    let settings: Settings = common::get(&mut context, &Settings::program_address(&program_id).0)
+        .await
+        .unwrap();
+    assert_eq!(settings.total_stake, 0);
+}
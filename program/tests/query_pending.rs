@@ -0,0 +1,98 @@
+mod common;
+
+use std::convert::TryInto;
+
+use solana_program::pubkey::Pubkey;
+use solana_program_test::ProgramTestContext;
+use solana_sdk::{
+    signature::{Keypair, Signer},
+    transaction::Transaction,
+};
+use staking::{account::Beneficiary, instruction};
+
+/// The pending rewards of `authority`, read from the return data of a simulated
+/// `QueryPending`
+async fn query_pending(
+    context: &mut ProgramTestContext,
+    program_id: &Pubkey,
+    authority: &Pubkey,
+) -> u64 {
+    let transaction = Transaction::new_signed_with_payer(
+        &[instruction::query_pending(program_id, authority)],
+        Some(&context.payer.pubkey()),
+        &[&context.payer],
+        context.last_blockhash,
+    );
+    let simulation = context
+        .banks_client
+        .simulate_transaction(transaction)
+        .await
+        .unwrap();
+    simulation.result.unwrap().unwrap();
+    let return_data = simulation.simulation_details.unwrap().return_data.unwrap();
+    assert_eq!(return_data.program_id, *program_id);
+    u64::from_le_bytes(return_data.data.try_into().unwrap())
+}
+
+#[tokio::test]
+async fn test_query_pending() {
+    let program_id = Pubkey::new_unique();
+    let upgrade_authority = Keypair::new();
+
+    let (mut context, mint) = common::setup(&program_id, &upgrade_authority).await;
+    let payer = context.payer.pubkey();
+
+    let primary = Pubkey::new_unique();
+    let secondary = Pubkey::new_unique();
+    let endpoint =
+        common::register_endpoint(&mut context, &program_id, &primary, &primary, &secondary).await;
+
+    let staker = Keypair::new();
+    let staker_tokens = common::create_stake(
+        &mut context,
+        &program_id,
+        &mint,
+        &staker,
+        (&endpoint, &primary, &secondary),
+        1_000,
+        1_000,
+    )
+    .await;
+    assert_eq!(
+        query_pending(&mut context, &program_id, &staker.pubkey()).await,
+        0
+    );
+
+    common::fund_reward_pool(&mut context, &program_id, &mint, 1_000_000).await;
+    common::advance_clock(&mut context, 86_400).await;
+
+    // the query does not change anything
+    let pending = query_pending(&mut context, &program_id, &staker.pubkey()).await;
+    assert!(pending > 0);
+    let beneficiary_address = Beneficiary::program_address(&staker.pubkey(), &program_id).0;
+    let beneficiary: Beneficiary = common::get(&mut context, &beneficiary_address)
+        .await
+        .unwrap();
+    assert_eq!(beneficiary.holding, 0);
+
+    // a claim at the same time pays out exactly what was pending
+    common::process(
+        &mut context,
+        &[instruction::claim(
+            &program_id,
+            &payer,
+            &staker.pubkey(),
+            &staker_tokens,
+        )],
+        &[&staker],
+    )
+    .await;
+    assert_eq!(
+        common::token_balance(&mut context, &staker_tokens).await,
+        pending
+    );
+    assert_eq!(
+        query_pending(&mut context, &program_id, &staker.pubkey()).await,
+        0
+    );
+}
@@ -0,0 +1,125 @@
+mod common;
+
+use solana_program::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use staking::{
+    account::{Beneficiary, Endpoint, Split, Stake},
+    error::StakingError,
+    instruction,
+};
+
+#[tokio::test]
+async fn test_rebond() {
+    let program_id = Pubkey::new_unique();
+    let upgrade_authority = Keypair::new();
+
+    let (mut context, mint) = common::setup(&program_id, &upgrade_authority).await;
+    let payer = context.payer.pubkey();
+
+    let primary = Pubkey::new_unique();
+    let secondary = Pubkey::new_unique();
+    let endpoint =
+        common::register_endpoint(&mut context, &program_id, &primary, &primary, &secondary).await;
+
+    let staker = Keypair::new();
+    let staker_tokens = common::create_stake(
+        &mut context,
+        &program_id,
+        &mint,
+        &staker,
+        (&endpoint, &primary, &secondary),
+        2_000,
+        5_000,
+    )
+    .await;
+
+    // unstaking starts a tranche that can be rebonded until it is withdrawn
+    common::process(
+        &mut context,
+        &[instruction::stake(
+            &program_id,
+            &payer,
+            &staker.pubkey(),
+            &staker_tokens,
+            &endpoint,
+            &primary,
+            &secondary,
+            -500,
+            None,
+        )],
+        &[&staker],
+    )
+    .await;
+
+    let stake_address = Stake::program_address(&endpoint, &staker.pubkey(), &program_id).0;
+    let stake: Stake = common::get(&mut context, &stake_address).await.unwrap();
+    assert_eq!(stake.total_stake, 1_500);
+    assert_eq!(stake.unbonding_amount, 500);
+
+    // more than is unbonding
+    let err = common::try_process(
+        &mut context,
+        &[instruction::rebond(
+            &program_id,
+            &payer,
+            &staker.pubkey(),
+            &endpoint,
+            &primary,
+            &secondary,
+            501,
+        )],
+        &[&staker],
+    )
+    .await
+    .unwrap_err();
+    assert_eq!(
+        common::custom_error(err),
+        StakingError::RebondInvalidAmount as u32
+    );
+
+    common::process(
+        &mut context,
+        &[instruction::rebond(
+            &program_id,
+            &payer,
+            &staker.pubkey(),
+            &endpoint,
+            &primary,
+            &secondary,
+            500,
+        )],
+        &[&staker],
+    )
+    .await;
+
+    let stake: Stake = common::get(&mut context, &stake_address).await.unwrap();
+    assert_eq!(stake.total_stake, 2_000);
+    assert_eq!(stake.unbonding_amount, 0);
+    assert!(stake.unbonding.is_empty());
+
+    let endpoint_account: Endpoint = common::get(&mut context, &endpoint).await.unwrap();
+    assert_eq!(endpoint_account.total_stake, 2_000);
+
+    let (staker_share, primary_share, secondary_share) = Split::DEFAULT.apply(2_000);
+    for (authority, share) in [
+        (staker.pubkey(), staker_share),
+        (primary, primary_share),
+        (secondary, secondary_share),
+    ] {
+        let beneficiary: Beneficiary = common::get(
+            &mut context,
+            &Beneficiary::program_address(&authority, &program_id).0,
+        )
+        .await
+        .unwrap();
+        assert_eq!(beneficiary.staked, share);
+    }
+
+    // the tokens never left the stake fund
+    let fund = Stake::fund_address(&endpoint, &staker.pubkey(), &program_id).0;
+    assert_eq!(common::token_balance(&mut context, &fund).await, 2_000);
+    assert_eq!(
+        common::token_balance(&mut context, &staker_tokens).await,
+        3_000
+    );
+}
@@ -0,0 +1,143 @@
+mod common;
+
+use solana_program::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use staking::{
+    account::{Beneficiary, Endpoint, Split, Stake},
+    instruction,
+};
+
+#[tokio::test]
+async fn test_redelegate() {
+    let program_id = Pubkey::new_unique();
+    let upgrade_authority = Keypair::new();
+
+    let (mut context, mint) = common::setup(&program_id, &upgrade_authority).await;
+    let payer = context.payer.pubkey();
+
+    let owner = Keypair::new();
+    let source_primary = Pubkey::new_unique();
+    let source_secondary = Pubkey::new_unique();
+    let source = common::register_endpoint(
+        &mut context,
+        &program_id,
+        &owner.pubkey(),
+        &source_primary,
+        &source_secondary,
+    )
+    .await;
+    let destination_primary = Pubkey::new_unique();
+    let destination_secondary = Pubkey::new_unique();
+    let destination = common::register_endpoint(
+        &mut context,
+        &program_id,
+        &destination_primary,
+        &destination_primary,
+        &destination_secondary,
+    )
+    .await;
+
+    let staker = Keypair::new();
+    let staker_tokens = common::create_stake(
+        &mut context,
+        &program_id,
+        &mint,
+        &staker,
+        (&source, &source_primary, &source_secondary),
+        3_000,
+        3_000,
+    )
+    .await;
+    common::process(
+        &mut context,
+        &[instruction::initialize_stake(
+            &program_id,
+            &payer,
+            &staker.pubkey(),
+            &destination,
+            &mint,
+        )],
+        &[&staker],
+    )
+    .await;
+
+    let redelegate = |amount: u64, sunset_destination: Option<&Pubkey>| {
+        instruction::redelegate(
+            &program_id,
+            &payer,
+            &staker.pubkey(),
+            (&source, &source_primary, &source_secondary),
+            (&destination, &destination_primary, &destination_secondary),
+            amount,
+            sunset_destination,
+        )
+    };
+    common::process(&mut context, &[redelegate(1_000, None)], &[&staker]).await;
+
+    // the stake moves without unbonding
+    for (endpoint, amount) in [(source, 2_000), (destination, 1_000)] {
+        let stake: Stake = common::get(
+            &mut context,
+            &Stake::program_address(&endpoint, &staker.pubkey(), &program_id).0,
+        )
+        .await
+        .unwrap();
+        assert_eq!(stake.total_stake, amount);
+        assert_eq!(stake.unbonding_amount, 0);
+
+        let fund = Stake::fund_address(&endpoint, &staker.pubkey(), &program_id).0;
+        assert_eq!(common::token_balance(&mut context, &fund).await, amount);
+
+        let endpoint: Endpoint = common::get(&mut context, &endpoint).await.unwrap();
+        assert_eq!(endpoint.total_stake, amount);
+    }
+    assert_eq!(common::token_balance(&mut context, &staker_tokens).await, 0);
+
+    // the staker keeps their share, the primary and secondary shares move
+    let (staker_source, primary_source, secondary_source) = Split::DEFAULT.apply(2_000);
+    let (staker_destination, primary_destination, secondary_destination) =
+        Split::DEFAULT.apply(1_000);
+    for (authority, share) in [
+        (staker.pubkey(), staker_source + staker_destination),
+        (source_primary, primary_source),
+        (source_secondary, secondary_source),
+        (destination_primary, primary_destination),
+        (destination_secondary, secondary_destination),
+    ] {
+        let beneficiary: Beneficiary = common::get(
+            &mut context,
+            &Beneficiary::program_address(&authority, &program_id).0,
+        )
+        .await
+        .unwrap();
+        assert_eq!(beneficiary.staked, share);
+    }
+
+    // redelegating the rest out of a sunset endpoint closes it
+    let sunset_destination = Pubkey::new_unique();
+    common::process(
+        &mut context,
+        &[instruction::close_endpoint(
+            &program_id,
+            &payer,
+            &source,
+            &owner.pubkey(),
+            &owner.pubkey(),
+            &sunset_destination,
+            true,
+        )],
+        &[&owner],
+    )
+    .await;
+    common::process(
+        &mut context,
+        &[redelegate(2_000, Some(&sunset_destination))],
+        &[&staker],
+    )
+    .await;
+
+    assert!(!common::exists(&mut context, &source).await);
+    assert!(common::lamports(&mut context, &sunset_destination).await > 0);
+    let fund = Stake::fund_address(&destination, &staker.pubkey(), &program_id).0;
+    assert_eq!(common::token_balance(&mut context, &fund).await, 3_000);
+}